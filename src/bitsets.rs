@@ -6,7 +6,7 @@
 //!
 //! **Note:** "LSB" means "least significant `1` bit".
 
-use board::{Square, Bitboard};
+use board::{Square, Bitboard, Color, WHITE};
 
 
 /// Empty set of squares.
@@ -55,6 +55,12 @@ pub const BB_MAIN_DIAG: Bitboard = 0x8040201008040201;
 /// The squares on the main anti-diagonal (H1-A8).
 pub const BB_MAIN_ANTI_DIAG: Bitboard = 0x0102040810204080;
 
+/// The dark squares (A1, C1, ..., H8).
+pub const BB_DARK_SQUARES: Bitboard = 0xaa55aa55aa55aa55;
+
+/// The light squares (B1, D1, ..., G8).
+pub const BB_LIGHT_SQUARES: Bitboard = !BB_DARK_SQUARES;
+
 
 /// Returns the LSB of a value.
 ///
@@ -399,6 +405,176 @@ pub fn bb_anti_diag(square: Square) -> Bitboard {
 }
 
 
+
+/// Returns the set of squares in front of the given squares, from
+/// the point of view of the given side.
+///
+/// For each square in `bb`, the squares on the same file and closer
+/// to the promotion rank of `color` are included. These are the
+/// squares a pawn of `color` may advance to.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::bitsets::*;
+/// # use alcibiades::squares::*;
+/// # use alcibiades::{WHITE, BLACK};
+/// assert_eq!(front_span(WHITE, 1 << E4), bitboard_from_squares(&[E5, E6, E7, E8]));
+/// assert_eq!(front_span(BLACK, 1 << E4), bitboard_from_squares(&[E3, E2, E1]));
+/// ```
+#[inline]
+pub fn front_span(color: Color, bb: Bitboard) -> Bitboard {
+    if color == WHITE {
+        north_fill(bb) << 8
+    } else {
+        south_fill(bb) >> 8
+    }
+}
+
+
+/// Returns the set of squares that the given squares may attack,
+/// when advanced as pawns of the given side.
+///
+/// These are the squares on the adjacent files in front of each
+/// square in `bb`.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::bitsets::*;
+/// # use alcibiades::squares::*;
+/// # use alcibiades::WHITE;
+/// assert_eq!(attack_span(WHITE, 1 << A6), bitboard_from_squares(&[B7, B8]));
+/// ```
+#[inline]
+pub fn attack_span(color: Color, bb: Bitboard) -> Bitboard {
+    let front = front_span(color, bb);
+    (front & !BB_FILE_A) >> 1 | (front & !BB_FILE_H) << 1
+}
+
+
+/// Returns the set of squares on the same files as the given squares.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::bitsets::*;
+/// # use alcibiades::squares::*;
+/// assert_eq!(file_fill(1 << E4 | 1 << E6 | 1 << H1), BB_FILE_E | BB_FILE_H);
+/// ```
+#[inline]
+pub fn file_fill(bb: Bitboard) -> Bitboard {
+    north_fill(bb) | south_fill(bb)
+}
+
+
+/// A helper function. It adds to `bb` all the squares above the
+/// squares in `bb` (toward rank 8).
+#[inline]
+fn north_fill(mut bb: Bitboard) -> Bitboard {
+    bb |= bb << 8;
+    bb |= bb << 16;
+    bb | bb << 32
+}
+
+
+/// A helper function. It adds to `bb` all the squares below the
+/// squares in `bb` (toward rank 1).
+#[inline]
+fn south_fill(mut bb: Bitboard) -> Bitboard {
+    bb |= bb >> 8;
+    bb |= bb >> 16;
+    bb | bb >> 32
+}
+
+
+/// Returns the set of the given squares.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::bitsets::*;
+/// # use alcibiades::squares::*;
+/// assert_eq!(bitboard_from_squares(&[A1, H1]), 0b10000001);
+/// ```
+pub fn bitboard_from_squares(squares: &[Square]) -> Bitboard {
+    squares.iter().fold(BB_NONE, |bb, &square| {
+        debug_assert!(square <= 63);
+        bb | 1 << square
+    })
+}
+
+
+/// Renders a bitboard as an 8x8 grid.
+///
+/// The first line of the grid is rank 8, and the last line is
+/// rank 1. Each line ends with `'\n'`. Squares that are in the set are
+/// shown as `1`, and the rest as `.`.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::bitsets::*;
+/// assert_eq!(bitboard_to_string(BB_RANK_8 | 1),
+///            "11111111\n\
+///             ........\n\
+///             ........\n\
+///             ........\n\
+///             ........\n\
+///             ........\n\
+///             ........\n\
+///             1.......\n");
+/// ```
+pub fn bitboard_to_string(bb: Bitboard) -> String {
+    let mut s = String::with_capacity(72);
+    for rank in (0..8).rev() {
+        for file in 0..8 {
+            s.push(if bb & 1 << (rank * 8 + file) != 0 { '1' } else { '.' });
+        }
+        s.push('\n');
+    }
+    s
+}
+
+
+/// Parses a grid rendered by `bitboard_to_string`.
+///
+/// Whitespace is ignored, so the grid can be indented. Returns
+/// `None` if the grid is malformed.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::bitsets::*;
+/// # use alcibiades::squares::*;
+/// let bb = bitboard_from_string("........
+///                                ........
+///                                ........
+///                                ........
+///                                ....1...
+///                                ........
+///                                ........
+///                                ........");
+/// assert_eq!(bb, Some(1 << E4));
+/// ```
+pub fn bitboard_from_string(s: &str) -> Option<Bitboard> {
+    let mut bb = BB_NONE;
+    let mut count = 0;
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        if count == 64 {
+            return None;
+        }
+        let square = (7 - count / 8) * 8 + count % 8;
+        match c {
+            '1' => bb |= 1 << square,
+            '.' => (),
+            _ => return None,
+        }
+        count += 1;
+    }
+    if count == 64 { Some(bb) } else { None }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -436,4 +612,55 @@ mod tests {
         assert_eq!(bsf_reset(&mut x), 2);
         assert_eq!(x, 0b1100000);
     }
+
+    #[test]
+    fn spans() {
+        use squares::*;
+        use board::BLACK;
+        let ranks_1_to_3 = BB_RANK_1 | BB_RANK_2 | BB_RANK_3;
+        assert_eq!(front_span(WHITE, 1 << E4), BB_FILE_E & !(ranks_1_to_3 | BB_RANK_4));
+        assert_eq!(front_span(BLACK, 1 << E4), BB_FILE_E & ranks_1_to_3);
+        assert_eq!(front_span(WHITE, 1 << A8 | 1 << H1), BB_FILE_H & !BB_RANK_1);
+        assert_eq!(front_span(WHITE, BB_NONE), BB_NONE);
+        assert_eq!(attack_span(WHITE, 1 << E4),
+                   (BB_FILE_D | BB_FILE_F) & !(ranks_1_to_3 | BB_RANK_4));
+        assert_eq!(attack_span(BLACK, 1 << A3 | 1 << H3),
+                   (BB_FILE_B | BB_FILE_G) & (BB_RANK_1 | BB_RANK_2));
+        assert_eq!(attack_span(BLACK, BB_RANK_1), BB_NONE);
+        assert_eq!(file_fill(1 << E4), BB_FILE_E);
+        assert_eq!(file_fill(BB_RANK_5), BB_ALL);
+        assert_eq!(file_fill(BB_NONE), BB_NONE);
+
+        // A passed pawn: no enemy pawns in front of it or on the
+        // adjacent files in front of it.
+        let white_pawn = 1 << D5;
+        let black_pawns = 1 << A7 | 1 << B6 | 1 << F7;
+        assert_eq!((front_span(WHITE, white_pawn) | attack_span(WHITE, white_pawn)) &
+                   black_pawns,
+                   BB_NONE);
+    }
+
+    #[test]
+    fn bitboard_strings() {
+        use squares::*;
+        for &bb in &[BB_NONE,
+                     BB_ALL,
+                     BB_MAIN_DIAG,
+                     BB_FILE_A | BB_RANK_8,
+                     bitboard_from_squares(&[A1, E4, D5, H8]),
+                     0x0123456789abcdef] {
+            let s = bitboard_to_string(bb);
+            assert_eq!(s.lines().count(), 8);
+            assert_eq!(bitboard_from_string(&s), Some(bb));
+        }
+        assert_eq!(bitboard_from_squares(&[]), BB_NONE);
+        assert_eq!(bitboard_from_squares(&[E4, D5]), 1 << E4 | 1 << D5);
+        assert_eq!(bitboard_to_string(1 << H8).lines().next(), Some(".......1"));
+        assert_eq!(bitboard_to_string(1 << A1).lines().last(), Some("1......."));
+        assert_eq!(bitboard_from_string(""), None);
+        assert_eq!(bitboard_from_string(&"1".repeat(63)), None);
+        assert_eq!(bitboard_from_string(&"1".repeat(65)), None);
+        assert_eq!(bitboard_from_string(&"x".repeat(64)), None);
+        assert_eq!(bitboard_from_string(&"1".repeat(64)), Some(BB_ALL));
+    }
 }
@@ -1,7 +1,9 @@
 //! Defines how the chess board is represented in memory.
 
 use std::fmt;
-use utils::parse_fen;
+use bitsets::*;
+use ranks::*;
+use utils::{parse_fen, parse_castling_rights, ZobristArrays, BoardGeometry, NotationError};
 
 
 /// `WHITE` or `BLACK`.
@@ -10,6 +12,13 @@ pub type Color = usize;
 pub const WHITE: Color = 0;
 pub const BLACK: Color = 1;
 
+/// Returns the opposite color (`WHITE` for `BLACK`, and vice versa).
+#[inline]
+pub fn opposite(color: Color) -> Color {
+    debug_assert!(color <= 1);
+    1 ^ color
+}
+
 
 /// `KING`, `QUEEN`, `ROOK`, `BISHOP`, `KINGHT`, `PAWN` or `PIECE_NONE`.
 pub type PieceType = usize;
@@ -211,7 +220,8 @@ pub struct Board {
     /// The castling rights for both players.
     pub castling_rights: CastlingRights,
 
-    /// If the previous move was a double pawn push, contains pushed
+    /// If the previous move was a double pawn push, and an enemy
+    /// pawn can capture the pushed pawn en passant, contains pushed
     /// pawn's file (a value between 0 and 7). Otherwise contains `8`.
     pub enpassant_file: usize,
 
@@ -229,6 +239,208 @@ impl Board {
         parse_fen(fen).map(|x| x.0)
     }
 
+    /// Creates a new instance from Forsyth–Edwards Notation (FEN),
+    /// also returning the halfmove clock and the fullmove number.
+    ///
+    /// Unlike `from_fen`, this does not discard the counters, which
+    /// are needed for correct handling of the fifty-move rule.
+    pub fn from_fen_full(fen: &str) -> Result<(Board, u8, u16), IllegalBoard> {
+        parse_fen(fen)
+    }
+
+    /// Creates a new instance from an array of squares.
+    ///
+    /// `squares` is indexed by square (0 is A1, 63 is H8), and holds
+    /// the color and the type of the piece standing on each square,
+    /// or `None` for empty squares. `enpassant_file` should be a
+    /// value between 0 and 7, or `8` if there is no en-passant file.
+    ///
+    /// Returns an error if the position is illegal: each player must
+    /// have exactly one king and at most 16 pieces (8 pawns), pawns
+    /// can not stand on the first or the last rank, the castling
+    /// rights and the en-passant file must agree with the piece
+    /// placement, and the side that is not to move must not be in
+    /// check.
+    pub fn from_squares(squares: &[Option<(Color, PieceType)>; 64],
+                        to_move: Color,
+                        castling_rights: CastlingRights,
+                        enpassant_file: usize)
+                        -> Result<Board, NotationError> {
+        if to_move > 1 {
+            return Err(NotationError::InvalidSideToMove);
+        }
+        let mut pieces = PiecesPlacement {
+            piece_type: [0u64; 6],
+            color: [0u64; 2],
+        };
+        for (square, contents) in squares.iter().enumerate() {
+            if let Some((color, piece_type)) = *contents {
+                if color > 1 || piece_type >= PIECE_NONE {
+                    return Err(NotationError::InvalidPiece(square));
+                }
+                pieces.piece_type[piece_type] |= 1 << square;
+                pieces.color[color] |= 1 << square;
+            }
+        }
+        for color in 0..2 {
+            let ours = pieces.color[color];
+            if pop_count(ours & pieces.piece_type[KING]) != 1 {
+                return Err(NotationError::WrongKingCount(color));
+            }
+            if pop_count(ours) > 16 || pop_count(ours & pieces.piece_type[PAWN]) > 8 {
+                return Err(NotationError::TooManyPieces(color));
+            }
+        }
+        const BB_FIRST_AND_LAST_RANKS: Bitboard = 0xff000000000000ff;
+        let misplaced_pawns = pieces.piece_type[PAWN] & BB_FIRST_AND_LAST_RANKS;
+        if misplaced_pawns != 0 {
+            return Err(NotationError::PawnOnBackRank(bsf(misplaced_pawns)));
+        }
+
+        // The castling rights are verified the same way as in FEN.
+        parse_castling_rights(&castling_rights.to_string(), &pieces)?;
+
+        // The pawn that has just made a double push must be there,
+        // and the two squares behind it must be empty.
+        let occupied = pieces.color[WHITE] | pieces.color[BLACK];
+        let them = opposite(to_move);
+        if enpassant_file < 8 {
+            let (pushed_to, passed, pushed_from) = if them == WHITE {
+                (RANK_4, RANK_3, RANK_2)
+            } else {
+                (RANK_5, RANK_6, RANK_7)
+            };
+            let pawn = 1 << Board::square(enpassant_file, pushed_to);
+            let behind = 1 << Board::square(enpassant_file, passed) |
+                         1 << Board::square(enpassant_file, pushed_from);
+            if pawn & pieces.piece_type[PAWN] & pieces.color[them] == 0 ||
+               behind & occupied != 0 {
+                return Err(NotationError::InvalidEnpassantFile(enpassant_file));
+            }
+        } else if enpassant_file > 8 {
+            return Err(NotationError::InvalidEnpassantFile(enpassant_file));
+        }
+
+        // The side to move must not be able to capture the king.
+        let geometry = BoardGeometry::get();
+        let king_square = bsf(pieces.piece_type[KING] & pieces.color[them]);
+        let piece_type = &pieces.piece_type;
+        let attackers = (geometry.attacks_from(ROOK, king_square, occupied) &
+                         (piece_type[ROOK] | piece_type[QUEEN])) |
+                        (geometry.attacks_from(BISHOP, king_square, occupied) &
+                         (piece_type[BISHOP] | piece_type[QUEEN])) |
+                        (geometry.attacks_from(KNIGHT, king_square, occupied) &
+                         piece_type[KNIGHT]) |
+                        (geometry.attacks_from(KING, king_square, occupied) &
+                         piece_type[KING]) |
+                        (geometry.pawn_attacks[them][king_square] & piece_type[PAWN]);
+        if attackers & pieces.color[to_move] != 0 {
+            return Err(NotationError::OpponentInCheck);
+        }
+
+        Ok(Board {
+               occupied,
+               pieces,
+               to_move,
+               castling_rights,
+               enpassant_file,
+           })
+    }
+
+    /// Returns the color and the type of the piece standing on a
+    /// given square, or `None` if the square is empty.
+    #[inline]
+    pub fn piece_on(&self, square: Square) -> Option<(Color, PieceType)> {
+        debug_assert!(square <= 63);
+        let bb = 1 << square & self.occupied;
+        if bb == 0 {
+            return None;
+        }
+        let color = if bb & self.pieces.color[WHITE] != 0 {
+            WHITE
+        } else {
+            BLACK
+        };
+        (KING..PIECE_NONE)
+            .find(|&p| bb & self.pieces.piece_type[p] != 0)
+            .map(|p| (color, p))
+    }
+
+    /// Returns a Zobrist hash value for the pawn structure.
+    ///
+    /// Only the placement of the pawns is taken into account, so
+    /// boards with the same pawns have equal pawn hashes, regardless
+    /// of the other pieces and the side to move.
+    #[inline]
+    pub fn pawn_hash(&self) -> u64 {
+        ZobristArrays::get().pawn_hash(self)
+    }
+
+    /// Returns if none of the players can possibly checkmate.
+    ///
+    /// This is the case when only the kings and a single knight
+    /// remain on the board, or only the kings and some bishops, all
+    /// of them on same-colored squares.
+    pub fn is_insufficient_material(&self) -> bool {
+        let piece_type = &self.pieces.piece_type;
+        if piece_type[QUEEN] | piece_type[ROOK] | piece_type[PAWN] != 0 {
+            return false;
+        }
+        let bishops = piece_type[BISHOP];
+        match piece_type[KNIGHT] {
+            0 => bishops & BB_DARK_SQUARES == 0 || bishops & BB_LIGHT_SQUARES == 0,
+            knights => bishops == 0 && pop_count(knights) == 1,
+        }
+    }
+
+    /// Checks board's internal consistency (debug builds only).
+    ///
+    /// Returns a description of the first found inconsistency. This
+    /// is useful for catching board corruption early. Note that a
+    /// consistent board is not necessarily a legal one.
+    #[cfg(debug_assertions)]
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let color = &self.pieces.color;
+        let piece_type = &self.pieces.piece_type;
+        if self.to_move > 1 {
+            return Err("invalid side to move");
+        }
+        if color[WHITE] & color[BLACK] != 0 {
+            return Err("color bitboards overlap");
+        }
+        if self.occupied != color[WHITE] | color[BLACK] {
+            return Err("occupied squares do not match colors");
+        }
+        let mut union = 0;
+        for bb in piece_type.iter() {
+            if union & bb != 0 {
+                return Err("piece type bitboards overlap");
+            }
+            union |= *bb;
+        }
+        if union != self.occupied {
+            return Err("occupied squares do not match piece types");
+        }
+        if (piece_type[KING] & color[WHITE]).count_ones() != 1 ||
+           (piece_type[KING] & color[BLACK]).count_ones() != 1 {
+            return Err("each side must have exactly one king");
+        }
+        match self.enpassant_file {
+            8 => (),
+            file if file < 8 => {
+                let (pawn_rank, target_rank) = if self.to_move == WHITE { (4, 5) } else { (3, 2) };
+                let pawn_bb = 1 << Board::square(file, pawn_rank);
+                let target_bb = 1 << Board::square(file, target_rank);
+                if pawn_bb & piece_type[PAWN] & color[opposite(self.to_move)] == 0 ||
+                   target_bb & self.occupied != 0 {
+                    return Err("invalid en-passant file");
+                }
+            }
+            _ => return Err("invalid en-passant file"),
+        }
+        Ok(())
+    }
+
     /// Returns the square on given file and rank.
     ///
     /// * `file` should be a number between 0 and 7 (0 is file A, 7 is file H).
@@ -265,6 +477,96 @@ mod tests {
     use super::*;
     use squares::*;
 
+    #[test]
+    fn opposite() {
+        assert_eq!(super::opposite(WHITE), BLACK);
+        assert_eq!(super::opposite(BLACK), WHITE);
+    }
+
+    #[test]
+    fn is_insufficient_material() {
+        let draws = ["8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+                     "8/8/4k3/8/8/3K4/8/6n1 w - - 0 1",
+                     "8/8/4k3/8/8/3K4/8/5B2 b - - 0 1",
+                     "8/1b6/4k3/8/8/3K4/8/5B2 w - - 0 1",
+                     "b7/8/4k3/8/8/3K1B2/8/3B4 w - - 0 1"];
+        for fen in draws.iter() {
+            assert!(Board::from_fen(fen).ok().unwrap().is_insufficient_material(), "{}", fen);
+        }
+        let non_draws = ["8/8/4k3/8/8/3K4/8/5NN1 w - - 0 1",
+                         "8/8/4k3/8/8/3K4/8/5BN1 w - - 0 1",
+                         "8/2b5/4k3/8/8/3K4/8/5B2 w - - 0 1",
+                         "8/2n5/4k3/8/8/3K4/8/6N1 w - - 0 1",
+                         "8/8/4k3/8/8/3K4/7p/8 w - - 0 1",
+                         "8/8/4k3/8/8/3K4/8/7R w - - 0 1"];
+        for fen in non_draws.iter() {
+            assert!(!Board::from_fen(fen).ok().unwrap().is_insufficient_material(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn from_fen_full() {
+        let (b, halfmove_clock, fullmove_number) =
+            Board::from_fen_full("4k3/8/8/8/8/8/8/4K2R b K - 30 40").ok().unwrap();
+        assert_eq!(halfmove_clock, 30);
+        assert_eq!(fullmove_number, 40);
+        assert_eq!(b.to_move, BLACK);
+        assert_eq!(b.piece_on(H1), Some((WHITE, ROOK)));
+        assert!(Board::from_fen_full("4k3/8/8/8/8/8/8/4K2R b K - 30").is_err());
+    }
+
+    #[test]
+    fn piece_on() {
+        let b = Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+            .ok()
+            .unwrap();
+        assert_eq!(b.piece_on(A1), Some((WHITE, ROOK)));
+        assert_eq!(b.piece_on(E1), Some((WHITE, KING)));
+        assert_eq!(b.piece_on(D8), Some((BLACK, QUEEN)));
+        assert_eq!(b.piece_on(F3), Some((WHITE, KNIGHT)));
+        assert_eq!(b.piece_on(E5), Some((BLACK, PAWN)));
+        assert_eq!(b.piece_on(C8), Some((BLACK, BISHOP)));
+        assert_eq!(b.piece_on(G1), None);
+        assert_eq!(b.piece_on(E2), None);
+        assert_eq!(b.piece_on(D4), None);
+        assert_eq!(b.piece_on(H6), None);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn validate() {
+        let fen = "r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1";
+        assert!(Board::from_fen(fen).ok().unwrap().validate().is_ok());
+
+        let mut b = Board::from_fen(fen).ok().unwrap();
+        b.occupied ^= 1 << A1;
+        assert!(b.validate().is_err());
+
+        let mut b = Board::from_fen(fen).ok().unwrap();
+        b.pieces.piece_type[QUEEN] |= 1 << A1;
+        assert!(b.validate().is_err());
+
+        let mut b = Board::from_fen(fen).ok().unwrap();
+        b.pieces.color[BLACK] |= 1 << A1;
+        assert!(b.validate().is_err());
+
+        let mut b = Board::from_fen(fen).ok().unwrap();
+        b.pieces.piece_type[KING] ^= 1 << E1 | 1 << E2;
+        b.pieces.color[WHITE] ^= 1 << E1 | 1 << E2;
+        b.occupied ^= 1 << E1 | 1 << E2;
+        assert!(b.validate().is_ok());
+        b.pieces.piece_type[KING] ^= 1 << E2 | 1 << A2;
+        b.pieces.color[WHITE] ^= 1 << A2;
+        b.occupied ^= 1 << A2;
+        assert!(b.validate().is_err());
+
+        let mut b = Board::from_fen(fen).ok().unwrap();
+        b.enpassant_file = 4;
+        assert!(b.validate().is_err());
+        b.enpassant_file = 9;
+        assert!(b.validate().is_err());
+    }
+
     #[test]
     fn castling_rights() {
         let mut c = CastlingRights::new(0b1110);
@@ -282,4 +584,65 @@ mod tests {
         assert_eq!(c.grant(BLACK, KINGSIDE), false);
         assert_eq!(c.value(), 0b1110);
     }
+
+    #[test]
+    fn from_squares() {
+        const BACK_RANK: [PieceType; 8] = [ROOK, KNIGHT, BISHOP, QUEEN, KING, BISHOP, KNIGHT, ROOK];
+        let mut squares = [None; 64];
+        for file in 0..8 {
+            squares[Board::square(file, 0)] = Some((WHITE, BACK_RANK[file]));
+            squares[Board::square(file, 1)] = Some((WHITE, PAWN));
+            squares[Board::square(file, 6)] = Some((BLACK, PAWN));
+            squares[Board::square(file, 7)] = Some((BLACK, BACK_RANK[file]));
+        }
+        let b = Board::from_squares(&squares, WHITE, CastlingRights::new(0b1111), 8)
+            .ok()
+            .unwrap();
+        let f = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .ok()
+            .unwrap();
+        assert_eq!(b.pieces.piece_type, f.pieces.piece_type);
+        assert_eq!(b.pieces.color, f.pieces.color);
+        assert_eq!(b.to_move, f.to_move);
+        assert_eq!(b.castling_rights.value(), f.castling_rights.value());
+        assert_eq!(b.enpassant_file, f.enpassant_file);
+        assert_eq!(b.occupied, f.occupied);
+
+        let error = |squares: &[Option<(Color, PieceType)>; 64], to_move, castling, enpassant| {
+            Board::from_squares(squares, to_move, CastlingRights::new(castling), enpassant).err()
+        };
+        assert_eq!(error(&squares, 2, 0, 8), Some(NotationError::InvalidSideToMove));
+        assert_eq!(error(&squares, WHITE, 0, 9), Some(NotationError::InvalidEnpassantFile(9)));
+        assert_eq!(error(&squares, WHITE, 0, 4), Some(NotationError::InvalidEnpassantFile(4)));
+        squares[E4] = Some((WHITE, PAWN));
+        squares[E2] = None;
+        assert!(error(&squares, BLACK, 0, 4).is_none());
+        squares[E3] = squares[G1].take();
+        assert_eq!(error(&squares, BLACK, 0, 4), Some(NotationError::InvalidEnpassantFile(4)));
+        squares[G1] = squares[E3].take();
+        squares[E3] = Some((WHITE, PAWN));
+        assert_eq!(error(&squares, BLACK, 0, 8), Some(NotationError::TooManyPieces(WHITE)));
+        squares[E3] = None;
+        squares[E2] = Some((WHITE, PAWN));
+        squares[E4] = None;
+        squares[A8] = None;
+        assert_eq!(error(&squares, WHITE, 0b1111, 8),
+                   Some(NotationError::MissingCastlingRook('q')));
+        squares[A8] = Some((BLACK, ROOK));
+        squares[E1] = None;
+        assert_eq!(error(&squares, WHITE, 0, 8), Some(NotationError::WrongKingCount(WHITE)));
+        squares[E1] = Some((WHITE, KING));
+        squares[A1] = squares[A2].take();
+        assert_eq!(error(&squares, WHITE, 0, 8), Some(NotationError::PawnOnBackRank(A1)));
+        squares[A1] = Some((2, ROOK));
+        assert_eq!(error(&squares, WHITE, 0, 8), Some(NotationError::InvalidPiece(A1)));
+
+        // The side to move can capture the king.
+        let mut squares = [None; 64];
+        squares[E1] = Some((WHITE, KING));
+        squares[E8] = Some((BLACK, KING));
+        squares[E5] = Some((WHITE, ROOK));
+        assert!(error(&squares, BLACK, 0, 8).is_none());
+        assert_eq!(error(&squares, WHITE, 0, 8), Some(NotationError::OpponentInCheck));
+    }
 }
@@ -15,6 +15,10 @@ use ttable::*;
 use moves::Move;
 use search_node::SearchNode;
 use time_manager::{TimeManager, RemainingTime};
+use board::*;
+use bitsets::pop_count;
+use move_generator::MoveGenerator;
+use utils::{self, PolyglotBook};
 
 
 struct SearchStatus {
@@ -25,6 +29,16 @@ struct SearchStatus {
 
     // The duration of the search in milliseconds.
     pub duration_millis: u64,
+
+    // How many times the best move has changed during the search.
+    pub best_move_changes: usize,
+
+    // The number of consecutive completed depths for which neither
+    // the best move, nor its value have changed noticeably.
+    pub stable_depths: usize,
+
+    // The value of the best move at the last completed depth.
+    pub pv_value: Value,
 }
 
 impl Default for SearchStatus {
@@ -35,6 +49,9 @@ impl Default for SearchStatus {
             value: VALUE_UNKNOWN,
             searched_nodes: 0,
             duration_millis: 0,
+            best_move_changes: 0,
+            stable_depths: 0,
+            pv_value: VALUE_UNKNOWN,
         }
     }
 }
@@ -80,8 +97,19 @@ struct Engine<S, T>
     // Whether the engine is thinking in pondering mode at the moment.
     is_pondering: bool,
 
+    // Whether the current/last search is in a closed position (see
+    // `is_closed_position`).
+    is_closed: bool,
+
     // Tells the engine when it must stop thinking and play the best move.
     play_when: PlayWhen<S, T>,
+
+    // The transposition table size requested during the current
+    // search. The table will be re-sized when the search is done.
+    pending_hash_size: Option<usize>,
+
+    // The opening book loaded from the "BookFile" option.
+    book: Option<PolyglotBook>,
 }
 
 impl<S, T> UciEngine for Engine<S, T>
@@ -101,10 +129,24 @@ impl<S, T> UciEngine for Engine<S, T>
         let mut options = vec![("Hash",
                                 OptionDescription::Spin {
                                     min: 1,
-                                    max: 64 * 1024,
+                                    max: HASH_SIZE_MAX_MB as i32,
                                     default: 16,
                                 }),
-                               ("Clear Hash", OptionDescription::Button)];
+                               ("Clear Hash", OptionDescription::Button),
+                               ("Centipawn Scale",
+                                OptionDescription::Spin {
+                                    min: 1,
+                                    max: 1000,
+                                    default: 100,
+                                }),
+                               ("Random Seed",
+                                OptionDescription::Spin {
+                                    min: 0,
+                                    max: i32::MAX,
+                                    default: 0,
+                                }),
+                               ("OwnBook", OptionDescription::Check { default: false }),
+                               ("BookFile", OptionDescription::String { default: String::new() })];
         options.extend(S::options());
         options.extend(T::options());
 
@@ -159,8 +201,18 @@ impl<S, T> UciEngine for Engine<S, T>
         options_dedup
     }
 
-    fn new(tt_size_mb: Option<usize>) -> Engine<S, T> {
+    fn new(options: &[(&str, &str)]) -> Engine<S, T> {
         const START_FEN: &'static str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w QKqk - 0 1";
+        let is_hash = |name: &str| name.eq_ignore_ascii_case("Hash");
+
+        // The transposition table is allocated only once, with the
+        // last given size. Never try to allocate more than the
+        // maximum for the "Hash" option.
+        let tt_size_mb = options.iter()
+            .rev()
+            .find(|o| is_hash(o.0))
+            .and_then(|o| o.1.parse::<usize>().ok())
+            .map(|x| x.clamp(1, HASH_SIZE_MAX_MB));
         let tt = Arc::new(S::Ttable::new(tt_size_mb));
         let started_at = SystemTime::now();
         let mut engine = Engine {
@@ -179,21 +231,24 @@ impl<S, T> UciEngine for Engine<S, T>
             nps_stats: (0, 0, 0),
             silent_since: started_at,
             is_pondering: false,
+            is_closed: false,
             play_when: PlayWhen::Never(PhantomData),
+            pending_hash_size: None,
+            book: None,
         };
 
-        // Set correct value for the "Hash" option.
         if let Some(v) = tt_size_mb {
-            ::CONFIGURATION
-                .write()
-                .unwrap()
-                .insert("Hash", format!("{}", v));
+            engine.update_hash_option(v);
         }
 
-        // Issue a "setoption" command for each changed default.
+        // Issue a "setoption" command for each changed default, and
+        // then for each option set by the GUI.
         for o in CHANGED_DEFAULTS.read().unwrap().iter() {
             engine.set_option(o.0, o.1);
         }
+        for &(name, value) in options.iter().filter(|o| !is_hash(o.0)) {
+            engine.set_option(name, value);
+        }
 
         engine
     }
@@ -212,12 +267,27 @@ impl<S, T> UciEngine for Engine<S, T>
         };
         match name {
             "Hash" => {
-                // We do not support re-sizing of the transposition
-                // table once the engine has been started.
+                // The transposition table is shared with the search
+                // threads, so it can be re-sized only between
+                // searches. The entries are not preserved.
+                if let Ok(v) = value.parse::<usize>() {
+                    let v = v.clamp(1, HASH_SIZE_MAX_MB);
+                    if self.status.done {
+                        self.resize_tt(v);
+                    } else {
+                        self.pending_hash_size = Some(v);
+                    }
+                }
             }
             "Clear Hash" => {
                 self.tt.clear();
             }
+            "BookFile" => {
+                // A missing or unreadable file leaves the engine
+                // without an opening book.
+                self.book = PolyglotBook::open(value).ok();
+                *::CONFIGURATION.write().unwrap().get_mut(name).unwrap() = value.to_string();
+            }
             _ => {
                 S::set_option(name, value);
                 T::set_option(name, value);
@@ -227,10 +297,15 @@ impl<S, T> UciEngine for Engine<S, T>
     }
 
     fn new_game(&mut self) {
-        self.tt.clear();
+        self.tt.soft_clear();
+        self.searcher.new_game();
     }
 
     fn position(&mut self, fen: &str, moves: &mut dyn Iterator<Item = &str>) {
+        // Note that the transposition table is not cleared here. The
+        // entries stored by previous searches in the same game are
+        // very likely to be useful for the new position too, and
+        // `Ttable::new_search` will make them age out gracefully.
         if let Ok(p) = S::SearchNode::from_history(fen, moves) {
             self.position = p;
         }
@@ -240,9 +315,11 @@ impl<S, T> UciEngine for Engine<S, T>
         self.terminate();
 
         // Validate `params.searchmoves`.
+        let legal_move_count;
         let searchmoves = {
             let mut moves = vec![];
             let legal_moves = self.position.legal_moves();
+            legal_move_count = legal_moves.len();
             if !params.searchmoves.is_empty() {
                 let mut v = params.searchmoves.clone();
                 v.sort();
@@ -255,10 +332,27 @@ impl<S, T> UciEngine for Engine<S, T>
             if moves.is_empty() { legal_moves } else { moves }
         };
 
-        // Start a new search.
-        let depth = params
-            .depth
-            .map_or(DEPTH_MAX, |x| min(x, DEPTH_MAX as u64) as Depth);
+        // Play the book move at once, if there is one. (When
+        // pondering or searching infinitely, the best move must not
+        // be sent before "stop" is received.)
+        if !params.ponder && !params.infinite {
+            if let Some(m) = self.book_move(&searchmoves) {
+                self.queue
+                    .push_back(EngineReply::BestMove {
+                                   best_move: m.notation(),
+                                   ponder_move: None,
+                               });
+                return;
+            }
+        }
+
+        // Start a new search. Note that `params.infinite` overrides
+        // all other search limits -- the engine searches until
+        // `stop` is received.
+        let depth = match params.depth {
+            Some(x) if !params.infinite => min(x, DEPTH_MAX as u64) as Depth,
+            _ => DEPTH_MAX,
+        };
         let remaining_time = RemainingTime {
             white_millis: params.wtime.unwrap_or(300_000),
             black_millis: params.btime.unwrap_or(300_000),
@@ -276,6 +370,7 @@ impl<S, T> UciEngine for Engine<S, T>
         self.nps_stats = (self.nps_stats.0, 0, 0);
         self.silent_since = self.started_at;
         self.is_pondering = params.ponder;
+        self.is_closed = is_closed_position(self.position.board(), legal_move_count);
         self.play_when = if params.infinite {
             PlayWhen::Never(PhantomData)
         } else if params.movetime.is_some() {
@@ -289,6 +384,49 @@ impl<S, T> UciEngine for Engine<S, T>
         } else {
             PlayWhen::TimeManagement(T::new(&self.position, &remaining_time))
         };
+
+        // When there is only one legal move, there is no point in
+        // thinking long. We still do a shallow search though, so as
+        // to find a good ponder move.
+        if legal_move_count == 1 {
+            match self.play_when {
+                PlayWhen::TimeManagement(_) |
+                PlayWhen::MoveTime(_) => {
+                    self.play_when = PlayWhen::Depth(min(depth, FORCED_MOVE_DEPTH));
+                }
+                _ => (),
+            }
+        }
+
+        // On very fast time controls the search should check for
+        // termination more often.
+        let millis_per_move = match self.play_when {
+            PlayWhen::MoveTime(t) => t,
+            PlayWhen::TimeManagement(_) => {
+                let (t, inc) = if self.position.board().to_move == WHITE {
+                    (remaining_time.white_millis, remaining_time.winc_millis)
+                } else {
+                    (remaining_time.black_millis, remaining_time.binc_millis)
+                };
+                t / remaining_time.movestogo.unwrap_or(40) + inc
+            }
+            _ => u64::MAX,
+        };
+        let report_interval = if millis_per_move < FAST_MOVE_MILLIS {
+            Some(1000)
+        } else {
+            None
+        };
+
+        // The options that affect the search are read once, at the
+        // beginning of the search.
+        let (contempt, analyse_mode, threads) = {
+            let configuration = ::CONFIGURATION.read().unwrap();
+            (configuration.get("Contempt").and_then(|s| s.parse().ok()).unwrap_or(0),
+             configuration.get("UCI_AnalyseMode").map(String::as_str) == Some("true"),
+             configuration.get("Threads").and_then(|s| s.parse().ok()).map_or(1, |n| max(1, n)))
+        };
+
         self.searcher
             .start_search(SearchParams {
                               search_id: 0,
@@ -297,9 +435,26 @@ impl<S, T> UciEngine for Engine<S, T>
                               lower_bound: VALUE_MIN,
                               upper_bound: VALUE_MAX,
                               searchmoves: searchmoves,
+                              report_interval,
+                              contempt,
+                              analyse_mode,
+                              threads,
                           });
     }
 
+    fn perft(&mut self, depth: u8) -> Vec<(String, u64)> {
+        // The search node would stop at repeated and rule-50
+        // positions, so its move generator is used directly.
+        let board = self.position.board().clone();
+        let mut position = <S::SearchNode as SearchNode>::MoveGenerator::from_board(board)
+            .ok()
+            .unwrap();
+        utils::perft_divide(&mut position, depth as Depth)
+            .into_iter()
+            .map(|(m, nodes)| (m.notation(), nodes))
+            .collect()
+    }
+
     fn ponder_hit(&mut self) {
         if self.status.done {
             self.queue_best_move();
@@ -309,6 +464,12 @@ impl<S, T> UciEngine for Engine<S, T>
     }
 
     fn stop(&mut self) {
+        // If the search has just been started, we let it complete
+        // the first iteration, so that we always have a properly
+        // searched best move to play.
+        while !self.status.done && self.best_line.is_empty() {
+            self.wait_status_update(Duration::from_millis(1000));
+        }
         self.terminate();
         self.queue_best_move();
     }
@@ -349,6 +510,62 @@ impl<S, T> Engine<S, T>
     where S: DeepeningSearch<ReportData = Vec<Variation>>,
           T: TimeManager<S>
 {
+    /// A helper method. It returns the opening book move for the
+    /// current position, if the "OwnBook" option is on and the move
+    /// is one of `moves`.
+    fn book_move(&self, moves: &[Move]) -> Option<Move> {
+        if ::get_option("OwnBook") != "true" {
+            return None;
+        }
+        let book = self.book.as_ref()?;
+        let board = self.position.board();
+        let digest = book.best_move(utils::polyglot_key(board), board)?;
+        moves.iter().find(|m| m.digest() == digest).cloned()
+    }
+
+    /// A helper method. It re-sizes the transposition table.
+    fn resize_tt(&mut self, size_mb: usize) {
+        // The searcher holds references to the table. Replacing it
+        // with a searcher that works with a minimal placeholder table
+        // releases them, so that the table can be re-sized in place.
+        self.searcher = S::new(Arc::new(S::Ttable::new(Some(0))));
+        match Arc::get_mut(&mut self.tt) {
+            Some(tt) => tt.resize(Some(size_mb)),
+            None => self.tt = Arc::new(S::Ttable::new(Some(size_mb))),
+        }
+        self.searcher = S::new(self.tt.clone());
+        self.update_hash_option(size_mb);
+    }
+
+    /// A helper method. It sets the correct value for the "Hash"
+    /// option after the transposition table has been allocated. If
+    /// there was not enough memory for the requested size, it warns
+    /// the GUI.
+    fn update_hash_option(&mut self, mut size_mb: usize) {
+        if let Some(actual) = self.tt.size_mb() {
+            // The table may round the requested size. Only a table
+            // smaller than that is a fallback for lack of memory.
+            if actual < S::Ttable::rounded_size_mb(size_mb) {
+                size_mb = actual;
+                let item = InfoItem {
+                    info_type: "string".to_string(),
+                    data: format!("hash size reduced to {} MB", size_mb),
+                };
+                self.queue.push_back(EngineReply::Info(vec![item]));
+            }
+        }
+        ::CONFIGURATION
+            .write()
+            .unwrap()
+            .insert("Hash", format!("{}", size_mb));
+    }
+
+    /// A helper method. It returns whether the search is in a closed
+    /// position, and has settled on the best move and its value.
+    fn is_settled(&self) -> bool {
+        self.is_closed && self.status.stable_depths >= STABLE_DEPTHS
+    }
+
     fn queue_progress_info(&mut self) {
         let SearchStatus {
             ref depth,
@@ -356,34 +573,40 @@ impl<S, T> Engine<S, T>
             ref duration_millis,
             ..
         } = self.status;
-        self.queue
-            .push_back(EngineReply::Info(vec![InfoItem {
-                                                  info_type: "depth".to_string(),
-                                                  data: format!("{}", depth),
-                                              },
-                                              InfoItem {
-                                                  info_type: "time".to_string(),
-                                                  data: format!("{}", duration_millis),
-                                              },
-                                              InfoItem {
-                                                  info_type: "nodes".to_string(),
-                                                  data: format!("{}", searched_nodes),
-                                              },
-                                              InfoItem {
-                                                  info_type: "nps".to_string(),
-                                                  data: format!("{}", self.nps_stats.0),
-                                              }]));
+        let mut items = vec![InfoItem {
+                                 info_type: "depth".to_string(),
+                                 data: format!("{}", depth),
+                             },
+                             InfoItem {
+                                 info_type: "time".to_string(),
+                                 data: format!("{}", duration_millis),
+                             },
+                             InfoItem {
+                                 info_type: "nodes".to_string(),
+                                 data: format!("{}", searched_nodes),
+                             },
+                             InfoItem {
+                                 info_type: "nps".to_string(),
+                                 data: format!("{}", self.nps_stats.0),
+                             }];
+        if let Some(permill) = self.tt.usage() {
+            items.push(InfoItem {
+                           info_type: "hashfull".to_string(),
+                           data: format!("{}", permill),
+                       });
+        }
+        self.queue.push_back(EngineReply::Info(items));
     }
 
     fn queue_pv(&mut self, variations: &Vec<Variation>) {
-        fn suffix(bound: BoundType) -> &'static str {
-            match bound {
-                BOUND_UPPER => " upperbound",
-                BOUND_LOWER => " lowerbound",
-                BOUND_EXACT => "",
-                _ => panic!("unexpected bound type"),
-            }
-        }
+        // The engine's evaluation may use finer units than
+        // centipawns. "Centipawn Scale" tells how many internal units
+        // correspond to one pawn, so that standard centipawns can be
+        // sent to the GUI.
+        let scale: isize = max(1,
+                               ::get_option("Centipawn Scale")
+                                   .parse()
+                                   .unwrap_or(100));
 
         let SearchStatus {
             ref depth,
@@ -397,19 +620,12 @@ impl<S, T> Engine<S, T>
                   value,
                   bound,
               }) in variations.iter().enumerate() {
-            let score = match value {
-                v if bound & BOUND_UPPER != 0 && VALUE_MIN < v && v < VALUE_EVAL_MIN => {
-                    format!("mate {}", (VALUE_MIN - v - 1) / 2)
-                }
-                v if bound & BOUND_LOWER != 0 && VALUE_EVAL_MAX < v && v < VALUE_MAX => {
-                    format!("mate {}", (VALUE_MAX - v + 1) / 2)
-                }
-                v if v <= -9999 => format!("cp -9999{}", suffix(bound | BOUND_LOWER)),
-                v if v >= 9999 => format!("cp 9999{}", suffix(bound | BOUND_UPPER)),
-                v => format!("cp {}{}", v, suffix(bound)),
-            };
+            let score = format_score(value, bound, scale);
             let mut pv = String::new();
-            for m in moves.iter().take(max(0, *depth) as usize) {
+            for m in moves
+                    .iter()
+                    .take(max(0, *depth) as usize)
+                    .take_while(|m| !m.is_null()) {
                 pv.push_str(&m.notation());
                 pv.push(' ');
             }
@@ -453,6 +669,9 @@ impl<S, T> Engine<S, T>
             // empty, we fall back to using the stored one.
             best_line = &self.best_line;
         };
+
+        // Null moves are never sent to the GUI.
+        let best_line = &best_line[..best_line.iter().take_while(|m| !m.is_null()).count()];
         let best_move = if let Some(m) = best_line.get(0) {
             m.notation()
         } else {
@@ -487,11 +706,17 @@ impl<S, T> Engine<S, T>
         if !received_report && !self.status.done {
             self.inform_time_manager(None);
         }
+        if self.status.done {
+            if let Some(v) = self.pending_hash_size.take() {
+                self.resize_tt(v);
+            }
+        }
     }
 
     fn inform_time_manager(&mut self, report: Option<&SearchReport<Vec<Variation>>>) {
+        let is_settled = self.is_settled();
         if let PlayWhen::TimeManagement(ref mut tm) = self.play_when {
-            if tm.must_play(&mut self.searcher, report) && !self.is_pondering {
+            if (tm.must_play(&mut self.searcher, report) || is_settled) && !self.is_pondering {
                 self.searcher.send_message("TERMINATE");
             }
         }
@@ -501,6 +726,7 @@ impl<S, T> Engine<S, T>
         assert!(!self.status.done);
         assert!(report.depth >= self.status.depth);
         assert!(report.searched_nodes >= self.status.searched_nodes);
+        let previous_depth = self.status.depth;
         let zero_millis = Duration::from_millis(0);
         let duration_millis = {
             let d = self.started_at.elapsed().unwrap_or(zero_millis);
@@ -512,6 +738,9 @@ impl<S, T> Engine<S, T>
             value: report.value,
             searched_nodes: report.searched_nodes,
             duration_millis: duration_millis,
+            best_move_changes: self.status.best_move_changes,
+            stable_depths: self.status.stable_depths,
+            pv_value: self.status.pv_value,
         };
 
         // Update `self.nps_stats` every 1000 milliseconds.
@@ -523,8 +752,46 @@ impl<S, T> Engine<S, T>
 
         // If principal variations are provided with the report, show them.
         if !report.data.is_empty() {
+            let best_move_changed = match (self.best_line.first(), report.best_move()) {
+                (Some(old), Some(new)) => !old.same_move(&new),
+                _ => false,
+            };
+            if report.depth > previous_depth {
+                let value = report.data[0].value;
+                let is_stable = !best_move_changed && !self.best_line.is_empty() &&
+                                (value as isize - self.status.pv_value as isize).abs() <=
+                                STABLE_VALUE_MARGIN as isize;
+                self.status.stable_depths = if is_stable {
+                    self.status.stable_depths + 1
+                } else {
+                    0
+                };
+                self.status.pv_value = value;
+                if self.is_closed && self.status.stable_depths == STABLE_DEPTHS {
+                    // The position is closed and the search has
+                    // settled. Deeper searches will most probably
+                    // only shuffle the pieces around, so we tell the
+                    // GUI.
+                    self.queue
+                        .push_back(EngineReply::Info(vec![InfoItem {
+                                                              info_type: "string".to_string(),
+                                                              data: "stable".to_string(),
+                                                          }]));
+                }
+            }
             self.best_line = report.data[0].moves.clone();
             self.queue_pv(&report.data);
+            if best_move_changed {
+                // Tell the GUI how stable the principal variation is.
+                self.status.best_move_changes += 1;
+                self.queue
+                    .push_back(EngineReply::Info(vec![InfoItem {
+                                                          info_type: "string".to_string(),
+                                                          data: format!("pvstability {}",
+                                                                        self.status
+                                                                            .best_move_changes),
+                                                      }]));
+            }
             self.silent_since = SystemTime::now();
         }
 
@@ -601,6 +868,56 @@ pub fn run_uci<S, T>(name: &'static str,
 }
 
 
+/// Formats the data for the "score" UCI info item.
+///
+/// `value` is the engine's evaluation, `bound` tells if it is exact,
+/// a lower bound, or an upper bound, and `scale` tells how many
+/// internal units correspond to one pawn. Values that designate
+/// inevitable checkmate are reported as `"mate <moves>"` (negative
+/// when the engine is getting mated), all other values are reported
+/// as `"cp <centipawns>"`.
+fn format_score(value: Value, bound: BoundType, scale: isize) -> String {
+    fn suffix(bound: BoundType) -> &'static str {
+        match bound {
+            BOUND_UPPER => " upperbound",
+            BOUND_LOWER => " lowerbound",
+            BOUND_EXACT => "",
+            _ => panic!("unexpected bound type"),
+        }
+    }
+
+    match value {
+        v if bound & BOUND_UPPER != 0 && VALUE_MIN < v && v < VALUE_EVAL_MIN => {
+            format!("mate {}", (VALUE_MIN - v - 1) / 2)
+        }
+        v if bound & BOUND_LOWER != 0 && VALUE_EVAL_MAX < v && v < VALUE_MAX => {
+            format!("mate {}", (VALUE_MAX - v + 1) / 2)
+        }
+        v => {
+            match v as isize * 100 / scale {
+                cp if cp <= -9999 => format!("cp -9999{}", suffix(bound | BOUND_LOWER)),
+                cp if cp >= 9999 => format!("cp 9999{}", suffix(bound | BOUND_UPPER)),
+                cp => format!("cp {}{}", cp, suffix(bound)),
+            }
+        }
+    }
+}
+
+
+/// Returns whether a position is closed.
+///
+/// A position is closed if at least `CLOSED_LOCKED_PAWNS` pairs of
+/// pawns block each other, and the side to move has no more than
+/// `CLOSED_MOBILITY` legal moves. In such positions (fortresses, for
+/// example) the search often finds nothing better than shuffling the
+/// pieces around.
+fn is_closed_position(board: &Board, legal_move_count: usize) -> bool {
+    let pawns = board.pieces.piece_type[PAWN];
+    let locked = (pawns & board.pieces.color[WHITE]) << 8 & pawns & board.pieces.color[BLACK];
+    pop_count(locked) >= CLOSED_LOCKED_PAWNS && legal_move_count <= CLOSED_MOBILITY
+}
+
+
 struct EngineInfo {
     name: &'static str,
     author: &'static str,
@@ -608,6 +925,40 @@ struct EngineInfo {
 }
 
 
+/// The search depth for positions with only one legal move.
+const FORCED_MOVE_DEPTH: Depth = 4;
+
+
+/// The minimum number of pawn pairs that block each other in a
+/// closed position.
+const CLOSED_LOCKED_PAWNS: usize = 3;
+
+
+/// The maximum number of legal moves in a closed position.
+const CLOSED_MOBILITY: usize = 16;
+
+
+/// In closed positions, the search is considered settled after this
+/// many consecutive completed depths with the same best move and
+/// (nearly) the same value.
+const STABLE_DEPTHS: usize = 6;
+
+
+/// The maximum change of the value between two consecutive depths,
+/// for which the search is considered stable.
+const STABLE_VALUE_MARGIN: Value = 16;
+
+
+/// The maximum size of the transposition table in Mbytes (the maximum
+/// for the "Hash" option).
+const HASH_SIZE_MAX_MB: usize = 64 * 1024;
+
+
+/// Time controls that leave less than this number of milliseconds per
+/// move are considered very fast.
+const FAST_MOVE_MILLIS: u64 = 1000;
+
+
 lazy_static! {
     static ref ENGINE: Mutex<Option<EngineInfo>> = Mutex::new(None);
     static ref CHANGED_DEFAULTS: RwLock<Vec<(&'static str, &'static str)>> = RwLock::new(vec![]);
@@ -644,3 +995,613 @@ impl OptionDescription {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+    use super::*;
+    use stock::*;
+
+    type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
+    type S = Deepening<SimpleSearch<StdTtable<StdTtableEntry>, P>>;
+
+    fn new_engine(options: &[(&str, &str)]) -> Engine<S, StdTimeManager> {
+        *ENGINE.lock().unwrap() = Some(EngineInfo {
+                                           name: "test",
+                                           author: "test",
+                                           options: vec![],
+                                       });
+        Engine::<S, StdTimeManager>::options();
+        Engine::new(options)
+    }
+
+    #[test]
+    fn perft() {
+        let mut engine = new_engine(&[]);
+        assert!(engine.perft(0).is_empty());
+        let divide = engine.perft(3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().fold(0, |acc, &(_, n)| acc + n), 8902);
+        engine.position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                        &mut vec!["e2e4"].into_iter());
+        let divide = engine.perft(1);
+        assert_eq!(divide.len(), 20);
+        assert!(divide.iter().all(|&(_, n)| n == 1));
+        assert!(engine.queue.is_empty());
+        assert!(engine.status.done);
+    }
+
+    #[test]
+    fn forced_move() {
+        let mut engine = new_engine(&[]);
+        engine.position("k7/8/8/8/8/8/8/1R5K b - - 0 1", &mut vec![].into_iter());
+        let started_at = SystemTime::now();
+        engine.go(&GoParams {
+                      wtime: Some(3_600_000),
+                      btime: Some(3_600_000),
+                      ..Default::default()
+                  });
+        let best_move = loop {
+            match engine.wait_for_reply(Duration::from_millis(10)) {
+                Some(EngineReply::BestMove { best_move, .. }) => break best_move,
+                _ => assert!(started_at.elapsed().unwrap() < Duration::from_secs(60)),
+            }
+        };
+        assert_eq!(best_move, "a8a7");
+        assert!(started_at.elapsed().unwrap() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn random_seed() {
+        use rand::Rng;
+        use utils::EngineRng;
+
+        // Plays a few random moves, and then lets the engine choose.
+        fn choose(engine: &mut Engine<S, StdTimeManager>) -> Vec<String> {
+            const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w QKqk - 0 1";
+            engine.new_game();
+            let mut rng = EngineRng::from_option();
+            let mut moves: Vec<String> = vec![];
+            for _ in 0..4 {
+                engine.position(START_FEN, &mut moves.iter().map(|s| s.as_str()));
+                let legal_moves = engine.position.legal_moves();
+                let m = legal_moves[rng.gen_range(0, legal_moves.len())];
+                moves.push(m.notation());
+            }
+            engine.position(START_FEN, &mut moves.iter().map(|s| s.as_str()));
+            engine.go(&GoParams {
+                          depth: Some(1),
+                          ..Default::default()
+                      });
+            loop {
+                if let Some(EngineReply::BestMove { best_move, .. }) =
+                    engine.wait_for_reply(Duration::from_millis(10)) {
+                    moves.push(best_move);
+                    return moves;
+                }
+            }
+        }
+
+        let mut a = new_engine(&[]);
+        let mut b = new_engine(&[]);
+        a.set_option("Random Seed", "12345");
+        let a_moves = choose(&mut a);
+        let b_moves = choose(&mut b);
+        assert_eq!(a_moves, b_moves);
+        let mut other_moves = vec![];
+        for seed in 1..4 {
+            a.set_option("Random Seed", &format!("{}", seed));
+            other_moves.push(choose(&mut a));
+        }
+        assert!(other_moves.iter().any(|x| *x != a_moves));
+        a.set_option("Random Seed", "0");
+    }
+
+    #[test]
+    fn format_score() {
+        use super::format_score;
+        assert_eq!(format_score(VALUE_MAX - 1, BOUND_EXACT, 100), "mate 1");
+        assert_eq!(format_score(VALUE_MAX - 5, BOUND_EXACT, 100), "mate 3");
+        assert_eq!(format_score(VALUE_MAX - 5, BOUND_LOWER, 100), "mate 3");
+        assert_eq!(format_score(VALUE_MIN + 2, BOUND_EXACT, 100), "mate -1");
+        assert_eq!(format_score(VALUE_MIN + 4, BOUND_UPPER, 100), "mate -2");
+        assert_eq!(format_score(35, BOUND_EXACT, 100), "cp 35");
+        assert_eq!(format_score(-35, BOUND_EXACT, 100), "cp -35");
+        assert_eq!(format_score(35, BOUND_LOWER, 100), "cp 35 lowerbound");
+        assert_eq!(format_score(35, BOUND_UPPER, 200), "cp 17 upperbound");
+
+        // Mate values that are not proven are not reported as mates.
+        assert_eq!(format_score(VALUE_MAX - 5, BOUND_UPPER, 100), "cp 9999 upperbound");
+        assert_eq!(format_score(VALUE_MIN + 4, BOUND_LOWER, 100), "cp -9999 lowerbound");
+    }
+
+    #[test]
+    fn centipawn_scale() {
+        fn score(engine: &mut Engine<S, StdTimeManager>, value: Value) -> String {
+            engine.queue.clear();
+            engine.queue_pv(&vec![Variation {
+                                      moves: vec![],
+                                      value,
+                                      bound: BOUND_EXACT,
+                                  }]);
+            match engine.queue.pop_front() {
+                Some(EngineReply::Info(items)) => {
+                    items
+                        .into_iter()
+                        .find(|item| item.info_type == "score")
+                        .unwrap()
+                        .data
+                }
+                _ => panic!("no info"),
+            }
+        }
+
+        let mut engine = new_engine(&[]);
+        assert_eq!(::get_option("Centipawn Scale"), "100");
+        assert_eq!(score(&mut engine, 150), "cp 150");
+        assert_eq!(score(&mut engine, -150), "cp -150");
+        engine.set_option("Centipawn Scale", "200");
+        assert_eq!(score(&mut engine, 150), "cp 75");
+        assert_eq!(score(&mut engine, -150), "cp -75");
+        assert_eq!(score(&mut engine, VALUE_MAX - 1), "mate 1");
+        engine.set_option("Centipawn Scale", "100");
+        assert_eq!(score(&mut engine, 150), "cp 150");
+    }
+
+    #[test]
+    fn best_move_changes() {
+        let mut engine = new_engine(&[]);
+        engine.position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                        &mut vec![].into_iter());
+        let find = |notation: &str| {
+            *engine.position
+                 .legal_moves()
+                 .iter()
+                 .find(|m| m.notation() == notation)
+                 .unwrap()
+        };
+        let (e2e4, d2d4) = (find("e2e4"), find("d2d4"));
+        engine.status = Default::default();
+        let mut changes = vec![];
+        for (depth, &m) in [e2e4, d2d4, d2d4, e2e4, e2e4].iter().enumerate() {
+            engine.process_report(&SearchReport {
+                                      search_id: 0,
+                                      searched_nodes: 0,
+                                      depth: depth as Depth + 1,
+                                      value: 0,
+                                      data: vec![Variation {
+                                                     moves: vec![m],
+                                                     value: 0,
+                                                     bound: BOUND_EXACT,
+                                                 }],
+                                      done: false,
+                                  });
+            changes.push(engine.status.best_move_changes);
+        }
+        assert_eq!(changes, vec![0, 1, 1, 2, 2]);
+        let stability: Vec<String> = engine.queue
+            .iter()
+            .filter_map(|r| match *r {
+                            EngineReply::Info(ref items) if items[0].info_type == "string" => {
+                                Some(items[0].data.clone())
+                            }
+                            _ => None,
+                        })
+            .collect();
+        assert_eq!(stability, vec!["pvstability 1", "pvstability 2"]);
+    }
+
+    #[test]
+    fn fortress() {
+        let mut engine = new_engine(&[]);
+        let search = |engine: &mut Engine<S, StdTimeManager>, fen, params| {
+            engine.position(fen, &mut vec![].into_iter());
+            engine.go(&params);
+            let started_at = SystemTime::now();
+            let mut strings = vec![];
+            loop {
+                assert!(started_at.elapsed().unwrap() < Duration::from_millis(10000));
+                match engine.wait_for_reply(Duration::from_millis(10)) {
+                    Some(EngineReply::Info(ref items)) if items[0].info_type == "string" => {
+                        strings.push(items[0].data.clone());
+                    }
+                    Some(EngineReply::BestMove { .. }) => break,
+                    _ => (),
+                }
+            }
+            (strings, started_at.elapsed().unwrap())
+        };
+        let fortress = "8/8/1k6/p1p1p1p1/P1P1P1P1/1K6/8/8 w - - 0 1";
+
+        // The engine tells when the search has settled, and does not
+        // change its mind after that.
+        let (strings, _) = search(&mut engine,
+                                  fortress,
+                                  GoParams {
+                                      depth: Some(30),
+                                      ..Default::default()
+                                  });
+        let stable = strings.iter().position(|x| x == "stable").unwrap();
+        assert!(strings[stable + 1..].is_empty());
+        assert!(engine.status.best_move_changes < 10);
+
+        // With time management, the engine does not waste its time.
+        let (strings, elapsed) = search(&mut engine,
+                                        fortress,
+                                        GoParams {
+                                            wtime: Some(600_000),
+                                            btime: Some(600_000),
+                                            ..Default::default()
+                                        });
+        assert_eq!(strings.iter().filter(|x| *x == "stable").count(), 1);
+        assert!(elapsed < Duration::from_millis(5000));
+
+        // Open positions are never flagged.
+        let (strings, _) = search(&mut engine,
+                                  "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                                  GoParams {
+                                      depth: Some(5),
+                                      ..Default::default()
+                                  });
+        assert!(!strings.iter().any(|x| x == "stable"));
+        let b = Board::from_fen("4k3/8/8/p1p1p1p1/P1P1P1P1/8/8/R3K3 w - - 0 1").ok().unwrap();
+        assert!(!is_closed_position(&b, 17));
+        assert!(is_closed_position(&b, 16));
+    }
+
+    #[test]
+    fn warm_ttable() {
+        fn search(engine: &mut Engine<S, StdTimeManager>, moves: &[&str]) -> u64 {
+            engine.position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+                            &mut moves.iter().cloned());
+            engine.go(&GoParams {
+                          depth: Some(6),
+                          ..Default::default()
+                      });
+            loop {
+                if let Some(EngineReply::BestMove { .. }) =
+                    engine.wait_for_reply(Duration::from_millis(10)) {
+                    return engine.status.searched_nodes;
+                }
+            }
+        }
+
+        let mut warm = new_engine(&[("Hash", "16")]);
+        warm.new_game();
+        search(&mut warm, &[]);
+        let pv: Vec<String> = warm.best_line.iter().take(2).map(|m| m.notation()).collect();
+        assert_eq!(pv.len(), 2);
+        let pv: Vec<&str> = pv.iter().map(|m| m.as_str()).collect();
+        let warm_nodes = search(&mut warm, &pv);
+
+        let mut cold = new_engine(&[("Hash", "16")]);
+        cold.new_game();
+        let cold_nodes = search(&mut cold, &pv);
+        assert!(warm_nodes < cold_nodes);
+    }
+
+    #[test]
+    fn go_infinite_overrides_limits() {
+        let mut engine = new_engine(&[]);
+        engine.position("k7/8/8/8/8/8/8/1R5K b - - 0 1", &mut vec![].into_iter());
+        engine.go(&GoParams {
+                      infinite: true,
+                      wtime: Some(1000),
+                      btime: Some(1000),
+                      movetime: Some(100),
+                      depth: Some(1),
+                      nodes: Some(100),
+                      ..Default::default()
+                  });
+        let started_at = SystemTime::now();
+        while started_at.elapsed().unwrap() < Duration::from_millis(1500) {
+            if let Some(EngineReply::BestMove { .. }) =
+                engine.wait_for_reply(Duration::from_millis(10)) {
+                panic!("the search terminated by itself");
+            }
+        }
+        engine.stop();
+        let best_move = loop {
+            match engine.wait_for_reply(Duration::from_millis(10)) {
+                Some(EngineReply::BestMove { best_move, .. }) => break best_move,
+                Some(_) => continue,
+                None => panic!("no best move"),
+            }
+        };
+        assert_eq!(best_move, "a8a7");
+    }
+
+    #[test]
+    fn go_infinite_stop() {
+        let mut engine = new_engine(&[]);
+        for fen in &["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w QKqk - 0 1",
+                     "k7/8/8/8/8/8/8/1R5K b - - 0 1",
+                     "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"] {
+            for _ in 0..5 {
+                engine.position(fen, &mut vec![].into_iter());
+                engine.go(&GoParams {
+                              infinite: true,
+                              ..Default::default()
+                          });
+                engine.stop();
+                assert!(!engine.best_line.is_empty());
+                let best_move = loop {
+                    match engine.wait_for_reply(Duration::from_millis(10)) {
+                        Some(EngineReply::BestMove { best_move, .. }) => break best_move,
+                        Some(_) => continue,
+                        None => panic!("no best move"),
+                    }
+                };
+                assert!(engine
+                            .position
+                            .legal_moves()
+                            .iter()
+                            .any(|m| m.notation() == best_move));
+            }
+        }
+    }
+
+    #[test]
+    fn ponder() {
+        let mut engine = new_engine(&[]);
+        let wait_best_move = |engine: &mut Engine<S, StdTimeManager>, timeout| {
+            let started_at = SystemTime::now();
+            while started_at.elapsed().unwrap() < timeout {
+                if let Some(EngineReply::BestMove { best_move, .. }) =
+                    engine.wait_for_reply(Duration::from_millis(10)) {
+                    return Some(best_move);
+                }
+            }
+            None
+        };
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w QKqk - 0 1";
+        let go_ponder = GoParams {
+            ponder: true,
+            wtime: Some(2000),
+            btime: Some(2000),
+            ..Default::default()
+        };
+
+        // The opponent plays the ponder move. The search goes on,
+        // and is not restarted after "ponderhit".
+        engine.position(fen, &mut vec!["e2e4", "e7e5"].into_iter());
+        engine.go(&go_ponder);
+        assert_eq!(wait_best_move(&mut engine, Duration::from_millis(1000)), None);
+        assert!(engine.status.depth > 0);
+        let searched_nodes = engine.status.searched_nodes;
+        engine.ponder_hit();
+        let best_move = wait_best_move(&mut engine, Duration::from_millis(1000)).unwrap();
+        assert!(engine.status.searched_nodes >= searched_nodes);
+        assert!(engine
+                    .position
+                    .legal_moves()
+                    .iter()
+                    .any(|m| m.notation() == best_move));
+
+        // The opponent plays a different move. The GUI stops the
+        // search, discards the best move, and starts a new search.
+        engine.position(fen, &mut vec!["e2e4", "e7e5", "g1f3", "b8c6"].into_iter());
+        engine.go(&go_ponder);
+        assert_eq!(wait_best_move(&mut engine, Duration::from_millis(300)), None);
+        engine.stop();
+        assert!(wait_best_move(&mut engine, Duration::from_millis(1000)).is_some());
+        engine.position(fen, &mut vec!["e2e4", "e7e5", "g1f3", "g8f6"].into_iter());
+        engine.go(&GoParams {
+                      depth: Some(2),
+                      ..Default::default()
+                  });
+        let best_move = wait_best_move(&mut engine, Duration::from_millis(5000)).unwrap();
+        assert!(engine
+                    .position
+                    .legal_moves()
+                    .iter()
+                    .any(|m| m.notation() == best_move));
+    }
+
+    #[test]
+    fn absurd_hash_size() {
+        let engine = new_engine(&[("Hash", &usize::MAX.to_string())]);
+        assert!(engine.tt.size_mb().unwrap() <= HASH_SIZE_MAX_MB);
+    }
+
+    #[test]
+    fn rounded_hash_size() {
+        // The number of buckets is rounded down to a power of two,
+        // which is not a fallback for lack of memory.
+        let mut engine = new_engine(&[("Hash", "100")]);
+        assert_eq!(engine.tt.size_mb(), Some(64));
+        engine.set_option("Hash", "3");
+        assert_eq!(engine.tt.size_mb(), Some(2));
+        assert!(engine.queue.iter().all(|r| match *r {
+            EngineReply::Info(ref items) => {
+                items.iter().all(|x| !x.data.starts_with("hash size reduced"))
+            }
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn hashfull() {
+        let mut engine = new_engine(&[("Hash", "1")]);
+        engine.queue_progress_info();
+        match engine.queue.pop_back() {
+            Some(EngineReply::Info(items)) => {
+                let item = items.iter().find(|x| x.info_type == "hashfull").unwrap();
+                assert_eq!(item.data, "0");
+            }
+            _ => panic!("no progress info"),
+        }
+    }
+
+    #[test]
+    fn resize_hash() {
+        let mut engine = new_engine(&[("Hash", "4"), ("hash", "1")]);
+        assert_eq!(engine.tt.size_mb(), Some(1));
+        engine.tt.store(12345, StdTtableEntry::new(100, BOUND_EXACT, 5));
+        assert!(engine.tt.probe(12345).is_some());
+
+        // The table grows, and the old entries are gone.
+        engine.set_option("hash", "4");
+        assert_eq!(engine.tt.size_mb(), Some(4));
+        assert!(engine.tt.probe(12345).is_none());
+
+        // The new table is used by the search.
+        engine.go(&GoParams {
+                      depth: Some(3),
+                      ..Default::default()
+                  });
+        let started_at = SystemTime::now();
+        loop {
+            assert!(started_at.elapsed().unwrap() < Duration::from_millis(5000));
+            if let Some(EngineReply::BestMove { .. }) =
+                engine.wait_for_reply(Duration::from_millis(10)) {
+                break;
+            }
+        }
+        assert!(engine.tt.probe(engine.position.hash()).is_some());
+
+        // A size given during a search is applied when the search is
+        // done.
+        engine.go(&GoParams {
+                      depth: Some(3),
+                      ..Default::default()
+                  });
+        engine.set_option("Hash", "2");
+        assert_eq!(engine.tt.size_mb(), Some(4));
+        let started_at = SystemTime::now();
+        loop {
+            assert!(started_at.elapsed().unwrap() < Duration::from_millis(5000));
+            if let Some(EngineReply::BestMove { .. }) =
+                engine.wait_for_reply(Duration::from_millis(10)) {
+                break;
+            }
+        }
+        assert_eq!(engine.tt.size_mb(), Some(2));
+
+        // Invalid sizes are ignored.
+        engine.set_option("Hash", "many");
+        assert_eq!(engine.tt.size_mb(), Some(2));
+    }
+
+    #[test]
+    fn early_options() {
+        use std::io::Cursor;
+
+        // The options received before the engine is created are
+        // passed to `Engine::new`.
+        *ENGINE.lock().unwrap() = Some(EngineInfo {
+                                           name: "test",
+                                           author: "test",
+                                           options: vec![],
+                                       });
+        Engine::<S, StdTimeManager>::options();
+        let script = "setoption name Threads value 4\n\
+                      setoption name Hash value 32\n\
+                      isready\n\
+                      quit\n";
+        let mut server = Server::<Engine<S, StdTimeManager>> { engine: None };
+        let mut output = vec![];
+        server.serve_io(Cursor::new(script.as_bytes().to_vec()), &mut output).ok().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output), "readyok\n");
+        let mut engine = server.engine.unwrap();
+        assert_eq!(engine.tt.size_mb(), Some(32));
+
+        // The search runs with three helper threads.
+        engine.go(&GoParams {
+                      depth: Some(3),
+                      ..Default::default()
+                  });
+        let started_at = SystemTime::now();
+        loop {
+            assert!(started_at.elapsed().unwrap() < Duration::from_millis(5000));
+            if let Some(EngineReply::BestMove { .. }) =
+                engine.wait_for_reply(Duration::from_millis(10)) {
+                break;
+            }
+        }
+        assert_eq!(engine.searcher.helper_count(), 3);
+        engine.set_option("Threads", "1");
+    }
+
+    #[test]
+    fn own_book() {
+        fn best_move(engine: &mut Engine<S, StdTimeManager>, searchmoves: &[&str]) -> String {
+            engine.go(&GoParams {
+                          depth: Some(1),
+                          searchmoves: searchmoves.iter().map(|s| s.to_string()).collect(),
+                          ..Default::default()
+                      });
+            loop {
+                if let Some(EngineReply::BestMove { best_move, .. }) =
+                    engine.wait_for_reply(Duration::from_millis(10)) {
+                    return best_move;
+                }
+            }
+        }
+
+        // A book that knows only "d2d4" in the start position.
+        let key = utils::polyglot_key(new_engine(&[]).position.board());
+        let mut data = key.to_be_bytes().to_vec();
+        data.extend_from_slice(&[0x02, 0xdb, 0, 1, 0, 0, 0, 0]);
+        let path = ::std::env::temp_dir().join("alcibiades-own-book-test.bin");
+        ::std::fs::write(&path, &data).unwrap();
+
+        let mut engine = new_engine(&[("OwnBook", "true"), ("BookFile", path.to_str().unwrap())]);
+        ::std::fs::remove_file(&path).unwrap();
+        assert!(engine.book.is_some());
+        assert_eq!(best_move(&mut engine, &[]), "d2d4");
+
+        // The book move is not played when it is not in "searchmoves".
+        assert_eq!(best_move(&mut engine, &["e2e4"]), "e2e4");
+
+        // Out of book.
+        engine.position("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1",
+                        &mut vec![].into_iter());
+        assert!(engine.book_move(&engine.position.legal_moves()).is_none());
+
+        // A missing file leaves the engine without a book.
+        engine.set_option("BookFile", "no-such-book.bin");
+        assert!(engine.book.is_none());
+    }
+
+    #[test]
+    fn selfplay() {
+        use selfplay::*;
+        use move_generator::GameResult;
+        let mut a = new_engine(&[("Hash", "1")]);
+        let mut b = new_engine(&[("Hash", "1")]);
+        let fens = ["8/8/4k3/8/8/8/3KP3/8 w - - 0 1",
+                    "8/8/8/3k4/8/8/8/R2K4 w - - 0 1",
+                    "k7/8/8/8/8/8/8/KNB5 b - - 98 80",
+                    "6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1"];
+        let games = 2 * fens.len();
+        let tally = play_match::<StdMoveGenerator<SimpleEvaluator>, _, _>(&mut a,
+                                          &[],
+                                          &mut b,
+                                          &[],
+                                          &fens,
+                                          games,
+                                          &GoParams {
+                                              depth: Some(2),
+                                              ..Default::default()
+                                          });
+        assert_eq!(tally.wins + tally.draws + tally.losses, games);
+        assert!(tally.draws >= 2);
+
+        let result = play_game::<StdMoveGenerator<SimpleEvaluator>, _, _>(&mut a,
+                                          &[],
+                                          &mut b,
+                                          &[],
+                                          "7k/8/6Q1/8/8/8/8/K7 b - - 0 1",
+                                          &GoParams {
+                                              depth: Some(1),
+                                              ..Default::default()
+                                          });
+        assert_eq!(result,
+                   GameOutcome {
+                       result: GameResult::Draw,
+                       termination: Termination::Stalemate,
+                   });
+    }
+}
@@ -45,7 +45,8 @@ pub trait Evaluator: Clone + Copy + SetOption + Send + 'static {
     /// currently bound.
     ///
     /// The returned value must be between `VALUE_EVAL_MIN` and
-    /// `VALUE_EVAL_MAX`.
+    /// `VALUE_EVAL_MAX`, and is relative to the side to move (see
+    /// `relative_to`).
     fn evaluate(&self, position: &Board) -> Value;
 
     /// Returns whether the position to which the evaluator is
@@ -106,6 +106,8 @@ mod move_generator;
 mod qsearch;
 mod time_manager;
 mod uci;
+#[cfg(test)]
+mod selfplay;
 
 pub use board::*;
 pub use moves::*;
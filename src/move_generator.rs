@@ -8,6 +8,7 @@ use moves::*;
 use value::*;
 use evaluator::Evaluator;
 use bitsets::*;
+use utils;
 use utils::BoardGeometry;
 
 
@@ -65,13 +66,57 @@ pub trait MoveGenerator: Clone + SetOption + Send + 'static {
     /// time-critical paths. (See `do_move`.)
     fn hash(&self) -> u64;
 
+    /// Returns a Zobrist hash value for the pawn structure.
+    ///
+    /// Positions with the same pawn placement have equal pawn hashes,
+    /// regardless of the other pieces. This is useful for caching
+    /// pawn-structure evaluations (see `PawnHashTable`).
+    fn pawn_hash(&self) -> u64 {
+        self.board().pawn_hash()
+    }
+
     /// Returns a bitboard with all pieces and pawns that attack a
     /// given square.
     fn attacks_to(&self, square: Square) -> Bitboard;
 
+    /// Returns the pieces of color `by` that pin a piece to a (maybe
+    /// hypothetical) king on `king_square`, and the pinned pieces.
+    ///
+    /// Returns a tuple with the following elements: `0`) the pinning
+    /// pieces (sliders of color `by`), `1`) the pinned pieces (pieces
+    /// of the opposite color, which are the only pieces standing
+    /// between `king_square` and a pinning piece). The piece on
+    /// `king_square` (if any) is never included in the result.
+    fn pinners_and_pinned(&self, king_square: Square, by: Color) -> (Bitboard, Bitboard);
+
     /// Returns if the side to move is in check.
     fn is_check(&self) -> bool;
 
+    /// Returns if the side to move is in double check.
+    ///
+    /// In double check only king moves can be legal. The default
+    /// implementation calls `attacks_to`, so implementations that
+    /// keep the set of checkers around should override it.
+    fn is_double_check(&self) -> bool {
+        let board = self.board();
+        let king_square = bsf(board.pieces.piece_type[KING] & board.pieces.color[board.to_move]);
+        let checkers = self.attacks_to(king_square) & board.pieces.color[opposite(board.to_move)];
+        pop_count(checkers) > 1
+    }
+
+    /// Returns if a given move would give check.
+    ///
+    /// `m` must have been generated by `generate_all`,
+    /// `generate_forcing`, or `try_move_digest` for the current
+    /// position on the board. Note that after castling, the rook (not
+    /// the king) can give check. The default implementation plays the
+    /// move on a copy of the board, so implementations are encouraged
+    /// to override it.
+    fn gives_check(&self, m: Move) -> bool {
+        let mut p = self.clone();
+        p.do_move(m).is_some() && p.is_check()
+    }
+
     /// Generates all legal moves, possibly including some
     /// pseudo-legal moves too.
     ///
@@ -90,12 +135,62 @@ pub trait MoveGenerator: Clone + SetOption + Send + 'static {
     ///   saving time.
     ///
     /// The initial move score for the generated moves is
-    /// *unspecified*.
+    /// *unspecified*. Every generated move encodes the castling
+    /// rights and the en-passant file of the current position, so
+    /// that `undo_move` can restore them without any external state.
     ///
     /// **Note:** A pseudo-legal move is a move that is otherwise
     /// legal, except it might leave the king in check.
     fn generate_all<T: AddMove>(&self, moves: &mut T);
 
+    /// Generates all strictly legal moves.
+    ///
+    /// The moves are added to `moves`. This is the same as
+    /// `generate_all`, except that king's moves to attacked squares
+    /// are filtered out, so that `do_move` will succeed for every
+    /// generated move. This is handy when the moves are shown to the
+    /// user, but is slower than `generate_all`. The default
+    /// implementation verifies each king's move by playing it on a
+    /// copy of the board, so implementations are encouraged to
+    /// override it.
+    fn generate_legal<T: AddMove>(&self, moves: &mut T) {
+        let mut v = Vec::with_capacity(96);
+        self.generate_all(&mut v);
+        let mut p = self.clone();
+        for m in v {
+            if m.played_piece() != KING {
+                moves.add_move(m);
+            } else if p.do_move(m).is_some() {
+                p.undo_move(m);
+                moves.add_move(m);
+            }
+        }
+    }
+
+    /// Returns the result of the game in the current position.
+    ///
+    /// When the side to move has no legal moves, the game is over:
+    /// if the king is in check, the other side wins by checkmate,
+    /// otherwise the game is drawn by stalemate. In all other cases
+    /// `GameResult::Ongoing` is returned.
+    ///
+    /// **Note:** Draws by repetition, by rule-50, and by insufficient
+    /// material (see `Board::is_insufficient_material`) are not
+    /// detected.
+    fn result(&self) -> GameResult {
+        let mut moves = Vec::with_capacity(96);
+        self.generate_legal(&mut moves);
+        if !moves.is_empty() {
+            GameResult::Ongoing
+        } else if !self.is_check() {
+            GameResult::Draw
+        } else if self.board().to_move == WHITE {
+            GameResult::BlackWins
+        } else {
+            GameResult::WhiteWins
+        }
+    }
+
     /// Generates moves for the quiescence search.
     ///
     /// The moves are added to `moves`. This method always generates a
@@ -127,8 +222,22 @@ pub trait MoveGenerator: Clone + SetOption + Send + 'static {
     /// will return `Some(m)`. Otherwise it will return `None`. This
     /// is useful when playing moves from the transposition table,
     /// without calling `generate_all`.
+    ///
+    /// Move digests created by `MoveDigest::from_lan` for castling
+    /// and en-passant captures have `MOVE_NORMAL` move type.
+    /// Implementations should resolve them to the correct moves.
     fn try_move_digest(&self, move_digest: MoveDigest) -> Option<Move>;
 
+    /// Parses a move in Standard Algebraic Notation (SAN).
+    ///
+    /// Examples: `Nbd7`, `exd6`, `exd6e.p.`, `O-O-O`, `a1=Q`.
+    /// Trailing check signs and annotations (`+`, `#`, `!`, `?`) are
+    /// ignored. Returns `None` if the notation is invalid, or if it
+    /// does not match exactly one legal move in the current position.
+    fn parse_san(&self, s: &str) -> Option<Move> {
+        utils::parse_san(self, s)
+    }
+
     /// Plays a move on the board.
     ///
     /// It the move leaves the king in check, `None` is returned
@@ -141,6 +250,17 @@ pub trait MoveGenerator: Clone + SetOption + Send + 'static {
     /// position on the board.
     fn do_move(&mut self, m: Move) -> Option<u64>;
 
+    /// Plays a move that is known to be legal on the board.
+    ///
+    /// This is the same as `do_move`, except that the implementation
+    /// may skip the verification whether the move leaves the king in
+    /// check. The move passed to this method **must** be a move for
+    /// which `do_move` would not return `None`. The default
+    /// implementation simply calls `do_move`.
+    fn do_move_unchecked(&mut self, m: Move) -> u64 {
+        self.do_move(m).expect("illegal move")
+    }
+
     /// Takes back last played move.
     ///
     /// The move passed to this method **must** be the last move passed
@@ -217,10 +337,12 @@ pub trait MoveGenerator: Clone + SetOption + Send + 'static {
 
                 // Store a speculative value that will be used if the
                 // captured piece happens to be defended.
-                gain[depth + 1].write(*PIECE_VALUES.get_unchecked(piece) - current_gain);
+                gain[depth + 1].write(PIECE_VALUES
+                                          .get_unchecked(piece)
+                                          .saturating_sub(current_gain));
                 let speculative_gain: &mut Value = gain.get_unchecked_mut(depth + 1).assume_init_mut();
                 
-                if max(-current_gain, *speculative_gain) < 0 {
+                if max(current_gain.saturating_neg(), *speculative_gain) < 0 {
                     // The side that made the last capture wins even if
                     // the captured piece happens to be defended. So, we
                     // stop here to save precious CPU cycles. Note that
@@ -276,14 +398,53 @@ pub trait MoveGenerator: Clone + SetOption + Send + 'static {
             // Negamax the `gain` array for the final static exchange
             // evaluation. (The `gain` array actually represents an unary
             // tree, at each node of which the player can either continue
-            // the exchange or back off.)
+            // the exchange or back off.) Saturating arithmetic
+            // guarantees that the king's huge value can not overflow
+            // `Value`, however long the exchange is.
             while depth > 0 {
+                let previous_gain = *gain.get_unchecked(depth - 1).assume_init_ref();
+                let next_gain = *gain.get_unchecked(depth).assume_init_ref();
                 *gain.get_unchecked_mut(depth - 1).assume_init_mut() =
-                                                        -max(-*gain.get_unchecked(depth - 1).assume_init_ref(),
-                                                                *gain.get_unchecked(depth).assume_init_ref());
+                    max(previous_gain.saturating_neg(), next_gain).saturating_neg();
                 depth -= 1;
             }
             gain[0].assume_init()
         }
     }
 }
+
+
+/// The result of a game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+
+    /// The game has not ended yet.
+    Ongoing,
+}
+
+impl GameResult {
+    /// Returns the winner of the game, or `None` if the game was drawn
+    /// or has not ended yet.
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            GameResult::WhiteWins => Some(WHITE),
+            GameResult::BlackWins => Some(BLACK),
+            GameResult::Draw | GameResult::Ongoing => None,
+        }
+    }
+
+    /// Returns the result token used in PGN files and tournament
+    /// reports: `"1-0"`, `"0-1"`, `"1/2-1/2"`, or `"*"` for an
+    /// unfinished game.
+    pub fn token(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        }
+    }
+}
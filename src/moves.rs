@@ -39,6 +39,66 @@ pub const MOVE_NORMAL: MoveType = 3;
 pub struct MoveDigest(u16);
 
 impl MoveDigest {
+    /// Creates a new instance.
+    ///
+    /// `aux_data` encodes the type of the promoted piece (`0` --
+    /// queen, `1` -- rook, `2` -- bishop, `3` -- knight) if
+    /// `move_type` is `MOVE_PROMOTION`, otherwise it must be zero.
+    #[inline]
+    pub fn new(move_type: MoveType,
+               orig_square: Square,
+               dest_square: Square,
+               aux_data: usize)
+               -> MoveDigest {
+        debug_assert!(move_type <= 3);
+        debug_assert!(orig_square <= 63);
+        debug_assert!(dest_square <= 63);
+        debug_assert!(aux_data <= 3);
+        debug_assert!(move_type == MOVE_PROMOTION || aux_data == 0);
+        MoveDigest((move_type << SHIFT_MOVE_TYPE | orig_square << SHIFT_ORIG_SQUARE |
+                    dest_square << SHIFT_DEST_SQUARE |
+                    aux_data << SHIFT_AUX_DATA) as u16)
+    }
+
+    /// Creates a new instance from the move's long algebraic
+    /// notation.
+    ///
+    /// Examples: `e2e4`, `e7e5`, `e1g1` (white short castling),
+    /// `e7e8q` (for promotion). Returns `None` if the notation is
+    /// invalid.
+    ///
+    /// **Important note:** The move type of the returned digest is
+    /// `MOVE_PROMOTION` for promotions, and `MOVE_NORMAL` for all
+    /// other moves. (`MoveGenerator::try_move_digest` resolves such
+    /// digests to castling and en-passant moves too.)
+    pub fn from_lan(s: &str) -> Option<MoveDigest> {
+        fn square(file: u8, rank: u8) -> Option<Square> {
+            match (file, rank) {
+                (b'a'..=b'h', b'1'..=b'8') => {
+                    Some(Board::square((file - b'a') as usize, (rank - b'1') as usize))
+                }
+                _ => None,
+            }
+        }
+        let s = s.as_bytes();
+        let (move_type, aux_data) = match s.len() {
+            4 => (MOVE_NORMAL, 0),
+            5 => {
+                match s[4] {
+                    b'q' => (MOVE_PROMOTION, 0),
+                    b'r' => (MOVE_PROMOTION, 1),
+                    b'b' => (MOVE_PROMOTION, 2),
+                    b'n' => (MOVE_PROMOTION, 3),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        let orig_square = square(s[0], s[1])?;
+        let dest_square = square(s[2], s[3])?;
+        Some(MoveDigest::new(move_type, orig_square, dest_square, aux_data))
+    }
+
     /// Creates an invalid move digest instance.
     ///
     /// The returned instance contains `0`. This is sometimes useful
@@ -244,6 +304,15 @@ impl Move {
         (self.0 >> SHIFT_SCORE) as u32
     }
 
+    /// Returns if `other` is the same move, ignoring the move scores.
+    ///
+    /// Unlike `==`, this method compares only the move digest and
+    /// the information needed to undo the move (bits 0-31).
+    #[inline]
+    pub fn same_move(&self, other: &Move) -> bool {
+        self.0 as u32 == other.0 as u32
+    }
+
     /// Returns the move type.
     #[inline]
     pub fn move_type(&self) -> MoveType {
@@ -310,8 +379,11 @@ impl Move {
     /// Returns the algebraic notation of the move.
     ///
     /// Examples: `e2e4`, `e7e5`, `e1g1` (white short castling),
-    /// `e7e8q` (for promotion).
+    /// `e7e8q` (for promotion), `0000` (for null moves).
     pub fn notation(&self) -> String {
+        if self.is_null() {
+            return "0000".to_string();
+        }
         format!("{}{}{}",
                 notation(self.orig_square()),
                 notation(self.dest_square()),
@@ -431,6 +503,10 @@ mod tests {
         m.set_score(::std::u32::MAX);
         assert_eq!(m.score(), ::std::u32::MAX);
         assert!(m > m_copy);
+        assert!(m != m_copy);
+        assert!(m.same_move(&m_copy));
+        assert!(m_copy.same_move(&m));
+        assert!(!m.same_move(&m1));
         m.set_score(0);
         assert_eq!(m.score(), 0);
         assert!(m.is_pawn_advance_or_capure());
@@ -440,6 +516,7 @@ mod tests {
         assert!(m4.is_pawn_advance_or_capure());
         assert!(!m5.is_pawn_advance_or_capure());
         assert!(m5.is_null());
+        assert_eq!(m5.notation(), "0000");
         assert!(MOVE_NORMAL != 0);
         assert!(!Move::invalid().is_null());
         assert_eq!(Move::invalid().digest(), MoveDigest::invalid());
@@ -448,4 +525,24 @@ mod tests {
         assert_eq!(m.digest().dest_square(), m.dest_square());
         assert_eq!(m.digest().aux_data(), m.aux_data());
     }
+
+    #[test]
+    fn move_digests() {
+        let cr = CastlingRights::new(0b1011);
+        let m = Move::new(MOVE_NORMAL, E2, E4, 0, PIECE_NONE, PAWN, cr, 8, 0);
+        let m1 = Move::new(MOVE_PROMOTION, F2, F1, 1, PIECE_NONE, PAWN, cr, 8, 0);
+        let m2 = Move::new(MOVE_CASTLING, E1, G1, 0, PIECE_NONE, KING, cr, 8, 0);
+        assert_eq!(MoveDigest::new(MOVE_NORMAL, E2, E4, 0), m.digest());
+        assert_eq!(MoveDigest::new(MOVE_PROMOTION, F2, F1, 1), m1.digest());
+        assert_eq!(MoveDigest::new(MOVE_CASTLING, E1, G1, 0), m2.digest());
+        assert_eq!(MoveDigest::from_lan("e2e4"), Some(m.digest()));
+        assert_eq!(MoveDigest::from_lan("f2f1r"), Some(m1.digest()));
+        assert_eq!(MoveDigest::from_lan("e1g1").unwrap().move_type(), MOVE_NORMAL);
+        assert_eq!(MoveDigest::from_lan("h8a1").unwrap().notation(), "h8a1");
+        assert!(MoveDigest::from_lan("e2e").is_none());
+        assert!(MoveDigest::from_lan("e2e4k").is_none());
+        assert!(MoveDigest::from_lan("i2e4").is_none());
+        assert!(MoveDigest::from_lan("e2e9").is_none());
+        assert!(MoveDigest::from_lan("e2e4qq").is_none());
+    }
 }
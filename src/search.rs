@@ -57,6 +57,35 @@ pub struct SearchParams<T: SearchNode> {
     /// The behavior of the search is *undefined* if the root position
     /// is not final, but `searchmoves` is empty.
     pub searchmoves: Vec<Move>,
+
+    /// The number of nodes that should be searched between two
+    /// consecutive progress reports, or `None` if the search should
+    /// decide itself.
+    ///
+    /// This is only a hint. On very fast time controls the engine
+    /// may ask for more frequent reports, so that it can react to
+    /// termination requests quickly.
+    pub report_interval: Option<u64>,
+
+    /// How much (in centipawns) a draw is considered worse than an
+    /// equal position, from the point of view of the side to move in
+    /// `position`.
+    ///
+    /// A negative value means that draws are welcome. Searches are
+    /// free to ignore this.
+    pub contempt: Value,
+
+    /// Whether the engine is in analysis mode.
+    ///
+    /// When analysing, exact values for the principal variation are
+    /// preferred over search speed.
+    pub analyse_mode: bool,
+
+    /// The number of threads that the search may use.
+    ///
+    /// Should be at least `1`. Searches that can not run in parallel
+    /// ignore this.
+    pub threads: usize,
 }
 
 
@@ -108,6 +137,17 @@ pub struct SearchReport<T> {
     pub data: T,
 }
 
+impl SearchReport<Vec<Variation>> {
+    /// Returns the first move of the best variation, if available.
+    ///
+    /// The variations in `data` are expected to be sorted by
+    /// descending strength.
+    #[inline]
+    pub fn best_move(&self) -> Option<Move> {
+        self.data.first().and_then(|v| v.moves.first()).cloned()
+    }
+}
+
 
 /// A trait for executing iterative deepening searches.
 ///
@@ -195,6 +235,13 @@ pub trait DeepeningSearch: SetOption {
     /// continue to be called periodically until the returned report
     /// indicates that the search is done.
     fn send_message(&mut self, msg: &str);
+
+    /// Forgets everything learned from the previous searches.
+    ///
+    /// This method is called when a new game begins. It will not be
+    /// called while a search is executing. The default implementation
+    /// does nothing.
+    fn new_game(&mut self) {}
 }
 
 
@@ -238,6 +285,14 @@ pub trait Search: SetOption {
     /// The type of auxiliary data that search progress reports carry.
     type ReportData;
 
+    /// The type of data that the implementation keeps between
+    /// searches (history tables, for example).
+    ///
+    /// An instance is owned by each search executor, and is given to
+    /// every search that the executor spawns. A fresh instance is
+    /// created when a new game begins.
+    type State: Default + Send + Sync + 'static;
+
     /// Spawns a new search thread.
     ///
     /// A join handle is returned that gives the calculated evaluation
@@ -253,6 +308,8 @@ pub trait Search: SetOption {
     ///   each moment, it contains the results of the work done so
     ///   far.
     ///
+    /// * `state` supplies the data kept between searches.
+    ///
     /// * `reports` gives the sending-half of progress reports'
     ///   channel.
     ///
@@ -274,6 +331,7 @@ pub trait Search: SetOption {
     ///     problems.
     fn spawn(params: SearchParams<Self::SearchNode>,
              tt: Arc<Self::Ttable>,
+             state: Arc<Self::State>,
              reports: Sender<SearchReport<Self::ReportData>>,
              messages: Receiver<String>)
              -> thread::JoinHandle<Value>;
@@ -1,11 +1,13 @@
 //! Defines the `SearchNode` trait.
 
+use std::ops::{Deref, DerefMut};
 use uci::SetOption;
-use board::{Board, IllegalBoard};
+use board::{Board, IllegalBoard, Square};
 use moves::{Move, MoveDigest, AddMove};
 use depth::*;
 use value::*;
 use evaluator::Evaluator;
+use move_generator::MoveGenerator;
 use qsearch::QsearchResult;
 
 
@@ -40,6 +42,12 @@ pub trait SearchNode: Clone + SetOption + Send + 'static {
     /// The type of result object that `qsearch` returns.
     type QsearchResult: QsearchResult;
 
+    /// The type of move generator that the implementation works with.
+    ///
+    /// This is useful for validating the move generation (see
+    /// `utils::perft`).
+    type MoveGenerator: MoveGenerator;
+
     /// Instantiates a new chess position from playing history.
     ///
     /// `fen` should be the Forsyth–Edwards Notation of a legal
@@ -69,6 +77,17 @@ pub trait SearchNode: Clone + SetOption + Send + 'static {
     /// Returns if the side to move is in check.
     fn is_check(&self) -> bool;
 
+    /// Returns if a given move would give check.
+    ///
+    /// `m` must have been generated by `generate_moves` or
+    /// `try_move_digest` for the current position on the board. The
+    /// default implementation plays the move on a copy of the
+    /// position, so implementations are encouraged to override it.
+    fn gives_check(&self, m: Move) -> bool {
+        let mut p = self.clone();
+        p.do_move(m) && p.is_check()
+    }
+
     /// Returns a reference to a static evaluator bound to the current
     /// position.
     fn evaluator(&self) -> &Self::Evaluator;
@@ -196,9 +215,46 @@ pub trait SearchNode: Clone + SetOption + Send + 'static {
     /// due to repetition or rule-50, `do_move` will return `false`.
     fn do_move(&mut self, m: Move) -> bool;
 
+    /// Plays a move that is known to be legal on the board.
+    ///
+    /// This is the same as `do_move`, except that the implementation
+    /// may skip the verification whether the move leaves the king in
+    /// check. The move passed to this method **must** be a move for
+    /// which `do_move` would return `true` (for example, a move
+    /// returned by `legal_moves`). The default implementation simply
+    /// calls `do_move`.
+    fn do_move_unchecked(&mut self, m: Move) {
+        assert!(self.do_move(m), "illegal move");
+    }
+
     /// Takes back the last played move.
     fn undo_last_move(&mut self);
 
+    /// Takes back the last `n` played moves, the most recent first.
+    ///
+    /// Only moves played after the root position (see `from_history`)
+    /// can be taken back.
+    fn undo_last_moves(&mut self, n: usize) {
+        for _ in 0..n {
+            self.undo_last_move();
+        }
+    }
+
+    /// Plays a null move on the board, and returns a guard that will
+    /// take it back when dropped.
+    ///
+    /// `None` is returned if the null move can not be played (the
+    /// king is in check, or the position is a draw). This makes it
+    /// impossible to forget to take back the null move.
+    fn do_null_move(&mut self) -> Option<NullMoveGuard<'_, Self>> {
+        let m = self.null_move();
+        if self.do_move(m) {
+            Some(NullMoveGuard { node: self })
+        } else {
+            None
+        }
+    }
+
     /// Returns all legal moves in the position.
     ///
     /// No moves are returned for repeated and rule-50 positions.
@@ -219,4 +275,47 @@ pub trait SearchNode: Clone + SetOption + Send + 'static {
         }
         legal_moves
     }
+
+    /// Returns all legal moves in the position that start from
+    /// `from`.
+    ///
+    /// An empty vector is returned if `from` does not hold a piece of
+    /// the side to move. This is useful for highlighting the legal
+    /// destinations of a piece in a GUI.
+    fn legal_moves_for(&self, from: Square) -> Vec<Move> {
+        self.legal_moves().into_iter().filter(|m| m.orig_square() == from).collect()
+    }
+}
+
+
+/// Takes back a null move when dropped.
+///
+/// Returned by `SearchNode::do_null_move`. Gives access to the search
+/// node after the null move has been played.
+pub struct NullMoveGuard<'a, T: SearchNode + 'a> {
+    node: &'a mut T,
+}
+
+impl<'a, T: SearchNode + 'a> Deref for NullMoveGuard<'a, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.node
+    }
+}
+
+impl<'a, T: SearchNode + 'a> DerefMut for NullMoveGuard<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.node
+    }
+}
+
+impl<'a, T: SearchNode + 'a> Drop for NullMoveGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        debug_assert!(self.node.last_move().is_null());
+        self.node.undo_last_move();
+    }
 }
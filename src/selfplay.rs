@@ -0,0 +1,244 @@
+//! Implements a self-play driver for strength testing.
+//!
+//! Playing many games between two engine configurations is the only
+//! reliable way to find out whether a change in the search or the
+//! evaluation makes the engine stronger. This module plays such
+//! matches in-process, talking to the engines through the `UciEngine`
+//! trait, and adjudicating the games according to the rules of chess.
+
+use std::time::Duration;
+use uci::*;
+use board::*;
+use moves::Move;
+use move_generator::{MoveGenerator, GameResult};
+use utils::{MoveStack, parse_fen};
+
+
+/// The reason for which a game has ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    Repetition,
+    InsufficientMaterial,
+}
+
+
+/// The outcome of a single game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameOutcome {
+    /// The result of the game. This is never `GameResult::Ongoing`.
+    pub result: GameResult,
+
+    /// The reason for which the game has ended.
+    pub termination: Termination,
+}
+
+
+/// The score of a match, from the first engine's point of view.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+
+/// Plays a match of `games` games between two engines.
+///
+/// The starting positions are taken from `fens` in turn. Each
+/// starting position is played twice in a row, so that both engines
+/// get to play both sides of it. `a` plays white in the first game.
+///
+/// **Important note:** The configuration options are stored in a
+/// global table, shared by all engine instances. Therefore
+/// `a_options` and `b_options` are re-applied before each move, and
+/// an option set for one of the engines should be set for the other
+/// one as well.
+pub fn play_match<N, A, B>(a: &mut A,
+                           a_options: &[(&str, &str)],
+                           b: &mut B,
+                           b_options: &[(&str, &str)],
+                           fens: &[&str],
+                           games: usize,
+                           go_params: &GoParams)
+                           -> Tally
+    where N: MoveGenerator,
+          A: UciEngine,
+          B: UciEngine
+{
+    assert!(!fens.is_empty());
+    let mut tally = Tally::default();
+    for i in 0..games {
+        let fen = fens[(i / 2) % fens.len()];
+        let (result, a_color) = if i % 2 == 0 {
+            (play_game::<N, A, B>(a, a_options, b, b_options, fen, go_params), WHITE)
+        } else {
+            (play_game::<N, B, A>(b, b_options, a, a_options, fen, go_params), BLACK)
+        };
+        match result.result.winner() {
+            None => tally.draws += 1,
+            Some(c) if c == a_color => tally.wins += 1,
+            Some(_) => tally.losses += 1,
+        }
+    }
+    tally
+}
+
+
+/// Plays a single game from the position described by `fen`.
+///
+/// `N` is used to keep track of the game, and to decide when it has
+/// ended.
+pub fn play_game<N, W, B>(white: &mut W,
+                          white_options: &[(&str, &str)],
+                          black: &mut B,
+                          black_options: &[(&str, &str)],
+                          fen: &str,
+                          go_params: &GoParams)
+                          -> GameOutcome
+    where N: MoveGenerator,
+          W: UciEngine,
+          B: UciEngine
+{
+    let (board, mut halfmove_clock, _) = parse_fen(fen).ok().expect("invalid FEN");
+    let mut position = N::from_board(board).ok().expect("illegal starting position");
+    let mut played_moves: Vec<String> = vec![];
+    let mut hashes = vec![position.hash()];
+    white.new_game();
+    black.new_game();
+
+    loop {
+        if let Some(result) = adjudicate(&position, halfmove_clock, &hashes) {
+            return result;
+        }
+        let best_move = if position.board().to_move == WHITE {
+            think(white, white_options, fen, &played_moves, go_params)
+        } else {
+            think(black, black_options, fen, &played_moves, go_params)
+        };
+        let m = legal_moves(&mut position)
+            .into_iter()
+            .find(|m| m.notation() == best_move)
+            .expect("the engine played an illegal move");
+        halfmove_clock = if m.is_pawn_advance_or_capure() {
+            0
+        } else {
+            halfmove_clock + 1
+        };
+        position.do_move(m);
+        played_moves.push(best_move);
+        hashes.push(position.hash());
+    }
+}
+
+
+/// Asks `engine` for its move in the given position.
+fn think<E: UciEngine>(engine: &mut E,
+                       options: &[(&str, &str)],
+                       fen: &str,
+                       played_moves: &[String],
+                       go_params: &GoParams)
+                       -> String {
+    for &(name, value) in options {
+        engine.set_option(name, value);
+    }
+    engine.position(fen, &mut played_moves.iter().map(|s| s.as_str()));
+    engine.go(go_params);
+    loop {
+        if let Some(EngineReply::BestMove { best_move, .. }) =
+            engine.wait_for_reply(Duration::from_millis(10)) {
+            return best_move;
+        }
+    }
+}
+
+
+/// Returns all legal moves in the position.
+fn legal_moves<N: MoveGenerator>(position: &mut N) -> Vec<Move> {
+    let mut moves = MoveStack::new();
+    let mut legal_moves = vec![];
+    position.generate_all(&mut moves);
+    while let Some(m) = moves.pop() {
+        if position.do_move(m).is_some() {
+            legal_moves.push(m);
+            position.undo_move(m);
+        }
+    }
+    legal_moves
+}
+
+
+/// Decides if the game has ended.
+///
+/// `hashes` contains the hashes of all the positions that occurred in
+/// the game so far, the last one being the current position.
+fn adjudicate<N: MoveGenerator>(position: &N,
+                                halfmove_clock: u8,
+                                hashes: &[u64])
+                                -> Option<GameOutcome> {
+    let draw = |termination| {
+        Some(GameOutcome {
+                 result: GameResult::Draw,
+                 termination,
+             })
+    };
+
+    match position.result() {
+        GameResult::Ongoing => {}
+        GameResult::Draw => return draw(Termination::Stalemate),
+        result => {
+            return Some(GameOutcome {
+                            result,
+                            termination: Termination::Checkmate,
+                        })
+        }
+    }
+    if halfmove_clock >= 100 {
+        return draw(Termination::FiftyMoveRule);
+    }
+    if hashes.iter().filter(|&&h| h == position.hash()).count() >= 3 {
+        return draw(Termination::Repetition);
+    }
+    if position.board().is_insufficient_material() {
+        return draw(Termination::InsufficientMaterial);
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stock::{StdMoveGenerator, SimpleEvaluator};
+
+    type P = StdMoveGenerator<SimpleEvaluator>;
+
+    fn outcome(fen: &str) -> Option<GameOutcome> {
+        let (board, halfmove_clock, _) = parse_fen(fen).ok().unwrap();
+        let p = P::from_board(board).ok().unwrap();
+        adjudicate(&p, halfmove_clock, &[p.hash()])
+    }
+
+    #[test]
+    fn adjudication() {
+        let o = outcome("4k3/8/8/8/8/8/5PPP/3r2K1 w - - 0 1").unwrap();
+        assert_eq!(o.termination, Termination::Checkmate);
+        assert_eq!(o.result.token(), "0-1");
+
+        let o = outcome("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(o.termination, Termination::Checkmate);
+        assert_eq!(o.result.token(), "1-0");
+
+        let o = outcome("7k/8/6Q1/8/8/8/8/K7 b - - 0 1").unwrap();
+        assert_eq!(o.termination, Termination::Stalemate);
+        assert_eq!(o.result.token(), "1/2-1/2");
+
+        let o = outcome("8/8/4k3/8/8/8/3KN3/8 w - - 0 1").unwrap();
+        assert_eq!(o.termination, Termination::InsufficientMaterial);
+        assert_eq!(o.result, GameResult::Draw);
+
+        assert!(outcome("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_none());
+    }
+}
@@ -0,0 +1,154 @@
+//! Implements an evaluator that delegates to user-supplied closures.
+
+use std::sync::RwLock;
+use uci::SetOption;
+use board::*;
+use value::*;
+use evaluator::Evaluator;
+
+
+/// A user-supplied function that evaluates a position.
+///
+/// The returned value is relative to the side to move, and is
+/// clamped to the `VALUE_EVAL_MIN..=VALUE_EVAL_MAX` range.
+pub type EvaluationFn = Box<dyn Fn(&Board) -> Value + Send + Sync>;
+
+
+/// A user-supplied function that tells whether a position is prone
+/// to zugzwang (see `Evaluator::is_zugzwangy`).
+pub type ZugzwangFn = Box<dyn Fn(&Board) -> bool + Send + Sync>;
+
+
+/// The closures set by `set_evaluation_callbacks`.
+struct Callbacks {
+    evaluate: EvaluationFn,
+    is_zugzwangy: ZugzwangFn,
+}
+
+
+lazy_static! {
+    static ref CALLBACKS: RwLock<Option<&'static Callbacks>> = RwLock::new(None);
+}
+
+
+/// Sets the closures that `CallbackEvaluator` delegates to.
+///
+/// This allows plugging in an external evaluation function (a neural
+/// network served over FFI, for example) without implementing the
+/// `Evaluator` trait. The closures are shared by all search threads,
+/// and are used by the evaluators created from now on. The evaluators
+/// that already exist keep using the previously set closures, which
+/// therefore are never freed.
+pub fn set_evaluation_callbacks<F, Z>(evaluate: F, is_zugzwangy: Z)
+    where F: Fn(&Board) -> Value + Send + Sync + 'static,
+          Z: Fn(&Board) -> bool + Send + Sync + 'static
+{
+    let callbacks = Box::new(Callbacks {
+                                 evaluate: Box::new(evaluate),
+                                 is_zugzwangy: Box::new(is_zugzwangy),
+                             });
+    *CALLBACKS.write().unwrap() = Some(Box::leak(callbacks));
+}
+
+
+/// An evaluator that calls the closures set by
+/// `set_evaluation_callbacks`.
+///
+/// The closures are fetched once, when the evaluator is created, so
+/// evaluating a position does not take any locks. Every position is
+/// evaluated from scratch.
+///
+/// If no closures have been set, every position is evaluated as
+/// equal, and is considered prone to zugzwang.
+#[derive(Clone, Copy)]
+pub struct CallbackEvaluator {
+    callbacks: Option<&'static Callbacks>,
+}
+
+impl SetOption for CallbackEvaluator {}
+
+impl Evaluator for CallbackEvaluator {
+    #[allow(unused_variables)]
+    fn new(position: &Board) -> CallbackEvaluator {
+        CallbackEvaluator { callbacks: *CALLBACKS.read().unwrap() }
+    }
+
+    fn evaluate(&self, position: &Board) -> Value {
+        match self.callbacks {
+            Some(c) => (c.evaluate)(position).clamp(VALUE_EVAL_MIN, VALUE_EVAL_MAX),
+            None => 0,
+        }
+    }
+
+    fn is_zugzwangy(&self, position: &Board) -> bool {
+        match self.callbacks {
+            Some(c) => (c.is_zugzwangy)(position),
+            None => true,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::channel;
+    use super::*;
+    use bitsets::*;
+    use ttable::*;
+    use search::{Search, SearchParams};
+    use search_node::SearchNode;
+    use stock::{StdTtable, StdTtableEntry, StdSearchNode, StdQsearch, StdMoveGenerator,
+                SimpleSearch};
+
+    type P = StdSearchNode<StdQsearch<StdMoveGenerator<CallbackEvaluator>>>;
+
+    #[test]
+    fn callbacks() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        set_evaluation_callbacks(|b: &Board| {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            let count = |c| pop_count(b.pieces.color[c] & !b.pieces.piece_type[KING]) as Value;
+            100 * (count(b.to_move) - count(opposite(b.to_move)))
+        },
+                                 |_: &Board| false);
+        let b = Board::from_fen("k7/8/8/3q4/4P3/8/8/K7 w - - 0 1").ok().unwrap();
+        assert_eq!(CallbackEvaluator::new(&b).evaluate(&b), 0);
+        assert!(!CallbackEvaluator::new(&b).is_zugzwangy(&b));
+
+        let tt = Arc::new(StdTtable::<StdTtableEntry>::new(None));
+        let p = P::from_history("k7/8/8/3q4/4P3/8/8/K7 w - - 0 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        let (reports_tx, _reports_rx) = channel();
+        let (_messages_tx, messages_rx) = channel();
+        let handle = SimpleSearch::spawn(SearchParams {
+                                             search_id: 0,
+                                             position: p.clone(),
+                                             depth: 2,
+                                             lower_bound: VALUE_MIN,
+                                             upper_bound: VALUE_MAX,
+                                             searchmoves: p.legal_moves(),
+                                             report_interval: None,
+                                             contempt: 0,
+                                             analyse_mode: false,
+                                             threads: 1,
+                                         },
+                                         tt.clone(),
+                                         Arc::default(),
+                                         reports_tx,
+                                         messages_rx);
+        assert_eq!(handle.join().unwrap(), 100);
+        assert_eq!(tt.extract_pv(&p).moves[0].notation(), "e4d5");
+        assert!(CALLS.load(Ordering::Relaxed) > 1);
+
+        // The existing evaluators keep their closures.
+        let e = CallbackEvaluator::new(&b);
+        set_evaluation_callbacks(|_: &Board| 7, |_: &Board| true);
+        assert_eq!(e.evaluate(&b), 0);
+        assert!(!e.is_zugzwangy(&b));
+        assert_eq!(CallbackEvaluator::new(&b).evaluate(&b), 7);
+        assert!(CallbackEvaluator::new(&b).is_zugzwangy(&b));
+    }
+}
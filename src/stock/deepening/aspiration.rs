@@ -27,6 +27,13 @@ fn initial_window() -> isize {
 }
 
 
+/// Converts `v` to `Value`, clamping it to the `VALUE_MIN..VALUE_MAX`
+/// range first.
+fn clamp_value(v: isize) -> Value {
+    max(VALUE_MIN as isize, min(v, VALUE_MAX as isize)) as Value
+}
+
+
 /// Executes searches with aspiration windows.
 pub struct Aspiration<T: SearchExecutor> {
     tt: Arc<T::Ttable>,
@@ -137,6 +144,10 @@ impl<T: SearchExecutor> SearchExecutor for Aspiration<T> {
         }
         self.searcher.send_message(message);
     }
+
+    fn new_game(&mut self) {
+        self.searcher.new_game();
+    }
 }
 
 
@@ -159,6 +170,12 @@ impl<T: SearchExecutor> SetOption for Aspiration<T> {
 
 
 impl<T: SearchExecutor> Aspiration<T> {
+    /// Returns the underlying search executor.
+    #[cfg(test)]
+    pub fn searcher(&self) -> &T {
+        &self.searcher
+    }
+
     fn start_aspirated_search(&mut self) {
         let depth = if self.lmr_mode && self.expected_to_fail_high && self.params.depth > 0 {
             self.params.depth - 1
@@ -188,10 +205,10 @@ impl<T: SearchExecutor> Aspiration<T> {
             if e.depth() >= 4 && e.depth() + 2 >= self.params.depth {
                 let v = e.value() as isize;
                 if e.bound() & BOUND_LOWER != 0 {
-                    a = max(v - self.delta, VALUE_MIN as isize) as Value;
+                    a = clamp_value(v - self.delta);
                 }
                 if e.bound() & BOUND_UPPER != 0 {
-                    b = min(v + self.delta, VALUE_MAX as isize) as Value;
+                    b = clamp_value(v + self.delta);
                 }
                 debug_assert!(a < b);
                 if a >= upper_bound {
@@ -220,10 +237,10 @@ impl<T: SearchExecutor> Aspiration<T> {
         if lower_bound < self.alpha && lower_bound < v && v <= self.alpha ||
            self.lmr_mode && self.expected_to_fail_high && v < upper_bound {
             // Failed low -- reduce alpha.
-            self.alpha = max(v as isize - self.delta, lower_bound as isize) as Value;
+            self.alpha = max(clamp_value(v as isize - self.delta), lower_bound);
         } else if self.beta < upper_bound && self.beta <= v && v < upper_bound {
             // Failed high -- raise beta.
-            self.beta = min(v as isize + self.delta, upper_bound as isize) as Value;
+            self.beta = min(clamp_value(v as isize + self.delta), upper_bound);
         } else {
             return false;
         }
@@ -239,3 +256,57 @@ impl<T: SearchExecutor> Aspiration<T> {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+    use super::super::ThreadExecutor;
+    use stock::{StdTtable, StdTtableEntry, StdSearchNode, StdQsearch, StdMoveGenerator,
+                SimpleEvaluator, SimpleSearch};
+
+    type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
+    type T = StdTtable<StdTtableEntry>;
+    type S = SimpleSearch<T, P>;
+
+    #[test]
+    fn clamp_value() {
+        assert_eq!(super::clamp_value(0), 0);
+        assert_eq!(super::clamp_value(VALUE_MAX as isize + 1_000_000), VALUE_MAX);
+        assert_eq!(super::clamp_value(VALUE_MIN as isize - 1_000_000), VALUE_MIN);
+        assert_eq!(super::clamp_value(isize::MAX), VALUE_MAX);
+        assert_eq!(super::clamp_value(isize::MIN), VALUE_MIN);
+    }
+
+    #[test]
+    fn widen_near_mate() {
+        ::CONFIGURATION
+            .write()
+            .unwrap()
+            .entry("Initial Aspiration Window")
+            .or_insert("16".to_string());
+        let mut a = Aspiration::<ThreadExecutor<S>>::new(Arc::new(T::new(None)));
+        for &delta in &[16, 1_000_000] {
+            a.delta = delta;
+            a.alpha = VALUE_MAX - 10;
+            a.beta = VALUE_MAX - 5;
+            assert!(a.widen_aspiration_window(VALUE_MAX - 3));
+            assert_eq!(a.alpha, VALUE_MAX - 10);
+            assert!(a.beta > VALUE_MAX - 5);
+            assert!(a.alpha < a.beta);
+
+            a.delta = delta;
+            a.alpha = VALUE_MIN + 5;
+            a.beta = VALUE_MIN + 10;
+            assert!(a.widen_aspiration_window(VALUE_MIN + 3));
+            assert!(a.alpha >= VALUE_MIN && a.alpha < VALUE_MIN + 5);
+            assert_eq!(a.beta, VALUE_MIN + 10);
+        }
+        a.delta = 1_000_000;
+        a.alpha = VALUE_MAX - 10;
+        a.beta = VALUE_MAX - 5;
+        assert!(a.widen_aspiration_window(VALUE_MAX - 3));
+        assert_eq!(a.beta, VALUE_MAX);
+    }
+}
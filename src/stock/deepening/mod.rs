@@ -6,6 +6,7 @@ mod multipv;
 
 use self::multipv::Multipv;
 use std::thread;
+use std::cmp::{min, Reverse};
 use std::time::Duration;
 use std::cell::RefCell;
 use std::sync::Arc;
@@ -61,6 +62,7 @@ use search::DeepeningSearch as SearchExecutor;
 /// **Important note:** `Deepening` requires a proper transposition
 /// table to do its work. It can not work with `DummyTtable`.
 pub struct Deepening<T: Search> {
+    tt: Arc<T::Ttable>,
     params: SearchParams<T::SearchNode>,
     search_is_terminated: bool,
     previously_searched_nodes: u64,
@@ -76,6 +78,12 @@ pub struct Deepening<T: Search> {
 
     // The depth at which the search are likely to be terminated.
     depth_target: Depth,
+
+    // The principal variations from the last completed depth.
+    variations: Vec<Variation>,
+
+    // The values of the root moves from the last completed depth.
+    root_move_values: Vec<(Move, Value)>,
 }
 
 
@@ -88,6 +96,7 @@ impl<T: Search> SearchExecutor for Deepening<T> {
 
     fn new(tt: Arc<Self::Ttable>) -> Deepening<T> {
         Deepening {
+            tt: tt.clone(),
             params: bogus_params(),
             search_is_terminated: false,
             previously_searched_nodes: 0,
@@ -95,6 +104,8 @@ impl<T: Search> SearchExecutor for Deepening<T> {
             depth: 0,
             value: VALUE_UNKNOWN,
             depth_target: DEPTH_MAX,
+            variations: vec![],
+            root_move_values: vec![],
         }
     }
 
@@ -111,6 +122,8 @@ impl<T: Search> SearchExecutor for Deepening<T> {
         self.depth = 0;
         self.value = VALUE_UNKNOWN;
         self.depth_target = DEPTH_MAX;
+        self.variations = vec![];
+        self.root_move_values = vec![];
         self.search_next_depth();
     }
 
@@ -123,7 +136,7 @@ impl<T: Search> SearchExecutor for Deepening<T> {
             done,
             ..
         } = self.multipv.try_recv_report()?;
-        if value != VALUE_UNKNOWN {
+        if value != VALUE_UNKNOWN && value >= self.mate_value() {
             self.value = value;
         }
         if !data.is_empty() {
@@ -141,13 +154,28 @@ impl<T: Search> SearchExecutor for Deepening<T> {
         if done && !self.search_is_terminated {
             debug_assert_eq!(depth, self.depth + 1);
             report.depth = depth;
-            report.data.extend(self.multipv.extract_variations());
+            let variations = self.multipv.extract_variations();
+            if variations.first().is_none_or(|v| v.value >= self.mate_value()) {
+                self.variations = variations;
+                self.root_move_values = self.multipv.root_move_values();
+            }
+            if data.is_empty() {
+                // A plain aspiration search does not sort the root
+                // moves, so we sort them here, so that the next
+                // iteration tries the most promising moves first.
+                self.sort_searchmoves();
+            }
+            report.data.extend(self.variations.iter().cloned());
             self.previously_searched_nodes = report.searched_nodes;
             self.depth = depth;
             if depth < self.params.depth {
                 self.search_next_depth();
                 report.done = false;
             }
+        } else if done {
+            // The search has been terminated. The last report should
+            // still contain the best variations found so far.
+            report.data.extend(self.variations.iter().cloned());
         }
         Ok(report)
     }
@@ -174,6 +202,10 @@ impl<T: Search> SearchExecutor for Deepening<T> {
             self.multipv.send_message(message);
         }
     }
+
+    fn new_game(&mut self) {
+        self.multipv.new_game();
+    }
 }
 
 
@@ -189,6 +221,80 @@ impl<T: Search> SetOption for Deepening<T> {
 
 
 impl<T: Search> Deepening<T> {
+    /// Returns the number of helper threads started for the last
+    /// search.
+    #[cfg(test)]
+    pub fn helper_count(&self) -> usize {
+        self.multipv.searcher().searcher().helpers.len()
+    }
+
+    /// Turns on or off the calculation of the values of all root
+    /// moves.
+    ///
+    /// The mode is off by default, because it makes the search
+    /// considerably slower. The change takes effect from the next
+    /// call to `start_search`.
+    pub fn set_root_move_values_mode(&mut self, on: bool) {
+        self.multipv.root_move_values_mode = on;
+    }
+
+    /// Returns the values of the root moves, as calculated at the
+    /// last completed depth.
+    ///
+    /// The moves are sorted by descending value. Unless the root move
+    /// values mode is on (see `set_root_move_values_mode`), this will
+    /// often be empty.
+    pub fn root_move_values(&self) -> &[(Move, Value)] {
+        &self.root_move_values
+    }
+
+    /// A helper method. It returns the value of the checkmate found
+    /// at the last completed depth, or `VALUE_UNKNOWN` if no
+    /// checkmate has been found.
+    ///
+    /// A checkmate, once found, is a proven fact. Deeper searches
+    /// may fail to see it (because of the reductions, for example),
+    /// so values and variations that do not reach this value are
+    /// not reported.
+    fn mate_value(&self) -> Value {
+        match self.variations.first() {
+            Some(v) if v.value > VALUE_EVAL_MAX => v.value,
+            _ => VALUE_UNKNOWN,
+        }
+    }
+
+    /// A helper method. It sorts `self.params.searchmoves` by
+    /// descending value, as recorded in the transposition table.
+    ///
+    /// The hash move for the root position goes first. The moves for
+    /// which there are no values in the transposition table go last.
+    fn sort_searchmoves(&mut self) {
+        let tt = &self.tt;
+        let p = &mut self.params.position;
+        let mut moves: Vec<(Move, Value)> = self.params
+            .searchmoves
+            .iter()
+            .map(|&m| {
+                assert!(p.do_move(m));
+                let v = match tt.probe(p.hash()) {
+                    Some(e) if e.bound() != BOUND_NONE => -e.value(),
+                    _ => VALUE_MIN,
+                };
+                p.undo_last_move();
+                (m, v)
+            })
+            .collect();
+        moves.sort_by_key(|&(_, v)| Reverse(v));
+        if let Some(e) = tt.probe(p.hash()) {
+            let digest = e.move_digest();
+            if let Some(i) = moves.iter().position(|&(m, _)| m.digest() == digest) {
+                let hash_move = moves.remove(i);
+                moves.insert(0, hash_move);
+            }
+        }
+        self.params.searchmoves = moves.into_iter().map(|(m, _)| m).collect();
+    }
+
     fn search_next_depth(&mut self) {
         self.multipv
             .start_search(SearchParams {
@@ -201,13 +307,22 @@ impl<T: Search> Deepening<T> {
 
 
 /// A helper type. It turns a `Search` into `SearchExecutor`.
+///
+/// When `SearchParams::threads` is greater than one, helper threads
+/// are started together with each search. They search the same position
+/// (every other one of them -- one ply deeper), sharing the
+/// transposition table with the main thread, which makes the main
+/// search faster ("lazy SMP"). Their reports are discarded, and they
+/// are told to terminate as soon as the main search is done.
 struct ThreadExecutor<T: Search> {
     tt: Arc<T::Ttable>,
+    state: Arc<T::State>,
     messages_tx: Sender<String>,
     reports_rx: Receiver<SearchReport<T::ReportData>>,
     reports_tx: Sender<SearchReport<T::ReportData>>,
     pending_report: RefCell<Option<SearchReport<T::ReportData>>>,
     handle: Option<thread::JoinHandle<Value>>,
+    helpers: Vec<(Sender<String>, thread::JoinHandle<Value>)>,
 }
 
 impl<T: Search> SearchExecutor for ThreadExecutor<T> {
@@ -221,11 +336,13 @@ impl<T: Search> SearchExecutor for ThreadExecutor<T> {
         let (reports_tx, reports_rx) = channel();
         Self {
             tt: tt,
+            state: Arc::new(T::State::default()),
             messages_tx: channel().0,
             reports_rx: reports_rx,
             reports_tx: reports_tx,
             pending_report: RefCell::new(None),
             handle: None,
+            helpers: vec![],
         }
     }
 
@@ -233,8 +350,23 @@ impl<T: Search> SearchExecutor for ThreadExecutor<T> {
         let (messages_tx, messages_rx) = channel();
         self.messages_tx = messages_tx;
         self.handle.take().and_then(|h| h.join().ok());
+        for (tx, h) in self.helpers.drain(..) {
+            tx.send("TERMINATE".to_string()).ok();
+            h.join().ok();
+        }
+        for i in 1..params.threads {
+            let (messages_tx, messages_rx) = channel();
+            let depth = min(params.depth + (i & 1) as Depth, DEPTH_MAX);
+            let handle = T::spawn(SearchParams { depth, ..params.clone() },
+                                  self.tt.clone(),
+                                  self.state.clone(),
+                                  channel().0,
+                                  messages_rx);
+            self.helpers.push((messages_tx, handle));
+        }
         self.handle = Some(T::spawn(params,
                                     self.tt.clone(),
+                                    self.state.clone(),
                                     self.reports_tx.clone(),
                                     messages_rx));
     }
@@ -247,21 +379,53 @@ impl<T: Search> SearchExecutor for ThreadExecutor<T> {
     }
 
     fn try_recv_report(&mut self) -> Result<SearchReport<Self::ReportData>, TryRecvError> {
-        self.pending_report
+        let report = self.pending_report
             .borrow_mut()
             .take()
             .ok_or(TryRecvError::Empty)
-            .or_else(|_| self.reports_rx.try_recv())
+            .or_else(|_| self.reports_rx.try_recv())?;
+        if report.done {
+            for (tx, _) in self.helpers.iter() {
+                tx.send("TERMINATE".to_string()).ok();
+            }
+        }
+        Ok(report)
     }
 
     fn send_message(&mut self, msg: &str) {
         self.messages_tx.send(msg.to_string()).ok();
+        for (tx, _) in self.helpers.iter() {
+            tx.send(msg.to_string()).ok();
+        }
+    }
+
+    fn new_game(&mut self) {
+        self.state = Arc::new(T::State::default());
+    }
+}
+
+impl<T: Search> Drop for ThreadExecutor<T> {
+    fn drop(&mut self) {
+        // Do not leave any search threads running.
+        self.send_message("TERMINATE");
+        self.handle.take().and_then(|h| h.join().ok());
+        for (_, h) in self.helpers.drain(..) {
+            h.join().ok();
+        }
     }
 }
 
 impl<T: Search> SetOption for ThreadExecutor<T> {
     fn options() -> Vec<(&'static str, OptionDescription)> {
-        T::options()
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get()) as i32;
+        let mut options = vec![("Threads",
+                                OptionDescription::Spin {
+                                    min: 1,
+                                    max: cpus,
+                                    default: 1,
+                                })];
+        options.extend(T::options());
+        options
     }
 
     fn set_option(name: &str, value: &str) {
@@ -282,6 +446,10 @@ fn bogus_params<T: SearchNode>() -> SearchParams<T> {
         lower_bound: VALUE_MIN,
         upper_bound: VALUE_MAX,
         searchmoves: vec![Move::invalid()],
+        report_interval: None,
+        contempt: 0,
+        analyse_mode: false,
+        threads: 1,
     }
 }
 
@@ -289,19 +457,403 @@ fn bogus_params<T: SearchNode>() -> SearchParams<T> {
 /// A helper function. It checks if there are moves in the supplied
 /// list that occur more than once.
 fn contains_dups(list: &Vec<Move>) -> bool {
-    let mut l = list.clone();
-    l.sort();
-    l.dedup();
-    l.len() < list.len()
+    list.iter()
+        .enumerate()
+        .any(|(i, m)| list[..i].iter().any(|x| x.same_move(m)))
 }
 
 
 /// A helper function. It checks if the two supplied lists of moves
 /// contain the same moves, possibly in different order.
 fn contains_same_moves(list1: &Vec<Move>, list2: &Vec<Move>) -> bool {
-    let mut list1 = list1.clone();
-    let mut list2 = list2.clone();
-    list1.sort();
-    list2.sort();
-    list1 == list2
+    list1.len() == list2.len() && !contains_dups(list1) && !contains_dups(list2) &&
+    list1.iter().all(|m| list2.iter().any(|x| x.same_move(m)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use std::sync::Arc;
+    use super::{ThreadExecutor, Deepening, contains_same_moves};
+    use super::aspiration::Aspiration;
+    use super::multipv::Multipv;
+    use super::SearchExecutor;
+    use std::sync::mpsc::channel;
+    use moves::Move;
+    use value::*;
+    use depth::*;
+    use ttable::*;
+    use search::{Search, SearchParams, SearchReport};
+    use search_node::SearchNode;
+    use uci::{SetOption, OptionDescription};
+    use stock::{StdTtable, StdTtableEntry, StdSearchNode, StdQsearch, StdMoveGenerator,
+                SimpleEvaluator, SimpleSearch};
+
+    type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
+    type T = StdTtable<StdTtableEntry>;
+    type S = SimpleSearch<T, P>;
+
+    fn params(depth: Depth, searchmoves_count: usize) -> SearchParams<P> {
+        let p = P::from_history("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let mut searchmoves = p.legal_moves();
+        searchmoves.truncate(searchmoves_count);
+        SearchParams {
+            search_id: 0,
+            position: p,
+            depth,
+            lower_bound: VALUE_MIN,
+            upper_bound: VALUE_MAX,
+            searchmoves,
+            report_interval: None,
+            contempt: 0,
+            analyse_mode: false,
+            threads: 1,
+        }
+    }
+
+    // Polls for reports until the search is done, and verifies that
+    // no reports arrive after the last one.
+    fn wait_done<E: SearchExecutor>(e: &mut E) {
+        let started_at = Instant::now();
+        loop {
+            assert!(started_at.elapsed() < Duration::from_secs(60), "search never done");
+            e.wait_report(Duration::from_millis(10));
+            if let Ok(r) = e.try_recv_report() {
+                if r.done {
+                    break;
+                }
+            }
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert!(e.try_recv_report().is_err());
+    }
+
+    fn check_termination<E>(searchmoves_count: usize)
+        where E: SearchExecutor<Ttable = T, SearchNode = P>
+    {
+        let tt = Arc::new(T::new(None));
+        let mut e = E::new(tt);
+
+        // Terminate before any report was received.
+        e.start_search(params(DEPTH_MAX, searchmoves_count));
+        e.send_message("TERMINATE");
+        e.send_message("TERMINATE");
+        wait_done(&mut e);
+
+        // Terminate after the search is done.
+        e.start_search(params(2, searchmoves_count));
+        wait_done(&mut e);
+        e.send_message("TERMINATE");
+        e.send_message("TERMINATE");
+        thread::sleep(Duration::from_millis(50));
+        assert!(e.try_recv_report().is_err());
+
+        // Terminate in the middle of the search.
+        e.start_search(params(DEPTH_MAX, searchmoves_count));
+        thread::sleep(Duration::from_millis(20));
+        e.send_message("TERMINATE");
+        wait_done(&mut e);
+    }
+
+    #[test]
+    fn termination() {
+        {
+            let mut configuration = ::CONFIGURATION.write().unwrap();
+            configuration.entry("MultiPV").or_insert("1".to_string());
+            configuration.entry("Initial Aspiration Window").or_insert("16".to_string());
+        }
+        let all = usize::MAX;
+        check_termination::<ThreadExecutor<S>>(all);
+        check_termination::<Aspiration<ThreadExecutor<S>>>(all);
+        check_termination::<Multipv<ThreadExecutor<S>>>(all);
+        check_termination::<Multipv<ThreadExecutor<S>>>(2);
+        check_termination::<Deepening<S>>(all);
+        check_termination::<Deepening<S>>(2);
+    }
+
+    #[test]
+    fn node_count() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::mpsc::{channel, Sender, Receiver};
+        use search::Search;
+
+        // The total number of nodes reported by the leaf searches.
+        static LEAF_NODES: AtomicU64 = AtomicU64::new(0);
+
+        // A search that independently counts the searched nodes.
+        struct CountingSearch;
+
+        impl SetOption for CountingSearch {
+            fn options() -> Vec<(&'static str, OptionDescription)> {
+                S::options()
+            }
+
+            fn set_option(name: &str, value: &str) {
+                S::set_option(name, value)
+            }
+        }
+
+        impl Search for CountingSearch {
+            type Ttable = T;
+
+            type SearchNode = P;
+
+            type ReportData = <S as Search>::ReportData;
+
+            type State = <S as Search>::State;
+
+            fn spawn(params: SearchParams<P>,
+                     tt: Arc<T>,
+                     state: Arc<Self::State>,
+                     reports: Sender<SearchReport<Self::ReportData>>,
+                     messages: Receiver<String>)
+                     -> thread::JoinHandle<Value> {
+                let (tx, rx) = channel();
+                let handle = S::spawn(params, tt, state, tx, messages);
+                thread::spawn(move || {
+                    let value = handle.join().unwrap();
+                    for r in rx.try_iter() {
+                        if r.done {
+                            LEAF_NODES.fetch_add(r.searched_nodes, Ordering::SeqCst);
+                        }
+                        reports.send(r).ok();
+                    }
+                    value
+                })
+            }
+        }
+
+        {
+            let mut configuration = ::CONFIGURATION.write().unwrap();
+            configuration.entry("MultiPV").or_insert("1".to_string());
+            configuration.entry("Initial Aspiration Window").or_insert("16".to_string());
+        }
+        let tt = Arc::new(T::new(None));
+        let mut e = Deepening::<CountingSearch>::new(tt);
+        e.start_search(params(5, usize::MAX));
+        let started_at = Instant::now();
+        let mut searched_nodes = 0;
+        loop {
+            assert!(started_at.elapsed() < Duration::from_secs(60), "search never done");
+            e.wait_report(Duration::from_millis(10));
+            if let Ok(r) = e.try_recv_report() {
+                assert!(r.searched_nodes >= searched_nodes);
+                searched_nodes = r.searched_nodes;
+                if r.done {
+                    break;
+                }
+            }
+        }
+        assert!(searched_nodes > 0);
+        assert_eq!(searched_nodes, LEAF_NODES.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn best_move() {
+        fn last_report(e: &mut Deepening<S>) -> SearchReport<Vec<Variation>> {
+            let started_at = Instant::now();
+            loop {
+                assert!(started_at.elapsed() < Duration::from_secs(60), "search never done");
+                e.wait_report(Duration::from_millis(10));
+                if let Ok(r) = e.try_recv_report() {
+                    if r.done {
+                        return r;
+                    }
+                }
+            }
+        }
+
+        {
+            let mut configuration = ::CONFIGURATION.write().unwrap();
+            configuration.entry("MultiPV").or_insert("1".to_string());
+            configuration.entry("Initial Aspiration Window").or_insert("16".to_string());
+        }
+        let tt = Arc::new(T::new(None));
+        let mut e = Deepening::<S>::new(tt.clone());
+        let p = params(4, usize::MAX);
+        let legal_moves = p.position.legal_moves();
+
+        // A completed search.
+        e.start_search(p.clone());
+        let r = last_report(&mut e);
+        let m = r.best_move().unwrap();
+        assert!(legal_moves.iter().any(|x| x.same_move(&m)));
+        assert!(tt.extract_pv(&p.position).moves[0].same_move(&m));
+
+        // A terminated search.
+        e.start_search(params(DEPTH_MAX, usize::MAX));
+        let started_at = Instant::now();
+        loop {
+            assert!(started_at.elapsed() < Duration::from_secs(60));
+            e.wait_report(Duration::from_millis(10));
+            if let Ok(r) = e.try_recv_report() {
+                if r.best_move().is_some() {
+                    break;
+                }
+            }
+        }
+        e.send_message("TERMINATE");
+        let m = last_report(&mut e).best_move().unwrap();
+        assert!(legal_moves.iter().any(|x| x.same_move(&m)));
+    }
+
+    #[test]
+    fn threads() {
+        let options = ThreadExecutor::<S>::options();
+        match options.iter().find(|o| o.0 == "Threads").unwrap().1 {
+            OptionDescription::Spin { min, max, default } => {
+                assert_eq!(min, 1);
+                assert_eq!(max as usize,
+                           thread::available_parallelism().map_or(1, |n| n.get()));
+                assert_eq!(default, 1);
+            }
+            _ => panic!("Threads must be a spin option"),
+        }
+
+        let tt = Arc::new(T::new(None));
+        let mut e = ThreadExecutor::<S>::new(tt);
+        e.start_search(SearchParams { threads: 3, ..params(DEPTH_MAX, usize::MAX) });
+        assert_eq!(e.helpers.len(), 2);
+        e.send_message("TERMINATE");
+        wait_done(&mut e);
+        e.start_search(params(2, usize::MAX));
+        assert_eq!(e.helpers.len(), 0);
+        wait_done(&mut e);
+
+        // The helper threads do not change the best move.
+        let best_move = |threads| {
+            let p = P::from_history("r5k1/5pp1/4r2p/1N6/8/7P/5PP1/3R2K1 w - - 0 1",
+                                    &mut vec![].into_iter())
+                    .ok()
+                    .unwrap();
+            let tt = Arc::new(T::new(None));
+            let mut e = ThreadExecutor::<S>::new(tt.clone());
+            e.start_search(SearchParams {
+                               search_id: 0,
+                               position: p.clone(),
+                               depth: 5,
+                               lower_bound: VALUE_MIN,
+                               upper_bound: VALUE_MAX,
+                               searchmoves: p.legal_moves(),
+                               report_interval: None,
+                               contempt: 0,
+                               analyse_mode: false,
+                               threads,
+                           });
+            wait_done(&mut e);
+            drop(e);
+            tt.extract_pv(&p).moves[0].notation()
+        };
+        assert_eq!(best_move(2), "b5c7");
+        assert_eq!(best_move(1), "b5c7");
+    }
+
+    #[test]
+    fn mate_is_kept() {
+        {
+            let mut configuration = ::CONFIGURATION.write().unwrap();
+            configuration.entry("MultiPV").or_insert("1".to_string());
+            configuration.entry("Initial Aspiration Window").or_insert("16".to_string());
+        }
+        let p = P::from_history("k7/8/8/3K4/8/8/8/7R w - - 0 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        let tt = Arc::new(T::new(None));
+        let mut e = Deepening::<S>::new(tt);
+        e.start_search(SearchParams {
+                           depth: 10,
+                           searchmoves: p.legal_moves(),
+                           position: p,
+                           ..params(1, 0)
+                       });
+
+        // Once the mate in 3 is found, every report tells it.
+        let started_at = Instant::now();
+        let mut mate_found_at = None;
+        loop {
+            assert!(started_at.elapsed() < Duration::from_secs(60), "search never done");
+            e.wait_report(Duration::from_millis(10));
+            if let Ok(r) = e.try_recv_report() {
+                if mate_found_at.is_some() {
+                    assert!(r.value >= VALUE_MAX - 5);
+                    assert!(r.data.iter().all(|v| v.value >= VALUE_MAX - 5));
+                } else if !r.data.is_empty() && r.data[0].value >= VALUE_MAX - 5 {
+                    mate_found_at = Some(r.depth);
+                }
+                if r.done {
+                    assert_eq!(r.depth, 10);
+                    break;
+                }
+            }
+        }
+        assert!(mate_found_at.is_some());
+    }
+
+    #[test]
+    fn root_move_values() {
+        {
+            let mut configuration = ::CONFIGURATION.write().unwrap();
+            configuration.entry("MultiPV").or_insert("1".to_string());
+            configuration.entry("Initial Aspiration Window").or_insert("16".to_string());
+        }
+        let tt = Arc::new(T::new(None));
+        let mut e = Deepening::<S>::new(tt);
+        e.start_search(params(2, usize::MAX));
+        wait_done(&mut e);
+        assert!(e.root_move_values().is_empty());
+
+        e.set_root_move_values_mode(true);
+        let p = params(2, usize::MAX);
+        let legal_moves = p.position.legal_moves();
+        e.start_search(p);
+        wait_done(&mut e);
+        let values = e.root_move_values();
+        assert_eq!(values.len(), legal_moves.len());
+        assert!(legal_moves.iter().all(|m| values.iter().any(|x| x.0.same_move(m))));
+        assert!(values.iter().all(|x| x.1 > VALUE_MIN && x.1 < VALUE_MAX));
+        assert!(values.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn root_move_order() {
+        fn node_count(depth: Depth, searchmoves: Vec<Move>) -> u64 {
+            let (reports_tx, reports_rx) = channel();
+            let (_messages_tx, messages_rx) = channel();
+            let tt = Arc::new(T::new(None));
+            let handle = S::spawn(SearchParams { searchmoves, ..params(depth, 0) },
+                                  tt,
+                                  Arc::default(),
+                                  reports_tx,
+                                  messages_rx);
+            handle.join().unwrap();
+            reports_rx.iter().last().unwrap().searched_nodes
+        }
+
+        {
+            let mut configuration = ::CONFIGURATION.write().unwrap();
+            configuration.entry("MultiPV").or_insert("1".to_string());
+            configuration.entry("Initial Aspiration Window").or_insert("16".to_string());
+        }
+        let tt = Arc::new(T::new(None));
+        let mut e = Deepening::<S>::new(tt);
+        let p = params(4, usize::MAX);
+        let legal_moves = p.position.legal_moves();
+        e.start_search(p);
+        wait_done(&mut e);
+        let sorted_moves = e.params.searchmoves.clone();
+        assert!(contains_same_moves(&sorted_moves, &legal_moves));
+
+        // The next iteration searches fewer nodes when it follows the
+        // order from the previous iteration.
+        let mut reversed_moves = sorted_moves.clone();
+        reversed_moves.reverse();
+        let sorted_count = node_count(5, sorted_moves);
+        let reversed_count = node_count(5, reversed_moves);
+        assert!(sorted_count < reversed_count);
+    }
 }
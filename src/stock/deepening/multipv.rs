@@ -49,6 +49,12 @@ pub struct Multipv<T: SearchExecutor> {
 
     // The values for the corresponding moves in `self.params.searchmoves`.
     values: Vec<Value>,
+
+    /// `Multipv` has a special mode in which the value of every root
+    /// move is calculated, even when only one best line of play is
+    /// needed (see `root_move_values`). This mode is off by default,
+    /// because it makes the search considerably slower.
+    pub root_move_values_mode: bool,
 }
 
 
@@ -70,6 +76,7 @@ impl<T: SearchExecutor> SearchExecutor for Multipv<T> {
             all_moves_are_considered: true,
             current_move_index: 0,
             values: vec![VALUE_MIN],
+            root_move_values_mode: false,
         }
     }
 
@@ -87,7 +94,9 @@ impl<T: SearchExecutor> SearchExecutor for Multipv<T> {
         self.search_is_terminated = false;
         self.previously_searched_nodes = 0;
         self.variation_count = min(n, max(1, ::get_option("MultiPV").parse().unwrap_or(0)));
-        if n == 0 || self.variation_count == 1 && self.all_moves_are_considered {
+        if n == 0 ||
+           self.variation_count == 1 && self.all_moves_are_considered &&
+           !self.root_move_values_mode {
             // A plain aspiration search.
             //
             // A search is not a genuine multi-PV search if all legal
@@ -152,6 +161,10 @@ impl<T: SearchExecutor> SearchExecutor for Multipv<T> {
         }
         self.searcher.send_message(message);
     }
+
+    fn new_game(&mut self) {
+        self.searcher.new_game();
+    }
 }
 
 
@@ -174,6 +187,12 @@ impl<T: SearchExecutor> SetOption for Multipv<T> {
 
 
 impl<T: SearchExecutor> Multipv<T> {
+    /// Returns the underlying aspiration searcher.
+    #[cfg(test)]
+    pub fn searcher(&self) -> &Aspiration<T> {
+        &self.searcher
+    }
+
     /// Returns the best lines of play so far.
     pub fn extract_variations(&mut self) -> Vec<Variation> {
         let mut variations = vec![];
@@ -202,12 +221,41 @@ impl<T: SearchExecutor> Multipv<T> {
         variations
     }
 
+    /// Returns the values of the root moves searched so far.
+    ///
+    /// The moves are sorted by descending value. Values that are
+    /// smaller or equal to the lower bound of the search are upper
+    /// bounds only. Unless `root_move_values_mode` is on, only the
+    /// values for the best lines of play are exact. An empty vector is
+    /// returned if the search is not a genuine multi-PV search.
+    pub fn root_move_values(&self) -> Vec<(Move, Value)> {
+        if self.runs_genuine_multipv_search() {
+            self.params
+                .searchmoves
+                .iter()
+                .cloned()
+                .zip(self.values.iter().cloned())
+                .take(self.current_move_index)
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
     fn search_current_move(&mut self) -> bool {
         if self.current_move_index < self.params.searchmoves.len() {
-            let alpha = self.values[self.variation_count - 1];
+            let alpha = if self.root_move_values_mode {
+                // Every move must be searched with a full window.
+                VALUE_MIN
+            } else {
+                self.values[self.variation_count - 1]
+            };
             if alpha < self.params.upper_bound {
                 let m = self.params.searchmoves[self.current_move_index];
-                assert!(self.params.position.do_move(m));
+                self.params.position.do_move_unchecked(m);
+
+                // The searcher will not count the node reached by
+                // `m`, so we count it here.
                 self.previously_searched_nodes += 1;
                 self.searcher
                     .start_search(SearchParams {
@@ -216,6 +264,7 @@ impl<T: SearchExecutor> Multipv<T> {
                                       lower_bound: -self.params.upper_bound,
                                       upper_bound: -max(alpha, self.params.lower_bound),
                                       searchmoves: self.params.position.legal_moves(),
+                                      contempt: -self.params.contempt,
                                       ..self.params.clone()
                                   });
                 return true;
@@ -9,6 +9,9 @@ mod std_qsearch;
 mod std_move_generator;
 mod std_time_manager;
 mod simple_evaluator;
+mod tapered_evaluator;
+mod callback_evaluator;
+mod pawn_hash_table;
 mod deepening;
 
 pub use self::std_ttable::*;
@@ -20,4 +23,7 @@ pub use self::std_qsearch::*;
 pub use self::std_move_generator::*;
 pub use self::std_time_manager::*;
 pub use self::simple_evaluator::*;
+pub use self::tapered_evaluator::*;
+pub use self::callback_evaluator::*;
+pub use self::pawn_hash_table::*;
 pub use self::deepening::*;
@@ -0,0 +1,213 @@
+//! Implements `PawnHashTable`.
+
+use board::*;
+use value::*;
+use bitsets::*;
+
+
+/// The result of a pawn-structure evaluation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PawnHashEntry {
+    /// The value of the pawn structure from white's point of view
+    /// (see `relative_to`).
+    pub value: Value,
+
+    /// The passed pawns for each color.
+    pub passed_pawns: [Bitboard; 2],
+}
+
+
+/// A hash table for pawn-structure evaluations.
+///
+/// Pawn-structure evaluation is relatively expensive, but depends
+/// only on the placement of the pawns, which rarely changes during
+/// the search. Therefore, the results are cached in a dedicated
+/// table, keyed by a pawn-only Zobrist hash (see
+/// `Board::pawn_hash`). The table is much smaller and simpler
+/// than the transposition table: each key maps to exactly one slot,
+/// and new records always replace old ones.
+pub struct PawnHashTable {
+    records: Vec<(u64, PawnHashEntry)>,
+    hits: u64,
+    misses: u64,
+}
+
+impl PawnHashTable {
+    /// Creates a new table with `2.pow(size_log2)` slots.
+    pub fn new(size_log2: usize) -> PawnHashTable {
+        let empty = PawnHashEntry {
+            value: 0,
+            passed_pawns: [BB_NONE; 2],
+        };
+        PawnHashTable {
+            records: vec![(0, empty); 1 << size_log2],
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Evaluates the pawn structure on a given board.
+    ///
+    /// `key` must be the pawn hash of the board (see
+    /// `Board::pawn_hash`). Normally, it is updated incrementally as
+    /// moves are played (see `ZobristArrays::pawn_hash_delta`). The
+    /// table is probed first, and only if the pawn structure is not
+    /// found in it, the evaluation is performed and its result
+    /// stored.
+    pub fn evaluate(&mut self, key: u64, board: &Board) -> PawnHashEntry {
+        debug_assert_eq!(key, board.pawn_hash());
+        if let Some(entry) = self.probe(key) {
+            return entry;
+        }
+        let entry = evaluate_pawn_structure(board);
+        self.store(key, entry);
+        entry
+    }
+
+    /// Probes for an entry with a given pawn hash (see
+    /// `Board::pawn_hash`).
+    ///
+    /// Returns `None` if the entry is not found in the table.
+    pub fn probe(&mut self, key: u64) -> Option<PawnHashEntry> {
+        let key = Self::nonzero(key);
+        let record = &self.records[self.slot(key)];
+        if record.0 == key {
+            self.hits += 1;
+            Some(record.1)
+        } else {
+            self.misses += 1;
+            None
+        }
+    }
+
+    /// Stores an entry for a given pawn hash (see `Board::pawn_hash`).
+    ///
+    /// The entry replaces any other entry that occupies the same
+    /// slot.
+    pub fn store(&mut self, key: u64, entry: PawnHashEntry) {
+        let key = Self::nonzero(key);
+        let slot = self.slot(key);
+        self.records[slot] = (key, entry);
+    }
+
+    /// Returns the number of evaluations found in the table.
+    #[inline]
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of evaluations not found in the table.
+    #[inline]
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// A helper method. The key `0` is reserved for empty slots, so
+    /// we make sure that the key is never `0`.
+    #[inline]
+    fn nonzero(key: u64) -> u64 {
+        key | 1
+    }
+
+    /// A helper method. It returns the slot for a given key.
+    #[inline]
+    fn slot(&self, key: u64) -> usize {
+        key as usize & (self.records.len() - 1)
+    }
+}
+
+
+/// The penalty for each doubled pawn.
+const DOUBLED_PAWN_PENALTY: Value = 10;
+
+/// The penalty for each isolated pawn.
+const ISOLATED_PAWN_PENALTY: Value = 15;
+
+/// The bonus for each passed pawn, multiplied by the number of ranks
+/// it has advanced.
+const PASSED_PAWN_BONUS: Value = 10;
+
+
+/// A helper function for `PawnHashTable`. It evaluates the pawn
+/// structure on a given board.
+fn evaluate_pawn_structure(board: &Board) -> PawnHashEntry {
+    let pawns = [board.pieces.color[WHITE] & board.pieces.piece_type[PAWN],
+                 board.pieces.color[BLACK] & board.pieces.piece_type[PAWN]];
+    let mut values = [0; 2];
+    let mut passed_pawns = [BB_NONE; 2];
+    for us in 0..2 {
+        let them = opposite(us);
+        let mut bb = pawns[us];
+        while bb != 0 {
+            let square = bsf_reset(&mut bb);
+            let file = square % 8;
+            let rank = square / 8;
+            let file_bb = BB_FILE_A << file;
+            let adjacent_files_bb = gen_shift(file_bb & !BB_FILE_A, -1) |
+                                    gen_shift(file_bb & !BB_FILE_H, 1);
+            if pawns[us] & file_bb & below_lsb(1 << square) != 0 {
+                values[us] -= DOUBLED_PAWN_PENALTY;
+            }
+            if pawns[us] & adjacent_files_bb == 0 {
+                values[us] -= ISOLATED_PAWN_PENALTY;
+            }
+            let ahead = if us == WHITE {
+                above_lsb(1 << square) & !bb_rank(square)
+            } else {
+                below_lsb(1 << square) & !bb_rank(square)
+            };
+            if pawns[them] & ahead & (file_bb | adjacent_files_bb) == 0 {
+                passed_pawns[us] |= 1 << square;
+                let advanced_ranks = if us == WHITE { rank - 1 } else { 6 - rank };
+                values[us] += PASSED_PAWN_BONUS * advanced_ranks as Value;
+            }
+        }
+    }
+    PawnHashEntry {
+        value: values[WHITE] - values[BLACK],
+        passed_pawns,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use squares::*;
+
+    #[test]
+    fn evaluate() {
+        let mut t = PawnHashTable::new(10);
+        let b1 = Board::from_fen("4k3/p7/8/8/3P4/3P4/8/4K3 w - - 0 1").ok().unwrap();
+        let b2 = Board::from_fen("3qk3/p7/8/8/3P4/3P4/8/R3K3 b - - 0 1").ok().unwrap();
+        let e1 = t.evaluate(b1.pawn_hash(), &b1);
+        assert_eq!((t.hits(), t.misses()), (0, 1));
+        let e2 = t.evaluate(b2.pawn_hash(), &b2);
+        assert_eq!((t.hits(), t.misses()), (1, 1));
+        assert_eq!(e1, e2);
+        assert_eq!(e1.passed_pawns[WHITE], 1 << D4 | 1 << D3);
+        assert_eq!(e1.passed_pawns[BLACK], 1 << A7);
+        let b3 = Board::from_fen("4k3/p7/8/8/3P4/8/8/4K3 w - - 0 1").ok().unwrap();
+        assert!(t.evaluate(b3.pawn_hash(), &b3) != e1);
+        assert_eq!((t.hits(), t.misses()), (1, 2));
+    }
+
+    #[test]
+    fn probe_and_store() {
+        let mut t = PawnHashTable::new(10);
+        let b1 = Board::from_fen("4k3/p7/8/8/3P4/3P4/8/4K3 w - - 0 1").ok().unwrap();
+        let b2 = Board::from_fen("2rk4/p7/8/8/3P4/3P4/2N5/6K1 b - - 0 1").ok().unwrap();
+        let b3 = Board::from_fen("4k3/p7/8/8/3P4/8/3P4/4K3 w - - 0 1").ok().unwrap();
+        assert_eq!(b1.pawn_hash(), b2.pawn_hash());
+        assert!(b1.pawn_hash() != b3.pawn_hash());
+        let entry = PawnHashEntry {
+            value: 42,
+            passed_pawns: [1 << D4, 1 << A7],
+        };
+        assert_eq!(t.probe(b1.pawn_hash()), None);
+        t.store(b1.pawn_hash(), entry);
+        assert_eq!(t.probe(b2.pawn_hash()), Some(entry));
+        assert_eq!(t.probe(b3.pawn_hash()), None);
+        assert_eq!((t.hits(), t.misses()), (1, 2));
+    }
+}
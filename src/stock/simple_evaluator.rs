@@ -10,6 +10,13 @@ use bitsets::*;
 
 /// A simple evaluator that adds a random number to the available
 /// material.
+///
+/// The evaluation is always from the point of view of the side to
+/// move, and includes a small bonus for having the move (`TEMPO`).
+/// The random number depends only on the occupied squares. Apart from
+/// it, a position and its color-flipped mirror image get the same
+/// evaluation, and evaluating the same board with each side to move
+/// gives values whose sum is twice the tempo bonus.
 #[derive(Clone, Copy)]
 pub struct SimpleEvaluator {
     material: Value,
@@ -20,7 +27,7 @@ impl SetOption for SimpleEvaluator {}
 impl Evaluator for SimpleEvaluator {
     fn new(position: &Board) -> SimpleEvaluator {
         let us = position.to_move;
-        let them = 1 ^ us;
+        let them = opposite(us);
         let color = position.pieces.color;
         let mut material = 0;
         for piece in QUEEN..PIECE_NONE {
@@ -44,9 +51,12 @@ impl Evaluator for SimpleEvaluator {
 
     #[inline]
     fn evaluate(&self, position: &Board) -> Value {
-        let k = (position.occupied >> 32 ^ position.occupied) as u32;
-        let random_number = (k.wrapping_mul(2654435769) >> 27) as Value;
-        self.material + random_number
+        let material = if is_likely_dead_draw(position) {
+            self.material / DEAD_DRAW_SCALE
+        } else {
+            self.material
+        };
+        material + random_number(position) + TEMPO
     }
 
     #[allow(unused_variables)]
@@ -60,6 +70,53 @@ impl Evaluator for SimpleEvaluator {
 const PIECE_VALUES: [Value; 8] = [10000, 975, 500, 325, 325, 100, 0, 0];
 
 
+/// The bonus for the side to move.
+pub const TEMPO: Value = 10;
+
+
+/// The material advantage is divided by this number in likely dead
+/// draws.
+const DEAD_DRAW_SCALE: Value = 16;
+
+
+/// Returns if the position is very likely a draw, even though it is
+/// still possible to checkmate.
+///
+/// This is the case when there are no pawns, and one of the sides has
+/// only its king, while the other has at most two knights, or a single
+/// minor piece. King and bishop versus king and bishop with bishops on
+/// the same color is a likely dead draw too. This function is used
+/// only to guide the evaluation, and must never be used to declare
+/// the game drawn.
+fn is_likely_dead_draw(position: &Board) -> bool {
+    let piece_type = &position.pieces.piece_type;
+    if piece_type[QUEEN] | piece_type[ROOK] | piece_type[PAWN] != 0 {
+        return false;
+    }
+    let color = &position.pieces.color;
+    let bishops = piece_type[BISHOP];
+    let knights = piece_type[KNIGHT];
+    let minors = [(bishops | knights) & color[WHITE], (bishops | knights) & color[BLACK]];
+    match (pop_count(minors[WHITE]), pop_count(minors[BLACK])) {
+        (0, n) | (n, 0) => n <= 1 || (n == 2 && bishops == 0),
+        (1, 1) => {
+            let dark = bishops & BB_DARK_SQUARES;
+            knights == 0 && (dark == 0 || dark == bishops)
+        }
+        _ => false,
+    }
+}
+
+
+/// Returns a pseudo-random number between 0 and 31 that depends only
+/// on the set of occupied squares.
+#[inline]
+fn random_number(position: &Board) -> Value {
+    let k = (position.occupied >> 32 ^ position.occupied) as u32;
+    (k.wrapping_mul(2654435769) >> 27) as Value
+}
+
+
 #[inline]
 fn gained_material(m: Move) -> Value {
     if m.move_type() == MOVE_PROMOTION {
@@ -75,3 +132,70 @@ fn gained_material(m: Move) -> Value {
         unsafe { *PIECE_VALUES.get_unchecked(m.captured_piece()) }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirror(b: &Board) -> Board {
+        let mut pieces = b.pieces.clone();
+        for bb in pieces.piece_type.iter_mut() {
+            *bb = bb.swap_bytes();
+        }
+        pieces.color = [b.pieces.color[BLACK].swap_bytes(), b.pieces.color[WHITE].swap_bytes()];
+        let c = b.castling_rights.value();
+        Board {
+            pieces,
+            to_move: opposite(b.to_move),
+            castling_rights: CastlingRights::new(c >> 2 | c << 2),
+            enpassant_file: b.enpassant_file,
+            occupied: b.occupied.swap_bytes(),
+        }
+    }
+
+    #[test]
+    fn tempo() {
+        for fen in ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                    "rnbnkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                    "r3k3/p7/8/8/8/8/P7/R3K3 b - - 0 1",
+                    "4k3/3p4/8/8/8/2N5/PP6/4K3 b - - 0 1"]
+                .iter() {
+            let b = Board::from_fen(fen).ok().unwrap();
+            let m = mirror(&b);
+            assert_eq!(SimpleEvaluator::new(&b).evaluate(&b) - random_number(&b),
+                       SimpleEvaluator::new(&m).evaluate(&m) - random_number(&m));
+
+            let mut other_side = b.clone();
+            other_side.to_move = opposite(b.to_move);
+            assert_eq!(SimpleEvaluator::new(&b).evaluate(&b) +
+                       SimpleEvaluator::new(&other_side).evaluate(&other_side),
+                       2 * (TEMPO + random_number(&b)));
+        }
+    }
+
+    #[test]
+    fn dead_draw() {
+        for &(fen, dead_draw) in [("8/8/8/4k3/8/8/8/2N1K1N1 w - - 0 1", true),
+                                  ("8/8/8/4k3/8/8/8/2N1K1N1 b - - 0 1", true),
+                                  ("8/8/8/4k3/8/8/8/4K1B1 w - - 0 1", true),
+                                  ("8/4b3/8/4k3/8/8/8/4K1B1 w - - 0 1", true),
+                                  ("8/5b2/8/4k3/8/8/8/4K1B1 w - - 0 1", false),
+                                  ("8/8/8/4k3/8/8/8/3QK3 w - - 0 1", false),
+                                  ("8/8/8/4k3/8/8/8/2B1K1N1 w - - 0 1", false),
+                                  ("8/8/8/4k3/8/8/8/2B1KB2 w - - 0 1", false),
+                                  ("8/8/8/4k3/8/8/4P3/2N1K1N1 w - - 0 1", false)]
+                .iter() {
+            let b = Board::from_fen(fen).ok().unwrap();
+            assert_eq!(is_likely_dead_draw(&b), dead_draw);
+        }
+
+        // The evaluation is scaled toward zero.
+        let b = Board::from_fen("8/8/8/4k3/8/8/8/2N1K1N1 w - - 0 1").ok().unwrap();
+        let v = SimpleEvaluator::new(&b).evaluate(&b);
+        assert!(v - random_number(&b) - TEMPO < PIECE_VALUES[PAWN]);
+        let b = Board::from_fen("8/8/8/4k3/8/8/8/3QK3 w - - 0 1").ok().unwrap();
+        let v = SimpleEvaluator::new(&b).evaluate(&b);
+        assert_eq!(v - random_number(&b) - TEMPO, PIECE_VALUES[QUEEN]);
+    }
+}
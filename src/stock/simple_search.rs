@@ -2,9 +2,9 @@
 
 use std::mem;
 use std::mem::MaybeUninit;
-use std::cmp::max;
+use std::cmp::{min, max};
 use std::thread;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::sync::mpsc::{Sender, Receiver};
 use std::marker::PhantomData;
 use std::ops::Deref;
@@ -40,9 +40,16 @@ use utils::MoveStack;
 /// depth for moves that are ordered closer to the end (likely
 /// fail-low nodes).
 ///
-/// **Important note:** `SimpleSearch` ignores the `searchmoves`
-/// search parameter. It always analyses all legal moves in the root
-/// position.
+/// *Futility pruning* discards the quiet moves near the leaves of the
+/// tree, when the static evaluation of the position is so far below
+/// alpha that a quiet move is very unlikely to raise it.
+///
+/// *Late move pruning* discards the quiet moves near the leaves of the
+/// tree, once enough quiet moves have been tried without success.
+///
+/// **Important note:** `SimpleSearch` always analyses all legal
+/// moves in the root position. The `searchmoves` search parameter
+/// only determines the order in which the root moves are tried.
 pub struct SimpleSearch<T: Ttable, N: SearchNode> {
     phantom_t: PhantomData<T>,
     phantom_n: PhantomData<N>,
@@ -56,10 +63,13 @@ impl<T, N> Search for SimpleSearch<T, N>
 
     type SearchNode = N;
 
-    type ReportData = ();
+    type ReportData = OrderingStats;
+
+    type State = SimpleSearchState;
 
     fn spawn(params: SearchParams<Self::SearchNode>,
              tt: Arc<Self::Ttable>,
+             state: Arc<Self::State>,
              reports_tx: Sender<SearchReport<Self::ReportData>>,
              messages_rx: Receiver<String>)
              -> thread::JoinHandle<Value> {
@@ -69,7 +79,7 @@ impl<T, N> Search for SimpleSearch<T, N>
         debug_assert!(params.lower_bound != VALUE_UNKNOWN);
         debug_assert!(params.searchmoves.is_empty() ||
                       contains_same_moves(&params.searchmoves, &params.position.legal_moves()),
-                      "SimpleSearch ignores searchmoves");
+                      "SimpleSearch analyses all legal moves");
         thread::spawn(move || {
             let SearchParams {
                 search_id,
@@ -77,6 +87,10 @@ impl<T, N> Search for SimpleSearch<T, N>
                 depth,
                 lower_bound,
                 upper_bound,
+                searchmoves,
+                report_interval,
+                contempt,
+                analyse_mode,
                 ..
             } = params;
             let report = SearchReport {
@@ -84,7 +98,7 @@ impl<T, N> Search for SimpleSearch<T, N>
                 searched_nodes: 0,
                 depth: 0,
                 value: VALUE_UNKNOWN,
-                data: (),
+                data: OrderingStats::default(),
                 done: false,
             };
             let mut reporting = |searched_nodes| {
@@ -103,17 +117,26 @@ impl<T, N> Search for SimpleSearch<T, N>
             let mut move_stack = MoveStack::new();
             let mut search =
                 SearchRunner::new(position, tt.deref(), &mut move_stack, &mut reporting);
+            if let Some(n) = report_interval {
+                search.set_report_interval(n);
+            }
+            search.contempt = contempt;
+            search.exact_pv = analyse_mode;
+            search.set_root_move_order(searchmoves);
+            search.load_history(&state);
             let (depth, value) = if let Ok(v) =
                 search.run(lower_bound, upper_bound, depth, Move::invalid()) {
                 (depth, v)
             } else {
                 (0, VALUE_UNKNOWN)
             };
+            search.save_history(&state);
             reports_tx
                 .send(SearchReport {
                           searched_nodes: search.node_count(),
                           depth: depth,
                           value: value,
+                          data: search.ordering_stats(),
                           done: true,
                           ..report
                       })
@@ -125,7 +148,15 @@ impl<T, N> Search for SimpleSearch<T, N>
 
 impl<T: Ttable, N: SearchNode> SetOption for SimpleSearch<T, N> {
     fn options() -> Vec<(&'static str, OptionDescription)> {
-        N::options()
+        let mut options = vec![("Contempt",
+                                OptionDescription::Spin {
+                                    min: -100,
+                                    max: 100,
+                                    default: 0,
+                                }),
+                               ("UCI_AnalyseMode", OptionDescription::Check { default: false })];
+        options.extend(N::options());
+        options
     }
 
     fn set_option(name: &str, value: &str) {
@@ -134,10 +165,96 @@ impl<T: Ttable, N: SearchNode> SetOption for SimpleSearch<T, N> {
 }
 
 
+/// Move ordering statistics, sent with `SimpleSearch`'s reports.
+///
+/// Good move ordering causes most of the beta cut-offs to happen on
+/// the first searched move. Therefore, the rate of first-move
+/// cut-offs is a good measure of the quality of the move ordering.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OrderingStats {
+    /// The number of beta cut-offs.
+    pub cutoffs: u64,
+
+    /// The number of beta cut-offs caused by the first searched move.
+    pub first_move_cutoffs: u64,
+}
+
+impl OrderingStats {
+    /// Returns the fraction (between `0.0` and `1.0`) of the beta
+    /// cut-offs that were caused by the first searched move, or
+    /// `None` if there were no cut-offs.
+    pub fn first_move_cutoff_rate(&self) -> Option<f64> {
+        if self.cutoffs == 0 {
+            None
+        } else {
+            Some(self.first_move_cutoffs as f64 / self.cutoffs as f64)
+        }
+    }
+}
+
+
 /// Represents a terminated search condition.
 struct TerminatedSearch;
 
 
+/// The type of a node in the game tree.
+///
+/// Nodes searched with a null window are classified according to
+/// their expected role: the children of root, PV, and All nodes are
+/// expected to fail high (Cut nodes), and the children of Cut nodes
+/// are expected to fail low (All nodes).
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NodeType {
+    /// The root node.
+    Root,
+
+    /// A node searched with an open window.
+    Pv,
+
+    /// A node searched with a null window, that is expected to cause
+    /// a beta cut-off.
+    Cut,
+
+    /// A node searched with a null window, in which all moves are
+    /// expected to be searched.
+    All,
+}
+
+impl NodeType {
+    /// Returns if the node is on the principal variation (a root or
+    /// a PV node).
+    #[inline]
+    fn is_pv(self) -> bool {
+        match self {
+            NodeType::Root | NodeType::Pv => true,
+            NodeType::Cut | NodeType::All => false,
+        }
+    }
+}
+
+
+/// Records a search depth decision (debug builds only).
+///
+/// `new_depth` is the depth with which the position reached by `m`
+/// was searched. Values smaller than `depth - 1` mean that the move
+/// was reduced, bigger values mean that it was extended.
+/// `move_number` and `quiet_move_number` give the position of `m`
+/// among all tried moves, and among the tried quiet moves (`0` for
+/// null moves).
+#[cfg(debug_assertions)]
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+struct DepthRecord {
+    ply: usize,
+    node_type: NodeType,
+    m: Move,
+    move_number: usize,
+    quiet_move_number: usize,
+    depth: Depth,
+    new_depth: Depth,
+}
+
+
 /// Represents a game tree search.
 struct SearchRunner<'a, T, N>
     where T: Ttable + 'a,
@@ -145,12 +262,32 @@ struct SearchRunner<'a, T, N>
 {
     tt: &'a T,
     killers: KillerTable,
+    killers_enabled: bool,
+    late_move_pruning: bool,
+    history: Option<HistoryTable>,
+    root_move_order: Vec<Move>,
+    ordering_stats: OrderingStats,
     position: N,
     moves: &'a mut MoveStack,
     state_stack: Vec<NodeState>,
     reported_nodes: u64,
     unreported_nodes: u64,
+    report_interval: u64,
     report_function: &'a mut dyn FnMut(u64) -> bool,
+
+    // How much (in centipawns) a draw is considered worse than an
+    // equal position, from the point of view of the side to move at
+    // the root.
+    contempt: Value,
+
+    // Whether the transposition table is prevented from giving the
+    // results for PV nodes. This is slower, but gives exact values
+    // for the PV nodes, which is what we want when analysing.
+    exact_pv: bool,
+
+    // Depth reduction/extension decisions, recorded when debugging.
+    #[cfg(debug_assertions)]
+    trace: Option<Vec<DepthRecord>>,
 }
 
 impl<'a, T, N> SearchRunner<'a, T, N>
@@ -172,15 +309,114 @@ impl<'a, T, N> SearchRunner<'a, T, N>
         SearchRunner {
             tt: tt,
             killers: KillerTable::new(),
+            killers_enabled: true,
+            late_move_pruning: true,
+            history: Some(HistoryTable::new()),
+            root_move_order: vec![],
+            ordering_stats: OrderingStats::default(),
             position: root,
             moves: move_stack,
             state_stack: Vec::with_capacity(32),
             reported_nodes: 0,
             unreported_nodes: 0,
+            report_interval: if cfg!(debug_assertions) {
+                NODE_COUNT_REPORT_INTERVAL / 100
+            } else {
+                NODE_COUNT_REPORT_INTERVAL
+            },
             report_function: report_function,
+            contempt: 0,
+            exact_pv: false,
+            #[cfg(debug_assertions)]
+            trace: None,
+        }
+    }
+
+    /// Sets the number of nodes that will be searched without
+    /// reporting search progress.
+    ///
+    /// Smaller intervals make the search more responsive to
+    /// termination requests, but slower.
+    pub fn set_report_interval(&mut self, n: u64) {
+        self.report_interval = max(1, n);
+    }
+
+    /// Sets the order in which the moves in the root position will
+    /// be tried.
+    ///
+    /// Usually `moves` will be the root moves sorted by their values
+    /// from the previous iteration of an iterative deepening. The
+    /// hash move is still tried first. Moves not in `moves` are tried
+    /// last. All moves in `moves` must be legal.
+    pub fn set_root_move_order(&mut self, moves: Vec<Move>) {
+        self.root_move_order = moves;
+    }
+
+    /// Stops using the history heuristic for ordering quiet moves.
+    ///
+    /// This is useful for measuring how much the history heuristic
+    /// helps.
+    #[allow(dead_code)]
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Stops using killer moves.
+    ///
+    /// This is useful for measuring how much the killer moves help.
+    #[allow(dead_code)]
+    pub fn disable_killers(&mut self) {
+        self.killers_enabled = false;
+    }
+
+    /// Stops pruning late quiet moves.
+    ///
+    /// This is useful for measuring how much late move pruning helps.
+    #[allow(dead_code)]
+    pub fn disable_late_move_pruning(&mut self) {
+        self.late_move_pruning = false;
+    }
+
+    /// Returns the move ordering statistics collected so far.
+    pub fn ordering_stats(&self) -> OrderingStats {
+        self.ordering_stats
+    }
+
+    /// Continues with the history heuristic statistics collected by
+    /// the previous searches.
+    pub fn load_history(&mut self, state: &SimpleSearchState) {
+        if self.history.is_some() {
+            self.history = Some(state.history.read().unwrap().clone());
+        }
+    }
+
+    /// Saves the collected history heuristic statistics, so that
+    /// future searches can use them.
+    pub fn save_history(&self, state: &SimpleSearchState) {
+        if let Some(ref h) = self.history {
+            *state.history.write().unwrap() = h.clone();
         }
     }
 
+    /// Starts recording all depth reduction/extension decisions
+    /// (debug builds only).
+    ///
+    /// The trace grows with every searched node, so this should be
+    /// used only for shallow searches.
+    #[cfg(debug_assertions)]
+    #[allow(dead_code)]
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(vec![]);
+    }
+
+    /// Returns the depth reduction/extension decisions recorded so
+    /// far (debug builds only).
+    #[cfg(debug_assertions)]
+    #[allow(dead_code)]
+    pub fn trace(&self) -> &[DepthRecord] {
+        self.trace.as_ref().map_or(&[], |t| t.as_slice())
+    }
+
     /// Performs a game tree search and returns the result.
     ///
     /// `alpha` and `beta` together give the interval within which an
@@ -223,8 +459,51 @@ impl<'a, T, N> SearchRunner<'a, T, N>
 
         debug_assert!(alpha < beta);
         let mut value = VALUE_UNKNOWN;
+        let node_type = if self.state_stack.is_empty() {
+            NodeType::Root
+        } else if beta as isize - alpha as isize > 1 {
+            NodeType::Pv
+        } else {
+            match self.state_stack.last().unwrap().node_type {
+                NodeType::Cut => NodeType::All,
+                _ => NodeType::Cut,
+            }
+        };
+
+        // When in check, standing pat on the static evaluation is
+        // wrong (the position is not quiet, and may be a checkmate).
+        // Therefore, instead of doing quiescence search, we search
+        // all check evasions.
+        let depth = if depth <= 0 && self.position.is_check() {
+            1
+        } else {
+            depth
+        };
+
+        // Never go deeper than `DEPTH_MAX` half-moves from the root.
+        // (This may happen with full-window searches that do not
+        // reduce the depth.)
+        let depth = if self.state_stack.len() + 1 >= DEPTH_MAX as usize {
+            0
+        } else {
+            depth
+        };
 
-        if let Some(v) = self.node_begin(alpha, beta, depth, last_move)? {
+        // Mate distance pruning. No position can be better than
+        // checkmate in one half-move, and no position that is not in
+        // check can be worse than being checkmated in two half-moves.
+        // So, if a shorter checkmate has been found already, there is
+        // no point in searching this position.
+        if node_type != NodeType::Root {
+            if alpha >= VALUE_MAX - 1 {
+                return Ok(alpha);
+            }
+            if beta <= VALUE_MIN + 2 && !self.position.is_check() {
+                return Ok(beta);
+            }
+        }
+
+        if let Some(v) = self.node_begin(alpha, beta, depth, node_type, last_move)? {
             // We already have the final result.
             value = v;
 
@@ -233,43 +512,99 @@ impl<'a, T, N> SearchRunner<'a, T, N>
             debug_assert!(depth > 0);
             let mut bound = BOUND_EXACT;
             let mut best_move = Move::invalid();
+            let mut move_number = 0;
+            let mut quiet_move_number = 0;
+
+            // Futility pruning. At frontier nodes, quiet moves that
+            // can not bring the static evaluation (plus a safety
+            // margin) above `alpha` are not searched at all. This is
+            // never done on PV nodes, nor when in check.
+            let futility_value = {
+                let state = self.state_stack.last().unwrap();
+                let is_check = unsafe { state.is_check.assume_init() };
+                if depth <= FUTILITY_DEPTH && !node_type.is_pv() && !is_check {
+                    min(state.static_eval + FUTILITY_MARGINS[depth as usize], VALUE_EVAL_MAX)
+                } else {
+                    VALUE_MAX
+                }
+            };
+
+            // Late move pruning. At nodes close to the leaves, only
+            // the first few quiet moves are searched. This is never
+            // done on PV nodes, nor when in check.
+            let quiet_move_limit = {
+                let state = self.state_stack.last().unwrap();
+                let is_check = unsafe { state.is_check.assume_init() };
+                if self.late_move_pruning && depth <= LATE_MOVE_PRUNING_DEPTH &&
+                   !node_type.is_pv() && !is_check {
+                    late_move_count(depth)
+                } else {
+                    usize::MAX
+                }
+            };
 
             // Try moves.
             while let Some(m) = self.do_move() {
+                // The child position's TT entry will be needed soon.
+                self.tt.prefetch(self.position.hash());
                 self.report_progress(1)?;
+                move_number += 1;
+
 
                 // Make a recursive call.
-                let mut v = if m.score() > REDUCTION_THRESHOLD {
+                let v = if m.score() > REDUCTION_THRESHOLD {
                     // The moves that have good chances to cause a
                     // beta cut-off we analyze with a full depth and
                     // fully open window (alpha, beta). We hope that
                     // at least one of these moves will raise `alpha`.
-                    -self.run(-beta, -alpha, depth - 1, m)?
+                    self.run_child(alpha, beta, depth - 1, m)?
+                } else if futility_value <= alpha {
+                    // A futile quiet move. (The hash move, the killer
+                    // moves, captures, promotions, checks, and check
+                    // evasions all have high move scores, and are
+                    // never pruned.) The move has not been searched,
+                    // so it must not become the best move.
+                    self.undo_move();
+                    if futility_value > value {
+                        value = futility_value;
+                        bound = BOUND_UPPER;
+                    }
+                    continue;
+                } else if quiet_move_number >= quiet_move_limit {
+                    // A late quiet move. (Checks and check evasions
+                    // have high move scores, and are never pruned.)
+                    // We do not know its value, so we can only tell
+                    // that the position is not better than `alpha`.
+                    self.undo_move();
+                    if alpha > value {
+                        value = alpha;
+                    }
+                    continue;
                 } else {
+                    quiet_move_number += 1;
+
                     // For the rest of the moves we first try to prove
                     // that they are not better than our current best
                     // move. For this purpose we search them with a
                     // reduced depth and a null window (alpha, alpha +
                     // 1). Only if it seems that the move is better
                     // than our current best move, we do a full-depth,
-                    // full-window search.
-                    match -self.run(-alpha - 1, -alpha, depth - 2, m)? {
+                    // full-window search. Moves on the principal
+                    // variation are never reduced.
+                    let reduced_depth = if node_type.is_pv() {
+                        depth - 1
+                    } else {
+                        depth - 1 - late_move_reduction(depth, move_number, node_type)
+                    };
+                    self.trace_depth(m, move_number, quiet_move_number, depth, reduced_depth);
+                    match self.run_child(alpha, alpha + 1, reduced_depth, m)? {
                         v if v <= alpha => v,
-                        _ => -self.run(-beta, -alpha, depth - 1, m)?,
+                        _ => self.run_child(alpha, beta, depth - 1, m)?,
                     }
                 };
                 self.undo_move();
                 debug_assert!(v > VALUE_UNKNOWN);
 
-                // Increase/decrease the value for a checkmate by one
-                // on every half-move. This way the engine will seek
-                // for the fastest checkmate possible.
-                if v < VALUE_EVAL_MIN - 1 {
-                    v += 1;
-                } else if v > VALUE_EVAL_MAX + 1 {
-                    v -= 1;
-                }
-
                 // See how good this move was.
                 if v >= beta {
                     // This move is so good, that the opponent will
@@ -279,7 +614,14 @@ impl<'a, T, N> SearchRunner<'a, T, N>
                     best_move = m;
                     value = v;
                     bound = BOUND_LOWER;
+                    self.ordering_stats.cutoffs += 1;
+                    if move_number == 1 {
+                        self.ordering_stats.first_move_cutoffs += 1;
+                    }
                     self.register_killer_move(m);
+                    if let Some(ref mut h) = self.history {
+                        h.register(m, depth);
+                    }
                     break;
                 }
                 if v > value {
@@ -301,14 +643,47 @@ impl<'a, T, N> SearchRunner<'a, T, N>
                 debug_assert_eq!(bound, BOUND_EXACT);
             }
 
-            // Store the result to the transposition table.
-            self.store(value, bound, depth, best_move);
+            if value == 0 && best_move == Move::invalid() {
+                // This is a draw. The value of a draw depends on the
+                // node type, so we do not store it to the
+                // transposition table.
+                value = self.draw_value(node_type);
+            } else {
+                // Store the result to the transposition table.
+                self.store(value, bound, depth, best_move);
+            }
         }
 
         self.node_end();
         Ok(value)
     }
 
+    /// A helper method for `run`. It searches the position reached
+    /// by the last played move `m`, and returns its value from the
+    /// perspective of the current position.
+    ///
+    /// Values for checkmate are increased/decreased by one on every
+    /// half-move. This way the engine will seek for the fastest
+    /// checkmate possible, and the returned values tell the distance
+    /// to checkmate. The `(alpha, beta)` window is adjusted
+    /// accordingly before it is passed to the child position.
+    fn run_child(&mut self,
+                 alpha: Value,
+                 beta: Value,
+                 depth: Depth,
+                 m: Move)
+                 -> Result<Value, TerminatedSearch> {
+        let (child_alpha, child_beta) = child_window(alpha, beta);
+        let v = -self.run(child_alpha, child_beta, depth, m)?;
+        Ok(if v < VALUE_EVAL_MIN - 1 {
+               v + 1
+           } else if v > VALUE_EVAL_MAX + 1 {
+               v - 1
+           } else {
+               v
+           })
+    }
+
     /// Returns the number of searched positions.
     #[inline]
     pub fn node_count(&self) -> u64 {
@@ -321,44 +696,38 @@ impl<'a, T, N> SearchRunner<'a, T, N>
     /// This method tries to calculate and return the value for the
     /// node. It basically does 3 things:
     ///
-    /// 1. Checks if the transposition table has the result.
+    /// 1. Checks if the transposition table has the result (not on
+    ///    PV nodes in analysis mode).
     /// 2. On leaf nodes, performs quiescence search.
-    /// 3. Performs null move pruning if possible.
+    /// 3. Performs null move pruning if possible (not on PV nodes).
     fn node_begin(&mut self,
                   alpha: Value,
                   beta: Value,
                   depth: Depth,
+                  node_type: NodeType,
                   last_move: Move)
                   -> Result<Option<Value>, TerminatedSearch> {
         // Probe the transposition table.
         let hash = self.position.hash();
-        let (entry, static_eval) = if let Some(e) = self.tt.probe(hash) {
-            match e.static_eval() {
-                VALUE_UNKNOWN => {
-                    (e,
-                     self.position
-                         .evaluator()
-                         .evaluate(self.position.board()))
-                }
-                v => (e, v),
-            }
+        let entry = if let Some(e) = self.tt.probe(hash) {
+            e
         } else {
-            let v = self.position
-                .evaluator()
-                .evaluate(self.position.board());
-            (T::Entry::new(0, BOUND_NONE, 0).set_static_eval(v), v)
+            T::Entry::new(0, BOUND_NONE, 0)
         };
         self.state_stack
             .push(NodeState {
                       phase: NodePhase::Pristine,
+                      node_type,
                       hash_move_digest: entry.move_digest(),
-                      static_eval: static_eval,
+                      static_eval: entry.static_eval(),
                       is_check: MaybeUninit::<bool>::uninit(), // We will initialize this soon!
                       killer: None,
                   });
 
-        // Check if the TT entry gives the result.
-        if entry.depth() >= depth {
+        // Check if the TT entry gives the result. In analysis mode,
+        // PV nodes are always searched, so that their values are
+        // exact. (The hash move is still tried first.)
+        if entry.depth() >= depth && !(self.exact_pv && node_type.is_pv()) {
             let value = entry.value();
             let bound = entry.bound();
             if (value >= beta && bound & BOUND_LOWER != 0) ||
@@ -368,6 +737,19 @@ impl<'a, T, N> SearchRunner<'a, T, N>
             };
         };
 
+        // Evaluate the position, unless the TT entry has the static
+        // evaluation already. (The static evaluation will be written
+        // to the TT together with the result for the node.)
+        let static_eval = {
+            let state = self.state_stack.last_mut().unwrap();
+            if state.static_eval == VALUE_UNKNOWN {
+                state.static_eval = self.position
+                    .evaluator()
+                    .evaluate(self.position.board());
+            }
+            state.static_eval
+        };
+
         // On leaf nodes, do quiescence search.
         if depth <= 0 {
             let result = self.position.qsearch(depth, alpha, beta, static_eval);
@@ -399,8 +781,12 @@ impl<'a, T, N> SearchRunner<'a, T, N>
         // trying a "null" or "passing" move, then seeing if the score
         // of the sub-tree search is still high enough to cause a beta
         // cutoff. Nodes are saved by reducing the depth of the
-        // sub-tree under the null move.
-        if !last_move.is_null() && static_eval >= beta &&
+        // sub-tree under the null move. A null move can not prove
+        // that the position is not lost (quiescence search reports
+        // checkmates as `VALUE_EVAL_MAX`), so we do not try it when
+        // `beta` designates inevitable checkmate.
+        if !node_type.is_pv() && !last_move.is_null() && static_eval >= beta &&
+           beta > VALUE_EVAL_MIN &&
            {
                let p = &self.position;
                !p.evaluator().is_zugzwangy(p.board())
@@ -423,6 +809,7 @@ impl<'a, T, N> SearchRunner<'a, T, N>
             // Play a null move and search.
             let m = self.position.null_move();
             if self.position.do_move(m) {
+                self.trace_depth(m, 0, 0, depth, max(0, reduced_depth - 1));
                 let value = -self.run(-beta, -alpha, max(0, reduced_depth - 1), m)?;
                 self.position.undo_last_move();
                 if value >= beta {
@@ -443,6 +830,34 @@ impl<'a, T, N> SearchRunner<'a, T, N>
         Ok(None)
     }
 
+    /// A helper method. It records a depth reduction/extension
+    /// decision if tracing is enabled.
+    #[inline]
+    #[allow(unused_variables)]
+    fn trace_depth(&mut self,
+                   m: Move,
+                   move_number: usize,
+                   quiet_move_number: usize,
+                   depth: Depth,
+                   new_depth: Depth) {
+        #[cfg(debug_assertions)]
+        {
+            let ply = self.state_stack.len();
+            let node_type = self.state_stack.last().unwrap().node_type;
+            if let Some(ref mut t) = self.trace {
+                t.push(DepthRecord {
+                           ply,
+                           node_type,
+                           m,
+                           move_number,
+                           quiet_move_number,
+                           depth,
+                           new_depth,
+                       });
+            }
+        }
+    }
+
     /// A helper method for `run`. Each call to `run` ends with a call
     /// to `node_end`.
     #[inline]
@@ -507,39 +922,55 @@ impl<'a, T, N> SearchRunner<'a, T, N>
                 self.moves.pull_move(state.hash_move_digest);
             }
 
-            // Set move scores to captures and pawn promotions to
-            // queen according to their static exchange evaluation.
-            for m in self.moves.list_mut().iter_mut() {
-                let move_score = if m.move_type() == MOVE_PROMOTION {
-                    if m.aux_data() == 0 {
-                        MOVE_SCORE_MAX - 1
-                    } else {
-                        0
-                    }
-                } else if m.captured_piece() < PIECE_NONE {
-                    match self.position.evaluate_move(*m) {
-                        see if see > 0 => MOVE_SCORE_MAX - 1,
-                        see if see == 0 => MOVE_SCORE_MAX - 2,
-                        _ => 0,
-                    }
-                } else {
-                    0
-                };
-                m.set_score(move_score);
+            if ply == 0 && !self.root_move_order.is_empty() {
+                // At the root, follow the given move order.
+                state.phase = NodePhase::OrderedRootMoves;
+                for m in self.moves.list_mut().iter_mut() {
+                    let digest = m.digest();
+                    let move_score = match self.root_move_order
+                              .iter()
+                              .position(|x| x.digest() == digest) {
+                        Some(i) => MOVE_SCORE_MAX - 1 - i as u32,
+                        None => 0,
+                    };
+                    m.set_score(move_score);
+                }
+            } else {
+                // Set move scores to captures and pawn promotions.
+                for m in self.moves.list_mut().iter_mut() {
+                    let move_score = initial_move_score(&self.position, *m);
+                    m.set_score(move_score);
+                }
             }
         }
 
         // Try the generated moves.
-        while let Some(mut m) = if let NodePhase::TriedLosingCaptures = state.phase {
-                  // After we have tried the losing captures, we try the
-                  // rest of the moves in the order in which they reside in
-                  // the move stack, because at this stage the probability
-                  // of cut-off is low, so the move ordering is not
-                  // important.
+        while let Some(mut m) = if let (&NodePhase::TriedLosingCaptures, None) =
+                  (&state.phase, &self.history) {
+                  // After we have tried the losing captures, and there
+                  // are no history statistics, we try the rest of the
+                  // moves in the order in which they reside in the move
+                  // stack.
                   self.moves.pop()
               } else {
                   self.moves.pull_best()
               } {
+            // The root moves in the given order. (Their original
+            // scores decide the depth reductions.) The moves that are
+            // in `root_move_order` are known to be legal.
+            if let NodePhase::OrderedRootMoves = state.phase {
+                let is_known_legal = m.score() > 0;
+                m.set_score(initial_move_score(&self.position, m));
+                if is_known_legal {
+                    self.position.do_move_unchecked(m);
+                    return Some(m);
+                }
+                if self.position.do_move(m) {
+                    return Some(m);
+                }
+                continue;
+            }
+
             // First -- the winning and even captures and promotions
             // to queen.
             if let NodePhase::GeneratedMoves = state.phase {
@@ -588,21 +1019,29 @@ impl<'a, T, N> SearchRunner<'a, T, N>
                 state.phase = NodePhase::TriedLosingCaptures;
                 self.moves.add_move(m);
 
-                // TODO: Pull selected quiet moves to the top of the
-                // move stack here, using the history
-                // heuristics.
+                // Order the quiet moves by how often they caused beta
+                // cut-offs in other positions (the history
+                // heuristic).
+                if let Some(ref h) = self.history {
+                    for m in self.moves.list_mut().iter_mut() {
+                        m.set_score(h.get(*m));
+                    }
+                }
                 continue;
             }
 
-            // Fourth -- the remaining quiet moves.
+            // Fourth -- the remaining quiet moves. (The history score
+            // must not save them from depth reductions.)
+            if unsafe { state.is_check.assume_init() } || m.move_type() == MOVE_PROMOTION ||
+               self.position.gives_check(m) {
+                // When evading check, giving check, or promoting a
+                // pawn -- set a high move score to avoid search depth
+                // reductions.
+                m.set_score(MOVE_SCORE_MAX);
+            } else {
+                m.set_score(0);
+            }
             if self.position.do_move(m) {
-                if unsafe { state.is_check.assume_init() } ||
-                    self.position.is_check() || m.move_type() == MOVE_PROMOTION {
-                    // When evading check, giving check, or promoting
-                    // a pawn -- set a high move score to avoid search
-                    // depth reductions.
-                    m.set_score(MOVE_SCORE_MAX);
-                }
                 return Some(m);
             }
         }
@@ -627,6 +1066,25 @@ impl<'a, T, N> SearchRunner<'a, T, N>
                        .set_static_eval(self.state_stack.last().unwrap().static_eval));
     }
 
+    /// A helper method for `run`. It returns the value of a drawn
+    /// position.
+    ///
+    /// Contempt is applied only at the root and at PV nodes. At all
+    /// other nodes draws are valued as `0`, so as to avoid search
+    /// instability.
+    #[inline]
+    fn draw_value(&self, node_type: NodeType) -> Value {
+        if !node_type.is_pv() {
+            return 0;
+        }
+        let ply = self.state_stack.len() - 1;
+        if ply & 1 == 0 {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
     /// A helper method for `run`. It reports search progress.
     ///
     /// From time to time, we should report how many nodes has been
@@ -634,13 +1092,8 @@ impl<'a, T, N> SearchRunner<'a, T, N>
     /// opportunity for the search to be terminated.
     #[inline]
     fn report_progress(&mut self, new_nodes: u64) -> Result<(), TerminatedSearch> {
-        let node_count_report_interval = if cfg!(debug_assertions) {
-            NODE_COUNT_REPORT_INTERVAL / 100
-        } else {
-            NODE_COUNT_REPORT_INTERVAL
-        };
         self.unreported_nodes += new_nodes;
-        if self.unreported_nodes >= node_count_report_interval {
+        if self.unreported_nodes >= self.report_interval {
             self.reported_nodes += self.unreported_nodes;
             self.unreported_nodes = 0;
             if (*self.report_function)(self.reported_nodes) {
@@ -654,7 +1107,9 @@ impl<'a, T, N> SearchRunner<'a, T, N>
     /// caused a beta cut-off (a killer move).
     #[inline]
     fn register_killer_move(&mut self, m: Move) {
-        self.killers.register(self.state_stack.len() - 1, m);
+        if self.killers_enabled {
+            self.killers.register(self.state_stack.len() - 1, m);
+        }
     }
 }
 
@@ -663,14 +1118,132 @@ impl<'a, T, N> SearchRunner<'a, T, N>
 const MOVE_SCORE_MAX: u32 = ::std::u32::MAX;
 
 
-/// The number of nodes that will be searched without reporting search
-/// progress.
+/// A helper function. It calculates the move score for a freshly
+/// generated move.
+///
+/// Pawn promotions to queen (including the ones that capture a
+/// piece) get the highest score. Other captures (including
+/// under-promotions that capture a piece) are scored according to
+/// their static exchange evaluation. All other moves get `0`.
+#[inline]
+fn initial_move_score<N: SearchNode>(position: &N, m: Move) -> u32 {
+    if m.move_type() == MOVE_PROMOTION && m.aux_data() == 0 {
+        MOVE_SCORE_MAX - 1
+    } else if m.captured_piece() < PIECE_NONE {
+        match position.evaluate_move(m) {
+            see if see > 0 => MOVE_SCORE_MAX - 1,
+            see if see == 0 => MOVE_SCORE_MAX - 2,
+            _ => 0,
+        }
+    } else {
+        0
+    }
+}
+
+
+/// A helper function. It calculates the `(alpha, beta)` window for a
+/// child position, given the window for the parent position.
+///
+/// Values for checkmate change by one on every half-move (see
+/// `SearchRunner::run_child`), so bounds that designate inevitable
+/// checkmate are shifted by one. The returned window is never
+/// narrower than necessary.
+fn child_window(alpha: Value, beta: Value) -> (Value, Value) {
+    let (alpha, beta) = (alpha as i32, beta as i32);
+    let (eval_min, eval_max) = (VALUE_EVAL_MIN as i32, VALUE_EVAL_MAX as i32);
+    let (value_min, value_max) = (VALUE_MIN as i32, VALUE_MAX as i32);
+
+    // The biggest parent value that does not exceed `alpha`, and the
+    // smallest parent value that reaches `beta`, before the
+    // adjustment for the distance to checkmate.
+    let max_below_alpha = match alpha {
+        a if a > eval_max => a + 1,
+        a if a < eval_min - 1 => a - 1,
+        a => a,
+    };
+    let min_above_beta = match beta {
+        b if b > eval_max + 1 => b + 1,
+        b if b < eval_min => b - 1,
+        b => b,
+    };
+    let child_alpha = (-min_above_beta).clamp(value_min, value_max);
+    let child_beta = (-max_below_alpha).clamp(value_min, value_max);
+    if child_alpha < child_beta {
+        (child_alpha as Value, child_beta as Value)
+    } else if child_beta > value_min {
+        ((child_beta - 1) as Value, child_beta as Value)
+    } else {
+        (child_alpha as Value, (child_alpha + 1) as Value)
+    }
+}
+
+
+/// A helper function. It calculates by how many half-moves the search
+/// depth should be reduced for a move that is not likely to cause a
+/// beta cut-off.
+///
+/// `move_number` is the position of the move in the order in which
+/// the moves are tried (starting from `1`). The first few moves are
+/// reduced by one half-move only. Later moves are reduced more,
+/// especially when the remaining depth is big, and even more in Cut
+/// nodes -- if the first moves did not cause the expected beta
+/// cut-off, the later moves are unlikely to cause it either. The
+/// returned reduction never makes the new depth smaller than `-1`.
+#[inline]
+fn late_move_reduction(depth: Depth, move_number: usize, node_type: NodeType) -> Depth {
+    if move_number <= LMR_FULL_DEPTH_MOVES || depth < 3 {
+        return 1;
+    }
+    let mut r = 1.0 + (depth as f64).ln() * (move_number as f64).ln() / 2.0;
+    if node_type == NodeType::Cut {
+        r += 1.0;
+    }
+    (r as Depth).min(depth)
+}
+
+
+/// A helper function. It returns how many quiet moves should be
+/// searched before the rest of the quiet moves are pruned (see
+/// `LATE_MOVE_COUNTS`).
+#[inline]
+fn late_move_count(depth: Depth) -> usize {
+    debug_assert!(depth > 0 && depth <= LATE_MOVE_PRUNING_DEPTH);
+    LATE_MOVE_COUNTS[depth as usize]
+}
+
+
+/// The number of moves that are reduced by one half-move only (see
+/// `late_move_reduction`).
+const LMR_FULL_DEPTH_MOVES: usize = 4;
+
+
+/// The default number of nodes that will be searched without
+/// reporting search progress.
 ///
 /// If this value is too small the engine may become slow, if this
 /// value is too big the engine may become unresponsive.
 const NODE_COUNT_REPORT_INTERVAL: u64 = 15000;
 
 
+/// The maximum remaining depth at which futility pruning is applied.
+const FUTILITY_DEPTH: Depth = 3;
+
+
+/// The futility pruning margins (in centipawns) for each remaining
+/// depth. Quiet moves are expected to change the static evaluation
+/// by less than that.
+const FUTILITY_MARGINS: [Value; FUTILITY_DEPTH as usize + 1] = [0, 150, 300, 500];
+
+
+/// The maximum remaining depth at which late move pruning is applied.
+const LATE_MOVE_PRUNING_DEPTH: Depth = 3;
+
+
+/// The number of quiet moves searched at each remaining depth, when
+/// late move pruning is applied.
+const LATE_MOVE_COUNTS: [usize; LATE_MOVE_PRUNING_DEPTH as usize + 1] = [0, 4, 7, 12];
+
+
 /// The number of half-moves with which the search depth will be
 /// reduced when trying null moves.
 const NULL_MOVE_REDUCTION: i8 = 3;
@@ -694,6 +1267,7 @@ enum NodePhase {
     ConsideredNullMove,
     TriedHashMove,
     GeneratedMoves,
+    OrderedRootMoves,
     TriedWinningMoves,
     TriedKillerMoves,
     TriedLosingCaptures,
@@ -703,6 +1277,7 @@ enum NodePhase {
 /// Holds information about the state of a node in the search tree.
 struct NodeState {
     phase: NodePhase,
+    node_type: NodeType,
     hash_move_digest: MoveDigest,
     static_eval: Value,
     is_check: MaybeUninit::<bool>,
@@ -789,6 +1364,72 @@ impl KillerTable {
 }
 
 
+/// Holds history heuristic statistics for quiet moves.
+///
+/// The history heuristic assumes that a quiet move which caused many
+/// beta cut-offs in other positions, is likely to cause a beta
+/// cut-off in the current position too. The statistics are kept for
+/// every origin and destination square, and deeper cut-offs are given
+/// much bigger weight.
+#[derive(Clone)]
+struct HistoryTable {
+    array: [[u32; 64]; 64],
+}
+
+impl HistoryTable {
+    /// Creates a new instance.
+    pub fn new() -> HistoryTable {
+        HistoryTable { array: [[0; 64]; 64] }
+    }
+
+    /// Registers that the move `m` caused a beta cut-off at `depth`.
+    #[inline]
+    pub fn register(&mut self, m: Move, depth: Depth) {
+        if m.captured_piece() < PIECE_NONE || m.move_type() == MOVE_PROMOTION || m.is_null() {
+            // Captures and promotions are tried early anyway.
+            return;
+        }
+        let depth = max(depth, 0) as u32;
+        let entry = &mut self.array[m.orig_square()][m.dest_square()];
+        *entry += depth * depth;
+
+        // Age the statistics, so that the old values do not dominate
+        // forever.
+        if *entry > HISTORY_MAX {
+            for x in self.array.iter_mut().flat_map(|row| row.iter_mut()) {
+                *x >>= 1;
+            }
+        }
+    }
+
+    /// Returns the history score for the move `m`.
+    #[inline]
+    pub fn get(&self, m: Move) -> u32 {
+        self.array[m.orig_square()][m.dest_square()]
+    }
+}
+
+
+/// When a history score exceeds this number, all history scores are
+/// halved.
+const HISTORY_MAX: u32 = 1 << 20;
+
+
+/// The data that `SimpleSearch` keeps between searches.
+///
+/// This is the history heuristic statistics collected by the
+/// previous searches.
+pub struct SimpleSearchState {
+    history: RwLock<HistoryTable>,
+}
+
+impl Default for SimpleSearchState {
+    fn default() -> SimpleSearchState {
+        SimpleSearchState { history: RwLock::new(HistoryTable::new()) }
+    }
+}
+
+
 /// A killer move with its hit counter.
 #[derive(Clone, Copy)]
 struct Killer {
@@ -837,14 +1478,18 @@ fn contains_same_moves(list1: &Vec<Move>, list2: &Vec<Move>) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{SearchRunner, KillerTable};
+    use super::{SearchRunner, KillerTable, HistoryTable, SimpleSearchState, initial_move_score,
+                MOVE_SCORE_MAX};
+    use std::sync::Arc;
+    use std::sync::mpsc::channel;
     use value::*;
     use board::*;
     use search_node::*;
     use moves::*;
     use ttable::*;
+    use search::*;
     use stock::{StdTtable, StdTtableEntry, StdSearchNode, StdQsearch, StdMoveGenerator,
-                SimpleEvaluator};
+                SimpleEvaluator, SimpleSearch};
     use utils::MoveStack;
 
     type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
@@ -864,7 +1509,9 @@ mod tests {
             .run(VALUE_MIN, VALUE_MAX, 1, Move::invalid())
             .ok()
             .unwrap();
-        assert!(value < -300);
+        // Searching the check evasions at the leaves reveals the
+        // smothered mate, but only a deeper search can prove it.
+        assert!(value > 300 && value <= VALUE_EVAL_MAX);
 
         let p = P::from_history("8/8/8/8/3q3k/7n/6PP/2Q2R1K b - - 0 1",
                                 &mut vec![].into_iter())
@@ -880,6 +1527,514 @@ mod tests {
         assert!(value > VALUE_EVAL_MAX);
     }
 
+    #[test]
+    fn killer_moves_save_nodes() {
+        let count_nodes = |killers: bool| {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let p = P::from_history("8/8/8/8/3q3k/7n/6PP/2Q2R1K b - - 0 1",
+                                    &mut vec![].into_iter())
+                    .ok()
+                    .unwrap();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+            if !killers {
+                search.disable_killers();
+            }
+            let value = search
+                .run(VALUE_MIN, VALUE_MAX, 8, Move::invalid())
+                .ok()
+                .unwrap();
+            assert!(value > VALUE_EVAL_MAX);
+            search.node_count()
+        };
+        assert!(count_nodes(true) <= count_nodes(false));
+    }
+
+    #[test]
+    fn static_eval_reuse() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use uci::SetOption;
+        use evaluator::Evaluator;
+        use stock::{StdSearchNode, StdQsearch, StdMoveGenerator};
+
+        static EVALUATIONS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Clone, Copy)]
+        struct CountingEvaluator(SimpleEvaluator);
+
+        impl SetOption for CountingEvaluator {}
+
+        impl Evaluator for CountingEvaluator {
+            fn new(position: &Board) -> Self {
+                CountingEvaluator(SimpleEvaluator::new(position))
+            }
+
+            fn evaluate(&self, position: &Board) -> Value {
+                EVALUATIONS.fetch_add(1, Ordering::Relaxed);
+                self.0.evaluate(position)
+            }
+
+            fn is_zugzwangy(&self, position: &Board) -> bool {
+                self.0.is_zugzwangy(position)
+            }
+
+            fn done_move(&mut self, position: &Board, m: Move) {
+                self.0.done_move(position, m)
+            }
+
+            fn undone_move(&mut self, position: &Board, m: Move) {
+                self.0.undone_move(position, m)
+            }
+        }
+
+        type C = StdSearchNode<StdQsearch<StdMoveGenerator<CountingEvaluator>>>;
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let search = |depth| {
+            let p = C::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+            EVALUATIONS.store(0, Ordering::Relaxed);
+            search.run(VALUE_MIN, VALUE_MAX, depth, Move::invalid()).ok().unwrap();
+            EVALUATIONS.load(Ordering::Relaxed)
+        };
+
+        assert!(search(2) > 0);
+        let p = C::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+        let e = tt.probe(p.hash()).unwrap();
+        assert_eq!(e.static_eval(), p.evaluator().evaluate(p.board()));
+
+        // The second search with the same depth gets its result
+        // directly from the TT, without evaluating anything.
+        assert_eq!(search(2), 0);
+    }
+
+    #[test]
+    fn promotion_capture_score() {
+        let p = P::from_history("3r1rk1/4P3/8/8/8/8/8/4K3 w - - 0 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        let mut moves = vec![];
+        p.generate_moves(&mut moves);
+        let score = |notation: &str| {
+            let m = *moves.iter().find(|m| m.notation() == notation).unwrap();
+            initial_move_score(&p, m)
+        };
+        assert_eq!(score("e7d8q"), MOVE_SCORE_MAX - 1);
+        assert_eq!(score("e7f8q"), MOVE_SCORE_MAX - 1);
+        assert_eq!(score("e7e8q"), MOVE_SCORE_MAX - 1);
+        assert!(score("e7d8n") > 0);
+        assert_eq!(score("e7e8n"), 0);
+        assert_eq!(score("e1e2"), 0);
+        let m = *moves.iter().find(|m| m.notation() == "e7d8q").unwrap();
+        assert_eq!(m.move_type(), MOVE_PROMOTION);
+        assert_eq!(m.captured_piece(), ROOK);
+        assert_eq!(Move::piece_from_aux_data(m.aux_data()), QUEEN);
+    }
+
+    #[test]
+    fn contempt() {
+        // White has only quiet moves, and all of them reach the
+        // fifty-move limit.
+        let fen = "8/8/8/4k3/8/8/8/1N2K3 w - - 99 80";
+        let run = |alpha, beta, depth| {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+            search.contempt = 20;
+            search.run(alpha, beta, depth, Move::invalid()).ok().unwrap()
+        };
+
+        // The draws at PV nodes reflect the contempt.
+        for depth in 2..6 {
+            assert_eq!(run(VALUE_MIN, VALUE_MAX, depth), -20);
+        }
+
+        // The draws at non-PV nodes are valued as `0`.
+        assert_eq!(run(-1, 0, 3), 0);
+        assert_eq!(run(-1, 0, 4), 0);
+
+        // The root position is a draw.
+        let fen = "8/8/8/4k3/8/8/8/1N2K3 w - - 100 80";
+        let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let mut moves = MoveStack::new();
+        let mut report = |_| false;
+        let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+        search.contempt = 20;
+        assert_eq!(search.run(VALUE_MIN, VALUE_MAX, 3, Move::invalid()).ok().unwrap(), -20);
+
+        // The contempt is taken from the search parameters.
+        let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+        let (reports_tx, _reports_rx) = channel();
+        let (_messages_tx, messages_rx) = channel();
+        let value = SimpleSearch::spawn(SearchParams {
+                                            search_id: 0,
+                                            searchmoves: p.legal_moves(),
+                                            position: p,
+                                            depth: 3,
+                                            lower_bound: VALUE_MIN,
+                                            upper_bound: VALUE_MAX,
+                                            report_interval: None,
+                                            contempt: 20,
+                                            analyse_mode: false,
+                                            threads: 1,
+                                        },
+                                        Arc::new(StdTtable::<StdTtableEntry>::new(None)),
+                                        Arc::new(SimpleSearchState::default()),
+                                        reports_tx,
+                                        messages_rx)
+            .join()
+            .unwrap();
+        assert_eq!(value, -20);
+    }
+
+    #[test]
+    fn contempt_avoids_draws() {
+        // Material is equal. Every king move completes 50 reversible
+        // moves, while the pawn moves keep the game going.
+        let fen = "7k/p7/8/8/8/8/P7/7K w - - 99 80";
+        let best_move = |contempt, depth| {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p.clone(), &tt, &mut moves, &mut report);
+            search.history = Some(HistoryTable::new());
+            search.contempt = contempt;
+            search.run(VALUE_MIN, VALUE_MAX, depth, Move::invalid()).ok().unwrap();
+            tt.extract_pv(&p).moves[0].notation()
+        };
+        // Without contempt the draw is as good as any other move.
+        assert!(best_move(0, 3).starts_with("h1"));
+        for depth in 2..6 {
+            assert!(best_move(50, depth).starts_with("a2"));
+        }
+    }
+
+    #[test]
+    fn exact_pv() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let run = |exact_pv| {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+
+            // A lower bound that makes the TT give the result for
+            // the root.
+            tt.store(p.hash(), StdTtableEntry::new(500, BOUND_LOWER, 10));
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+            search.exact_pv = exact_pv;
+            search.run(-200, 200, 3, Move::invalid()).ok().unwrap()
+        };
+        assert_eq!(run(false), 500);
+        let value = run(true);
+        assert!(-200 < value && value < 200);
+
+        // The analysis mode is taken from the search parameters.
+        let spawn = |analyse_mode| {
+            let tt = Arc::new(StdTtable::<StdTtableEntry>::new(None));
+            let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            tt.store(p.hash(), StdTtableEntry::new(500, BOUND_LOWER, 10));
+            let (reports_tx, _reports_rx) = channel();
+            let (_messages_tx, messages_rx) = channel();
+            SimpleSearch::spawn(SearchParams {
+                                    search_id: 0,
+                                    searchmoves: p.legal_moves(),
+                                    position: p,
+                                    depth: 3,
+                                    lower_bound: -200,
+                                    upper_bound: 200,
+                                    report_interval: None,
+                                    contempt: 0,
+                                    analyse_mode,
+                                    threads: 1,
+                                },
+                                tt,
+                                Arc::new(SimpleSearchState::default()),
+                                reports_tx,
+                                messages_rx)
+                .join()
+                .unwrap()
+        };
+        assert_eq!(spawn(false), 500);
+        let value = spawn(true);
+        assert!(-200 < value && value < 200);
+    }
+
+    #[test]
+    fn late_move_reductions() {
+        use super::{late_move_reduction, NodeType};
+        use depth::DEPTH_MAX;
+        let all = NodeType::All;
+        let cut = NodeType::Cut;
+        assert_eq!(late_move_reduction(1, 20, all), 1);
+        assert_eq!(late_move_reduction(8, 2, all), 1);
+        assert!(late_move_reduction(8, 20, all) > late_move_reduction(8, 5, all));
+        assert!(late_move_reduction(16, 20, all) > late_move_reduction(4, 20, all));
+        assert_eq!(late_move_reduction(8, 2, cut), 1);
+        assert_eq!(late_move_reduction(8, 20, cut), late_move_reduction(8, 20, all) + 1);
+        for depth in 1..DEPTH_MAX {
+            for move_number in 1..100 {
+                for &node_type in &[all, cut] {
+                    let r = late_move_reduction(depth, move_number, node_type);
+                    assert!(depth - 1 - r >= -1);
+                }
+            }
+        }
+
+        // The smothered mate is still found.
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let p = P::from_history("8/8/8/8/3q3k/7n/6PP/2Q2R1K b - - 0 1",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let mut moves = MoveStack::new();
+        let mut report = |_| false;
+        let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+        let value = search
+            .run(VALUE_MIN, VALUE_MAX, 4, Move::invalid())
+            .ok()
+            .unwrap();
+        assert!(value > VALUE_EVAL_MAX);
+    }
+
+    #[test]
+    fn repetition() {
+        let run = |moves: &[&str], depth| {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let p = P::from_history("7k/8/8/8/8/8/8/3Q2K1 w - - 0 1", &mut moves.iter().cloned())
+                .ok()
+                .unwrap();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+            search.contempt = 0;
+            search.run(VALUE_MIN, VALUE_MAX, depth, Move::invalid()).ok().unwrap()
+        };
+
+        // The initial position has occurred twice. After "d2d1",
+        // black can repeat it for the third time by playing "g8h8",
+        // and therefore the position is a draw.
+        let history = ["d1d2", "h8g8", "d2d1", "g8h8", "d1d2", "h8g8", "d2d1"];
+        for depth in 1..5 {
+            assert_eq!(run(&history, depth), 0);
+        }
+
+        // White avoids the repetition, and keeps the winning
+        // evaluation.
+        for depth in 1..5 {
+            assert!(run(&history[..6], depth) > 500);
+        }
+    }
+
+    #[test]
+    fn rule50() {
+        let run = |fen: &str, depth| {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+            search.contempt = 0;
+            search.run(VALUE_MIN, VALUE_MAX, depth, Move::invalid()).ok().unwrap()
+        };
+
+        // Every move completes 50 reversible moves, and therefore the
+        // position is a draw, regardless of the extra queen.
+        for depth in 1..5 {
+            assert_eq!(run("7k/8/8/8/8/8/8/3Q2K1 w - - 99 80", depth), 0);
+        }
+
+        // A checkmate on the 100th half-move is still a win.
+        for depth in 1..5 {
+            assert_eq!(run("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80", depth), VALUE_MAX - 1);
+        }
+    }
+
+    #[test]
+    fn mate_distance() {
+        use super::child_window;
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let p = P::from_history("k7/8/2K5/8/8/8/8/7R w - - 0 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        let mut moves = MoveStack::new();
+        let mut report = |_| false;
+        let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+        for depth in 4..8 {
+            let value = search
+                .run(VALUE_MIN, VALUE_MAX, depth, Move::invalid())
+                .ok()
+                .unwrap();
+            assert_eq!(value, VALUE_MAX - 3);
+        }
+
+        // The window for the child position is shifted by one for
+        // values that designate inevitable checkmate.
+        assert_eq!(child_window(-100, 100), (-100, 100));
+        assert_eq!(child_window(VALUE_MAX - 3, VALUE_MAX), (VALUE_MIN, VALUE_MIN + 2));
+        assert_eq!(child_window(VALUE_MIN, VALUE_MIN + 3), (VALUE_MAX - 2, VALUE_MAX));
+        assert_eq!(child_window(VALUE_MAX - 1, VALUE_MAX), (VALUE_MIN, VALUE_MIN + 1));
+        assert_eq!(child_window(VALUE_MIN, VALUE_MIN + 1), (VALUE_MAX - 1, VALUE_MAX));
+    }
+
+    #[test]
+    fn check_at_leaf() {
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let p = P::from_history("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        let mut moves = MoveStack::new();
+        let mut report = |_| false;
+        let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+        let value = search
+            .run(VALUE_MIN, VALUE_MAX, 1, Move::invalid())
+            .ok()
+            .unwrap();
+        assert_eq!(value, VALUE_MAX - 1);
+
+        let p = P::from_history("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        let mut moves = MoveStack::new();
+        let mut report = |_| false;
+        let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+        let value = search
+            .run(VALUE_MIN, VALUE_MAX, 0, Move::invalid())
+            .ok()
+            .unwrap();
+        assert_eq!(value, VALUE_MIN);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn trace() {
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let p = P::from_history("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let mut moves = MoveStack::new();
+        let mut report = |_| false;
+        let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+        search.enable_trace();
+        search.run(VALUE_MIN, VALUE_MAX, 3, Move::invalid()).ok().unwrap();
+        let trace = search.trace();
+        assert!(trace.iter().any(|r| !r.m.is_null() && r.new_depth == r.depth - 2));
+        for r in trace {
+            assert!(r.ply >= 1 && r.ply <= 3);
+            assert!(r.depth >= 1 && r.depth <= 3);
+            assert!(r.new_depth >= -1 && r.new_depth < r.depth);
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn node_types() {
+        use super::{NodeType, DepthRecord};
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let p = P::from_history("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let mut moves = MoveStack::new();
+        let mut report = |_| false;
+        let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+        search.enable_trace();
+        search.run(VALUE_MIN, VALUE_MAX, 5, Move::invalid()).ok().unwrap();
+        let trace = search.trace();
+
+        // PV nodes do not reduce moves, and do not try null moves.
+        for r in trace.iter().filter(|r| r.node_type.is_pv()) {
+            assert!(!r.m.is_null());
+            assert_eq!(r.new_depth, r.depth - 1);
+        }
+        assert!(trace.iter().any(|r| r.node_type == NodeType::Root));
+        assert!(trace.iter().any(|r| r.node_type == NodeType::Pv));
+
+        // Cut and All nodes do.
+        assert!(trace
+                    .iter()
+                    .any(|r| r.node_type == NodeType::All && !r.m.is_null() &&
+                             r.new_depth < r.depth - 1));
+        assert!(trace
+                    .iter()
+                    .any(|r| r.node_type == NodeType::Cut && r.m.is_null()));
+
+        // Late moves are reduced more in Cut nodes than in All nodes.
+        let reduction = |r: &DepthRecord| r.depth - 1 - r.new_depth;
+        let late_moves = |node_type| {
+            trace
+                .iter()
+                .filter(move |r| r.node_type == node_type && r.move_number > 4 && r.depth >= 3)
+        };
+        assert!(late_moves(NodeType::Cut).any(|c| {
+            late_moves(NodeType::All).any(|a| {
+                (a.depth, a.move_number) == (c.depth, c.move_number) &&
+                reduction(c) > reduction(a)
+            })
+        }));
+    }
+
+    #[test]
+    fn report_interval() {
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let count_reports = |interval| {
+            tt.clear();
+            let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            let mut moves = MoveStack::new();
+            let mut reports = 0;
+            {
+                let mut report = |_| {
+                    reports += 1;
+                    false
+                };
+                let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+                search.set_report_interval(interval);
+                search.run(VALUE_MIN, VALUE_MAX, 3, Move::invalid()).ok().unwrap();
+            }
+            reports
+        };
+        let few = count_reports(1000);
+        let many = count_reports(10);
+        assert!(many > few);
+
+        // The interval can be given with the search parameters too.
+        let spawn_and_count_reports = |report_interval| {
+            let tt = Arc::new(StdTtable::<StdTtableEntry>::new(None));
+            let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            let (reports_tx, reports_rx) = channel();
+            let (_messages_tx, messages_rx) = channel();
+            SimpleSearch::spawn(SearchParams {
+                                    search_id: 0,
+                                    searchmoves: p.legal_moves(),
+                                    position: p,
+                                    depth: 3,
+                                    lower_bound: VALUE_MIN,
+                                    upper_bound: VALUE_MAX,
+                                    report_interval,
+                                    contempt: 0,
+                                    analyse_mode: false,
+                                    threads: 1,
+                                },
+                                tt,
+                                Arc::default(),
+                                reports_tx,
+                                messages_rx)
+                .join()
+                .unwrap();
+            reports_rx.iter().count()
+        };
+        assert!(spawn_and_count_reports(Some(10)) > spawn_and_count_reports(Some(1000)));
+    }
+
     #[test]
     fn killers() {
         let mut killers = KillerTable::new();
@@ -906,4 +2061,242 @@ mod tests {
         }
         assert!(killers.get(1) == (MoveDigest::invalid(), MoveDigest::invalid()));
     }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn history() {
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let p = P::from_history("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let first_root_moves = |first: &str, second: &str| {
+            let legal_moves = p.legal_moves();
+            let find = |notation| *legal_moves.iter().find(|m| m.notation() == notation).unwrap();
+            let mut history = HistoryTable::new();
+            history.register(find(first), 10);
+            history.register(find(second), 5);
+            tt.clear();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p.clone(), &tt, &mut moves, &mut report);
+            search.history = Some(history);
+            search.enable_trace();
+            search.run(VALUE_MIN, VALUE_MAX, 1, Move::invalid()).ok().unwrap();
+            search
+                .trace()
+                .iter()
+                .filter(|r| r.ply == 1)
+                .take(2)
+                .map(|r| r.m.notation())
+                .collect::<Vec<_>>()
+        };
+
+        // There are no captures or killer moves in the starting
+        // position, so the quiet moves are tried in the order of
+        // their history scores.
+        assert_eq!(first_root_moves("a2a3", "h2h3"), vec!["a2a3", "h2h3"]);
+        assert_eq!(first_root_moves("h2h3", "a2a3"), vec!["h2h3", "a2a3"]);
+        assert_eq!(first_root_moves("b1c3", "g2g4"), vec!["b1c3", "g2g4"]);
+    }
+
+    #[test]
+    fn history_state() {
+        let is_empty = |state: &SimpleSearchState| {
+            state.history.read().unwrap().array.iter().all(|row| row.iter().all(|&x| x == 0))
+        };
+        let state = Arc::new(SimpleSearchState::default());
+        assert!(is_empty(&state));
+
+        // The statistics collected by a search are kept in the state.
+        let tt = Arc::new(StdTtable::<StdTtableEntry>::new(None));
+        let p = P::from_history("r1bq1rk1/pp2bppp/2n1pn2/2pp4/3P4/2PBPN2/PP1N1PPP/R2QK2R w KQ - 0 8",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let (reports_tx, _reports_rx) = channel();
+        let (_messages_tx, messages_rx) = channel();
+        SimpleSearch::spawn(SearchParams {
+                                search_id: 0,
+                                searchmoves: p.legal_moves(),
+                                position: p,
+                                depth: 4,
+                                lower_bound: VALUE_MIN,
+                                upper_bound: VALUE_MAX,
+                                report_interval: None,
+                                contempt: 0,
+                                analyse_mode: false,
+                                threads: 1,
+                            },
+                            tt,
+                            state.clone(),
+                            reports_tx,
+                            messages_rx)
+            .join()
+            .unwrap();
+        assert!(!is_empty(&state));
+    }
+
+    #[test]
+    fn late_move_pruning() {
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let best_move = |fen: &str, prune_late_moves: bool| {
+            tt.clear();
+            let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p.clone(), &tt, &mut moves, &mut report);
+            search.history = Some(HistoryTable::new());
+            if !prune_late_moves {
+                search.disable_late_move_pruning();
+            }
+            for depth in 1..7 {
+                search.run(VALUE_MIN, VALUE_MAX, depth, Move::invalid()).ok().unwrap();
+            }
+            tt.extract_pv(&p).moves[0].notation()
+        };
+
+        // Only the first few quiet moves are searched close to the
+        // leaves.
+        #[cfg(debug_assertions)]
+        {
+            use super::{late_move_count, LATE_MOVE_PRUNING_DEPTH};
+            let fen = "r1bq1rk1/pp2bppp/2n1pn2/2pp4/3P4/2PBPN2/PP1N1PPP/R2QK2R w KQ - 0 8";
+            let is_pruned = |prune_late_moves: bool| {
+                tt.clear();
+                let p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+                let mut moves = MoveStack::new();
+                let mut report = |_| false;
+                let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+                if !prune_late_moves {
+                    search.disable_late_move_pruning();
+                }
+                search.enable_trace();
+                search.run(VALUE_MIN, VALUE_MAX, 5, Move::invalid()).ok().unwrap();
+                search
+                    .trace()
+                    .iter()
+                    .filter(|r| !r.node_type.is_pv() && r.depth <= LATE_MOVE_PRUNING_DEPTH)
+                    .all(|r| r.quiet_move_number <= late_move_count(r.depth))
+            };
+            assert!(is_pruned(true));
+            assert!(!is_pruned(false));
+        }
+
+        // The best move is still found. ("b5c7" is a quiet knight
+        // fork, "f3d4" wins the black queen.)
+        for &(fen, expected) in &[("r5k1/5pp1/4r2p/1N6/8/7P/5PP1/3R2K1 w - - 0 1", "b5c7"),
+                                  ("r1b1kbnr/pppp1ppp/2n5/4p3/3qP3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 4",
+                                   "f3d4")] {
+            assert_eq!(best_move(fen, true), expected);
+            assert_eq!(best_move(fen, false), expected);
+        }
+    }
+
+    #[test]
+    fn futility_pruning() {
+        // The quiet knight fork "b5c7" wins a rook for a knight. The
+        // gain is close to the futility margins, and must not be
+        // pruned away.
+        let p = P::from_history("r5k1/5pp1/4r2p/1N6/8/7P/5PP1/3R2K1 w - - 0 1",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        for depth in 2..7 {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let value = {
+                let mut search = SearchRunner::new(p.clone(), &tt, &mut moves, &mut report);
+                search.run(VALUE_MIN, VALUE_MAX, depth, Move::invalid()).ok().unwrap()
+            };
+            assert!(value > -100);
+            assert_eq!(tt.extract_pv(&p).moves[0].notation(), "b5c7");
+        }
+    }
+
+    #[test]
+    fn ordering_stats() {
+        let p = P::from_history("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let first_move_cutoff_rate = |killers: bool| {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p.clone(), &tt, &mut moves, &mut report);
+            search.disable_history();
+            if !killers {
+                search.disable_killers();
+            }
+            assert_eq!(search.ordering_stats().first_move_cutoff_rate(), None);
+            search.run(VALUE_MIN, VALUE_MAX, 4, Move::invalid()).ok().unwrap();
+            let stats = search.ordering_stats();
+            assert!(stats.first_move_cutoffs <= stats.cutoffs);
+            let rate = stats.first_move_cutoff_rate().unwrap();
+            assert!((0.0..=1.0).contains(&rate));
+            rate
+        };
+
+        // Killer moves improve the move ordering.
+        assert!(first_move_cutoff_rate(true) > first_move_cutoff_rate(false));
+    }
+
+    #[test]
+    fn null_move_zugzwang() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use uci::SetOption;
+        use evaluator::Evaluator;
+        use stock::{StdSearchNode, StdQsearch, StdMoveGenerator};
+
+        static ZUGZWANGY: AtomicBool = AtomicBool::new(false);
+
+        #[derive(Clone, Copy)]
+        struct ZugzwangEvaluator(SimpleEvaluator);
+
+        impl SetOption for ZugzwangEvaluator {}
+
+        impl Evaluator for ZugzwangEvaluator {
+            fn new(position: &Board) -> Self {
+                ZugzwangEvaluator(SimpleEvaluator::new(position))
+            }
+
+            fn evaluate(&self, position: &Board) -> Value {
+                self.0.evaluate(position)
+            }
+
+            fn is_zugzwangy(&self, _: &Board) -> bool {
+                ZUGZWANGY.load(Ordering::SeqCst)
+            }
+
+            fn done_move(&mut self, position: &Board, m: Move) {
+                self.0.done_move(position, m)
+            }
+
+            fn undone_move(&mut self, position: &Board, m: Move) {
+                self.0.undone_move(position, m)
+            }
+        }
+
+        type C = StdSearchNode<StdQsearch<StdMoveGenerator<ZugzwangEvaluator>>>;
+
+        // A mutual zugzwang -- whoever is to move loses a pawn.
+        let fen = "8/8/8/3pK3/2kP4/8/8/8 w - - 0 1";
+        let search = |zugzwangy| {
+            ZUGZWANGY.store(zugzwangy, Ordering::SeqCst);
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            let p = C::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            let mut moves = MoveStack::new();
+            let mut report = |_| false;
+            let mut search = SearchRunner::new(p, &tt, &mut moves, &mut report);
+            search.history = Some(HistoryTable::new());
+            search.run(-100, -99, 8, Move::invalid()).ok().unwrap()
+        };
+
+        // Passing is not allowed here, so a null move search gives a
+        // wrong (too optimistic) result.
+        assert!(search(false) >= -99);
+        assert!(search(true) <= -100);
+    }
 }
@@ -2,6 +2,7 @@
 
 use std::mem::{transmute, MaybeUninit};
 use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
 use uci::{SetOption, OptionDescription};
 use board::*;
 use squares::*;
@@ -20,6 +21,9 @@ pub struct StdMoveGenerator<T: Evaluator> {
     board: Board,
     evaluator: MaybeUninit::<T>,
 
+    /// The Zobrist hash of the pawn structure, updated on each move.
+    pawn_hash: u64,
+
     /// Lazily calculated bitboard of all checkers -- `BB_ALL` if not
     /// calculated yet.
     checkers: Cell<Bitboard>,
@@ -33,11 +37,21 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         let mut gen = StdMoveGenerator {
             geometry: BoardGeometry::get(),
             zobrist: ZobristArrays::get(),
+            pawn_hash: board.pawn_hash(),
             board: board,
             evaluator: MaybeUninit::<T>::uninit(),
             checkers: Cell::new(BB_ALL),
         };
         if gen.is_legal() {
+            // Some FEN producers set the en-passant file even when no
+            // enemy pawn can capture en passant. We clear it in this
+            // case, so that equal positions always have equal hashes.
+            if gen.board.enpassant_file < 8 &&
+               gen.geometry.pawn_attacks[opposite(gen.board.to_move)][bsf(gen.enpassant_bb())] &
+               gen.board.pieces.color[gen.board.to_move] &
+               gen.board.pieces.piece_type[PAWN] == 0 {
+                gen.board.enpassant_file = 8;
+            }
             gen.evaluator.write(T::new(gen.board()));
             Ok(gen)
         } else {
@@ -64,6 +78,11 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         hash
     }
 
+    #[inline]
+    fn pawn_hash(&self) -> u64 {
+        self.pawn_hash
+    }
+
     #[inline]
     fn board(&self) -> &Board {
         &self.board
@@ -91,11 +110,130 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         }
     }
 
+    #[inline]
+    fn pinners_and_pinned(&self, king_square: Square, by: Color) -> (Bitboard, Bitboard) {
+        assert!(king_square <= 63);
+        assert!(by <= 1);
+        let mut pinners = 0;
+        let mut pinned = 0;
+        unsafe {
+            let g: &BoardGeometry = &self.geometry;
+            let occupied_by_them = *self.board.pieces.color.get_unchecked(by);
+
+            // To find the potential pinners, we "remove" all our
+            // pieces from the board, and then verify if a bishop or a
+            // rook placed on our king's square can attack any enemy
+            // bishops, rooks, or queens.
+            let file_sliders = self.board.pieces.piece_type[QUEEN] |
+                               self.board.pieces.piece_type[ROOK];
+            let diag_sliders = self.board.pieces.piece_type[QUEEN] |
+                               self.board.pieces.piece_type[BISHOP];
+            let mut candidates =
+                occupied_by_them & !(1 << king_square) &
+                (file_sliders & g.attacks_from_unsafe(ROOK, king_square, occupied_by_them) |
+                 diag_sliders & g.attacks_from_unsafe(BISHOP, king_square, occupied_by_them));
+
+            // Then, for each potential pinner we verify if there is
+            // exactly one defender between our king and the pinner.
+            if candidates != 0 {
+                let defenders = *self.board.pieces.color.get_unchecked(opposite(by)) &
+                                !(1 << king_square);
+                loop {
+                    let pinner_square = bsf_reset(&mut candidates);
+                    let bb = defenders &
+                             *g.squares_between_including
+                                  .get_unchecked(king_square)
+                                  .get_unchecked(pinner_square);
+                    if bb != 0 && lsb(bb) == bb {
+                        pinners |= 1 << pinner_square;
+                        pinned |= bb;
+                    }
+                    if candidates == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+        (pinners, pinned)
+    }
+
     #[inline]
     fn is_check(&self) -> bool {
         self.checkers() != 0
     }
 
+    #[inline]
+    fn is_double_check(&self) -> bool {
+        let checkers = self.checkers();
+        lsb(checkers) != checkers
+    }
+
+    fn gives_check(&self, m: Move) -> bool {
+        let us = self.board.to_move;
+        let them = opposite(us);
+        let move_type = m.move_type();
+        let orig_square = m.orig_square();
+        let dest_square = m.dest_square();
+        let dest_square_bb = 1 << dest_square;
+        let piece_type = &self.board.pieces.piece_type;
+        let their_king_square = bsf(piece_type[KING] & self.board.pieces.color[them]);
+
+        // Calculate the occupied squares, our pieces, and the
+        // placement of the pieces that can give check, as they will
+        // be after the move.
+        let not_moved = !(1 << orig_square | dest_square_bb);
+        let mut occupied = self.board.occupied & not_moved | dest_square_bb;
+        let mut ours = self.board.pieces.color[us] & not_moved | dest_square_bb;
+        let mut straight_sliders = (piece_type[ROOK] | piece_type[QUEEN]) & not_moved;
+        let mut diagonal_sliders = (piece_type[BISHOP] | piece_type[QUEEN]) & not_moved;
+        let mut knights = piece_type[KNIGHT] & not_moved;
+        let mut pawns = piece_type[PAWN] & not_moved;
+        match if move_type == MOVE_PROMOTION {
+                  Move::piece_from_aux_data(m.aux_data())
+              } else {
+                  m.played_piece()
+              } {
+            QUEEN => {
+                straight_sliders |= dest_square_bb;
+                diagonal_sliders |= dest_square_bb;
+            }
+            ROOK => straight_sliders |= dest_square_bb,
+            BISHOP => diagonal_sliders |= dest_square_bb,
+            KNIGHT => knights |= dest_square_bb,
+            PAWN => pawns |= dest_square_bb,
+            _ => (),
+        }
+        match move_type {
+            MOVE_ENPASSANT => {
+                let captured_pawn_square =
+                    (dest_square as isize + PAWN_MOVE_SHIFTS[them][PAWN_PUSH]) as Square;
+                occupied &= !(1 << captured_pawn_square);
+            }
+            MOVE_CASTLING => {
+                // The rook, not the king, can give check after castling.
+                let side = if dest_square > orig_square {
+                    KINGSIDE
+                } else {
+                    QUEENSIDE
+                };
+                let mask = BB_CASTLING_ROOK_MOVEMENT[us][side];
+                occupied ^= mask;
+                ours ^= mask;
+                straight_sliders ^= mask;
+            }
+            _ => (),
+        }
+
+        // Find if any of our pieces attacks their king.
+        let checkers = self.geometry.attacks_from(ROOK, their_king_square, occupied) &
+                       straight_sliders |
+                       self.geometry.attacks_from(BISHOP, their_king_square, occupied) &
+                       diagonal_sliders |
+                       self.geometry.attacks_from(KNIGHT, their_king_square, occupied) & knights |
+                       self.geometry.pawn_attacks[them][their_king_square] & pawns;
+        checkers & ours != 0
+    }
+
     #[inline]
     fn evaluator(&self) -> &Self::Evaluator {
         unsafe { self.evaluator.assume_init_ref() }
@@ -109,7 +247,11 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
     /// generated king's moves may be illegal because the destination
     /// square is under attack.
     ///
-    /// The initial move score for all generated moves is `0`.
+    /// The initial move score for all generated moves is `0`. Since
+    /// the captured and the played pieces are encoded in the lower
+    /// bits of each move, this means that `MoveStack::pull_best` will
+    /// yield the captures in MVV-LVA order (most valuable victim --
+    /// least valuable aggressor), without a separate ordering pass.
     ///
     /// **Note:** A pseudo-legal move is a move that is otherwise
     /// legal, except it might leave the king in check.
@@ -209,12 +351,22 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         self.add_piece_moves(KING, king_square, !occupied_by_us, moves);
     }
 
+    fn generate_legal<U: AddMove>(&self, moves: &mut U) {
+        // All moves with pieces other than the king are legal, so
+        // only king's destination squares need to be verified.
+        self.generate_all(&mut LegalMoves {
+                               generator: self,
+                               moves,
+                           });
+    }
+
     /// Generates moves for the quiescence search.
     ///
     /// The moves are added to `moves`. This method always generates a
     /// **subset** of the moves generated by `generate_all`:
     ///
-    /// * If the king is in check, all legal moves are included.
+    /// * If the king is in check, all legal moves are included. (In
+    ///   double check only king moves are generated.)
     ///
     /// * Captures and pawn promotions to queen are always included.
     ///
@@ -222,79 +374,13 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
     ///   included too. Discovered checks and checks given by castling
     ///   are omitted for speed.
     fn generate_forcing<U: AddMove>(&self, generate_checks: bool, moves: &mut U) {
-        let (king_square, checkers) = self.king_square_and_checkers();
-        if checkers != 0 {
-            return self.generate_all(moves);
-        }
-        let pinned = self.find_pinned(king_square);
-        let occupied_by_us = unsafe {
-            *self.board
-                 .pieces
-                 .color
-                 .get_unchecked(self.board.to_move)
-        };
-        let occupied_by_them = self.board.occupied ^ occupied_by_us;
-        let enpassant_bb = self.enpassant_bb();
-        let pawn_dests;
-
-        // Generate queen, rook, bishop, and knight moves.
-        if generate_checks {
-            let their_king_square = bsf(self.board.pieces.piece_type[KING] & occupied_by_them);
-            unsafe {
-                pawn_dests = occupied_by_them | enpassant_bb | BB_PAWN_PROMOTION_RANKS |
-                             *self.geometry
-                                  .pawn_attacks
-                                  .get_unchecked(1 ^ self.board.to_move)
-                                  .get_unchecked(their_king_square);
-                for piece in QUEEN..PAWN {
-                    let mut bb = self.board.pieces.piece_type[piece] & occupied_by_us;
-                    while bb != 0 {
-                        let orig_square = bsf_reset(&mut bb);
-                        let checking_squares =
-                            !occupied_by_us &
-                            self.geometry
-                                .attacks_from_unsafe(piece, their_king_square, self.board.occupied);
-                        let mut dests = occupied_by_them | checking_squares;
-                        if 1 << orig_square & pinned != 0 {
-                            dests &= self.geometry.squares_at_line[king_square][orig_square];
-                        }
-                        self.add_piece_moves(piece, orig_square, dests, moves);
-                    }
-                }
-            }
-        } else {
-            pawn_dests = occupied_by_them | enpassant_bb | BB_PAWN_PROMOTION_RANKS;
-            for piece in QUEEN..PAWN {
-                let mut bb = self.board.pieces.piece_type[piece] & occupied_by_us;
-                while bb != 0 {
-                    let orig_square = bsf_reset(&mut bb);
-                    let mut dests = occupied_by_them;
-                    if 1 << orig_square & pinned != 0 {
-                        dests &= self.geometry.squares_at_line[king_square][orig_square];
-                    }
-                    self.add_piece_moves(piece, orig_square, dests, moves);
-                }
-            }
-        }
-
-        // Generate pawn moves.
-        {
-            let our_pawns = self.board.pieces.piece_type[PAWN] & occupied_by_us;
-            let mut pinned_pawns = our_pawns & pinned;
-            self.add_pawn_moves(our_pawns ^ pinned_pawns, pawn_dests, true, moves);
-            while pinned_pawns != 0 {
-                let pawn_square = bsf_reset(&mut pinned_pawns);
-                let pawn_dests = pawn_dests &
-                                 self.geometry.squares_at_line[king_square][pawn_square];
-                self.add_pawn_moves(1 << pawn_square, pawn_dests, true, moves);
-            }
-        }
-
-        // Generate king moves.
-        self.add_piece_moves(KING, king_square, occupied_by_them, moves);
+        let safe_king_moves = SAFE_KING_MOVES.load(Ordering::Relaxed);
+        self.generate_forcing_moves(generate_checks, safe_king_moves, moves);
     }
 
     fn try_move_digest(&self, move_digest: MoveDigest) -> Option<Move> {
+        let move_digest = self.resolve_move_type(move_digest);
+
         // We will use `generated_move` to assert that our result is correct.
         let mut generated_move = MaybeUninit::<Option<Move>>::uninit();
 
@@ -425,7 +511,7 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
                                         *self.board
                                              .pieces
                                             .color
-                                             .get_unchecked(1 ^ self.board.to_move),
+                                             .get_unchecked(opposite(self.board.to_move)),
                                         enpassant_bb,
                                         orig_square_bb,
                                         &mut dest_sets);
@@ -506,10 +592,30 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
     }
 
     fn do_move(&mut self, m: Move) -> Option<u64> {
+        // Verify if the move will leave the king in check. (We are
+        // certain that all the moves that we generate with pieces
+        // other than the king do not leave the king in check.)
+        if m.played_piece() == KING {
+            let orig_square = m.orig_square();
+            let dest_square = m.dest_square();
+            if orig_square != dest_square {
+                if self.king_would_be_in_check(orig_square, dest_square) {
+                    return None; // the king is in check -- illegal move
+                }
+            } else {
+                if self.is_check() {
+                    return None; // invalid "null move"
+                }
+            }
+        }
+        Some(self.do_move_unchecked(m))
+    }
+
+    fn do_move_unchecked(&mut self, m: Move) -> u64 {
         let mut old_hash = MaybeUninit::<u64>::uninit();
         let mut h = 0;
         let us = self.board.to_move;
-        let them = 1 ^ us;
+        let them = opposite(us);
         let move_type = m.move_type();
         let orig_square = m.orig_square();
         let dest_square = m.dest_square();
@@ -518,6 +624,14 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         let captured_piece = m.captured_piece();
 
         if cfg!(debug_assertions) {
+            // Assert that `do_move` would agree that the move is legal.
+            assert!(played_piece != KING ||
+                    if orig_square != dest_square {
+                        !self.king_would_be_in_check(orig_square, dest_square)
+                    } else {
+                        !self.is_check()
+                    });
+
             // Assert that `m` could be generated by `null_move` or
             // `generate_all`.
             assert!({
@@ -536,21 +650,6 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
             old_hash.write(self.hash());
         }
 
-        // Verify if the move will leave the king in check. (We are
-        // certain that all the moves that we generate with pieces
-        // other than the king do not leave the king in check.)
-        if played_piece == KING {
-            if orig_square != dest_square {
-                if self.king_would_be_in_check(orig_square, dest_square) {
-                    return None; // the king is in check -- illegal move
-                }
-            } else {
-                if self.is_check() {
-                    return None; // invalid "null move"
-                }
-            }
-        }
-
         // Tell the evaluator that a move will be played.
         unsafe { self.evaluator.assume_init_mut().will_do_move(&self.board, m) };
 
@@ -617,9 +716,16 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
             h ^= *self.zobrist
                       .enpassant_file
                       .get_unchecked(self.board.enpassant_file);
+            // Note that the en-passant file is set only if an enemy
+            // pawn can actually capture en passant.
             let is_double_push = dest_square as isize - orig_square as isize == [16, -16][us] &&
                                  played_piece == PAWN;
-            self.board.enpassant_file = if is_double_push {
+            self.board.enpassant_file = if is_double_push &&
+                                           self.geometry.pawn_attacks[us]
+                                               [(orig_square + dest_square) >> 1] &
+                                           self.board.pieces.color[them] &
+                                           self.board.pieces.piece_type[PAWN] !=
+                                           0 {
                 let file = Board::file(dest_square);
                 h ^= *self.zobrist.enpassant_file.get_unchecked(file);
                 file
@@ -631,6 +737,7 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         // Change the side to move.
         self.board.to_move = them;
         h ^= self.zobrist.to_move;
+        self.pawn_hash ^= self.zobrist.pawn_hash_delta(us, m);
 
         // Update the auxiliary fields.
         self.board.occupied = self.board.pieces.color[WHITE] | self.board.pieces.color[BLACK];
@@ -639,9 +746,12 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         // Tell the evaluator that a move was played.
         unsafe { self.evaluator.assume_init_mut().done_move(&self.board, m) };
 
+        #[cfg(debug_assertions)]
+        assert_eq!(self.board.validate(), Ok(()));
         debug_assert!(self.is_legal());
         debug_assert_eq!(unsafe { old_hash.assume_init() } ^ h, self.hash());
-        Some(h)
+        debug_assert_eq!(self.pawn_hash, self.board.pawn_hash());
+        h
     }
 
     fn undo_move(&mut self, m: Move) {
@@ -649,7 +759,7 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         // in `do_move`, but in reverse.
 
         let them = self.board.to_move;
-        let us = 1 ^ them;
+        let us = opposite(them);
         let move_type = m.move_type();
         let orig_square = m.orig_square();
         let dest_square = m.dest_square();
@@ -663,6 +773,7 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
 
         // Change the side to move.
         self.board.to_move = us;
+        self.pawn_hash ^= self.zobrist.pawn_hash_delta(us, m);
 
         // Restore the en-passant file.
         self.board.enpassant_file = m.enpassant_file();
@@ -714,7 +825,10 @@ impl<T: Evaluator> MoveGenerator for StdMoveGenerator<T> {
         // Tell the evaluator that a move was taken back.
         unsafe { self.evaluator.assume_init_mut().undone_move(&self.board, m) };
 
+        #[cfg(debug_assertions)]
+        assert_eq!(self.board.validate(), Ok(()));
         debug_assert!(self.is_legal());
+        debug_assert_eq!(self.pawn_hash, self.board.pawn_hash());
     }
 }
 
@@ -725,6 +839,11 @@ impl<T: Evaluator> SetOption for StdMoveGenerator<T> {
     }
 
     fn set_option(name: &str, value: &str) {
+        if name == "Safe King Moves" {
+            // This is an internal option, not visible to the GUI.
+            SAFE_KING_MOVES.store(value == "true", Ordering::Relaxed);
+            return;
+        }
         T::set_option(name, value)
     }
 }
@@ -761,7 +880,7 @@ impl<T: Evaluator> StdMoveGenerator<T> {
             return false;
         }
         let us = self.board.to_move;
-        let them = 1 ^ us;
+        let them = opposite(us);
         let enpassant_bb = self.enpassant_bb();
         let color: &[Bitboard; 2] = &self.board.pieces.color;
         let piece_type: &[Bitboard; 6] = &self.board.pieces.piece_type;
@@ -827,6 +946,116 @@ impl<T: Evaluator> StdMoveGenerator<T> {
         }
     }
 
+    /// A helper method for `generate_forcing`.
+    ///
+    /// If `safe_king_moves` is `true`, king moves to squares attacked
+    /// by the enemy are not generated. This saves the `do_move` calls
+    /// that would reject them, at the cost of an `attacks_to` call
+    /// for each king destination. (King moves along the line of a
+    /// checking slider can still be generated, because the king
+    /// itself hides the attacked square behind it.)
+    fn generate_forcing_moves<U: AddMove>(&self,
+                                          generate_checks: bool,
+                                          safe_king_moves: bool,
+                                          moves: &mut U) {
+        let (king_square, checkers) = self.king_square_and_checkers();
+        if checkers != 0 {
+            if self.is_double_check() {
+                // Double check -- only king moves can be evasions.
+                let occupied_by_us = self.board.pieces.color[self.board.to_move];
+                let dests = self.king_dests(!occupied_by_us, safe_king_moves);
+                return self.add_piece_moves(KING, king_square, dests, moves);
+            }
+            return self.generate_all(moves);
+        }
+        let pinned = self.find_pinned(king_square);
+        let occupied_by_us = unsafe {
+            *self.board
+                 .pieces
+                 .color
+                 .get_unchecked(self.board.to_move)
+        };
+        let occupied_by_them = self.board.occupied ^ occupied_by_us;
+        let enpassant_bb = self.enpassant_bb();
+        let pawn_dests;
+
+        // Generate queen, rook, bishop, and knight moves.
+        if generate_checks {
+            let their_king_square = bsf(self.board.pieces.piece_type[KING] & occupied_by_them);
+            unsafe {
+                pawn_dests = occupied_by_them | enpassant_bb | BB_PAWN_PROMOTION_RANKS |
+                             *self.geometry
+                                  .pawn_attacks
+                                  .get_unchecked(opposite(self.board.to_move))
+                                  .get_unchecked(their_king_square);
+                for piece in QUEEN..PAWN {
+                    let mut bb = self.board.pieces.piece_type[piece] & occupied_by_us;
+                    while bb != 0 {
+                        let orig_square = bsf_reset(&mut bb);
+                        let checking_squares =
+                            !occupied_by_us &
+                            self.geometry
+                                .attacks_from_unsafe(piece, their_king_square, self.board.occupied);
+                        let mut dests = occupied_by_them | checking_squares;
+                        if 1 << orig_square & pinned != 0 {
+                            dests &= self.geometry.squares_at_line[king_square][orig_square];
+                        }
+                        self.add_piece_moves(piece, orig_square, dests, moves);
+                    }
+                }
+            }
+        } else {
+            pawn_dests = occupied_by_them | enpassant_bb | BB_PAWN_PROMOTION_RANKS;
+            for piece in QUEEN..PAWN {
+                let mut bb = self.board.pieces.piece_type[piece] & occupied_by_us;
+                while bb != 0 {
+                    let orig_square = bsf_reset(&mut bb);
+                    let mut dests = occupied_by_them;
+                    if 1 << orig_square & pinned != 0 {
+                        dests &= self.geometry.squares_at_line[king_square][orig_square];
+                    }
+                    self.add_piece_moves(piece, orig_square, dests, moves);
+                }
+            }
+        }
+
+        // Generate pawn moves.
+        {
+            let our_pawns = self.board.pieces.piece_type[PAWN] & occupied_by_us;
+            let mut pinned_pawns = our_pawns & pinned;
+            self.add_pawn_moves(our_pawns ^ pinned_pawns, pawn_dests, true, moves);
+            while pinned_pawns != 0 {
+                let pawn_square = bsf_reset(&mut pinned_pawns);
+                let pawn_dests = pawn_dests &
+                                 self.geometry.squares_at_line[king_square][pawn_square];
+                self.add_pawn_moves(1 << pawn_square, pawn_dests, true, moves);
+            }
+        }
+
+        // Generate king moves.
+        let dests = self.king_dests(occupied_by_them, safe_king_moves);
+        self.add_piece_moves(KING, king_square, dests, moves);
+    }
+
+    /// A helper method for `generate_forcing_moves`. It returns the
+    /// subset of `dests` to which the king should be allowed to move.
+    #[inline]
+    fn king_dests(&self, dests: Bitboard, safe_king_moves: bool) -> Bitboard {
+        if !safe_king_moves {
+            return dests;
+        }
+        let occupied_by_them = self.board.pieces.color[opposite(self.board.to_move)];
+        let mut safe_dests = dests;
+        let mut bb = dests;
+        while bb != 0 {
+            let square = bsf_reset(&mut bb);
+            if self.attacks_to(square) & occupied_by_them != 0 {
+                safe_dests ^= 1 << square;
+            }
+        }
+        safe_dests
+    }
+
     /// A helper method. It finds all squares attacked by `piece` from
     /// square `orig_square`, and for each square that is within the
     /// `legal_dests` set adds a new move to `moves`. `piece` must not
@@ -888,7 +1117,7 @@ impl<T: Evaluator> StdMoveGenerator<T> {
                                 *self.board
                                      .pieces
                                      .color
-                                     .get_unchecked(1 ^ self.board.to_move),
+                                     .get_unchecked(opposite(self.board.to_move)),
                                 enpassant_bb,
                                 pawns,
                                 &mut dest_sets)
@@ -961,52 +1190,9 @@ impl<T: Evaluator> StdMoveGenerator<T> {
     /// A helper method. It returns all pinned pieces belonging to the
     /// side to move. `king_square` should the side to move's king
     /// square.
+    #[inline]
     fn find_pinned(&self, king_square: Square) -> Bitboard {
-        let mut pinned = 0;
-        unsafe {
-            let g: &BoardGeometry = &self.geometry;
-            let occupied_by_them = *self.board
-                                        .pieces
-                                        .color
-                                        .get_unchecked(1 ^ self.board.to_move);
-
-            // To find the potential pinners, we "remove" all our
-            // pieces from the board, and then verify if a bishop or a
-            // rook placed on our king's square can attack any enemy
-            // bishops, rooks, or queens.
-            let file_sliders = self.board.pieces.piece_type[QUEEN] |
-                               self.board.pieces.piece_type[ROOK];
-            let diag_sliders = self.board.pieces.piece_type[QUEEN] |
-                               self.board.pieces.piece_type[BISHOP];
-            let mut pinners =
-                occupied_by_them &
-                (file_sliders & g.attacks_from_unsafe(ROOK, king_square, occupied_by_them) |
-                 diag_sliders & g.attacks_from_unsafe(BISHOP, king_square, occupied_by_them));
-
-            // Then, for each potential pinner we verify if there is
-            // exactly one defender between our king and the pinner.
-            if pinners != 0 {
-                let defenders = *self.board
-                                     .pieces
-                                     .color
-                                     .get_unchecked(self.board.to_move) &
-                                !(1 << king_square);
-                loop {
-                    let pinner_square = bsf_reset(&mut pinners);
-                    let bb = defenders &
-                             *g.squares_between_including
-                                  .get_unchecked(king_square)
-                                  .get_unchecked(pinner_square);
-                    if lsb(bb) == bb {
-                        pinned |= bb;
-                    }
-                    if pinners == 0 {
-                        break;
-                    }
-                }
-            }
-        }
-        pinned
+        self.pinners_and_pinned(king_square, opposite(self.board.to_move)).1
     }
 
     /// A helper method. It returns the square that the king of the
@@ -1039,7 +1225,7 @@ impl<T: Evaluator> StdMoveGenerator<T> {
                          *self.board
                               .pieces
                               .color
-                              .get_unchecked(1 ^ self.board.to_move)
+                              .get_unchecked(opposite(self.board.to_move))
                      });
         }
         self.checkers.get()
@@ -1058,7 +1244,7 @@ impl<T: Evaluator> StdMoveGenerator<T> {
                          *self.board
                               .pieces
                               .color
-                              .get_unchecked(1 ^ self.board.to_move)
+                              .get_unchecked(opposite(self.board.to_move))
                      });
         }
         (king_square, self.checkers.get())
@@ -1075,7 +1261,7 @@ impl<T: Evaluator> StdMoveGenerator<T> {
             *self.board
                  .pieces
                  .color
-                 .get_unchecked(1 ^ self.board.to_move) &
+                 .get_unchecked(opposite(self.board.to_move)) &
             ((self.geometry
                   .attacks_from_unsafe(ROOK, dest_square, occupied) &
               (self.board.pieces.piece_type[ROOK] | self.board.pieces.piece_type[QUEEN])) |
@@ -1119,6 +1305,31 @@ impl<T: Evaluator> StdMoveGenerator<T> {
         }
     }
 
+    /// A helper method. It returns the move digest with the correct
+    /// move type for castling and en-passant capture digests that
+    /// have `MOVE_NORMAL` move type. (`MoveDigest::from_lan` creates
+    /// such digests.)
+    #[inline]
+    fn resolve_move_type(&self, move_digest: MoveDigest) -> MoveDigest {
+        if move_digest.move_type() == MOVE_NORMAL {
+            let orig_square = move_digest.orig_square();
+            let dest_square = move_digest.dest_square();
+            let orig_square_bb = 1 << orig_square;
+            let pieces = &self.board.pieces;
+            if orig_square_bb & pieces.color[self.board.to_move] != 0 {
+                if orig_square_bb & pieces.piece_type[KING] != 0 &&
+                   (dest_square as isize - orig_square as isize).abs() == 2 {
+                    return MoveDigest::new(MOVE_CASTLING, orig_square, dest_square, 0);
+                }
+                if orig_square_bb & pieces.piece_type[PAWN] != 0 &&
+                   1 << dest_square == self.enpassant_bb() {
+                    return MoveDigest::new(MOVE_ENPASSANT, orig_square, dest_square, 0);
+                }
+            }
+        }
+        move_digest
+    }
+
     /// A helper method. It returns a bitboard representing the
     /// en-passant target square if there is one.
     #[inline]
@@ -1146,7 +1357,7 @@ impl<T: Evaluator> StdMoveGenerator<T> {
             let occupied = self.board.occupied & !(pawn1_bb | pawn2_bb);
             return 0 ==
                    self.geometry.attacks_from(ROOK, king_square, occupied) &
-                   self.board.pieces.color[1 ^ self.board.to_move] &
+                   self.board.pieces.color[opposite(self.board.to_move)] &
                    (self.board.pieces.piece_type[ROOK] | self.board.pieces.piece_type[QUEEN]);
         }
         true
@@ -1171,6 +1382,24 @@ impl<T: Evaluator> StdMoveGenerator<T> {
 }
 
 
+/// A helper type for `generate_legal`. It passes on the moves added
+/// to it, except king's moves to attacked squares.
+struct LegalMoves<'a, T: Evaluator + 'a, U: AddMove + 'a> {
+    generator: &'a StdMoveGenerator<T>,
+    moves: &'a mut U,
+}
+
+impl<'a, T: Evaluator + 'a, U: AddMove + 'a> AddMove for LegalMoves<'a, T, U> {
+    #[inline]
+    fn add_move(&mut self, m: Move) {
+        if m.played_piece() != KING ||
+           !self.generator.king_would_be_in_check(m.orig_square(), m.dest_square()) {
+            self.moves.add_move(m);
+        }
+    }
+}
+
+
 /// Pawn move sub-type -- a single push.
 const PAWN_PUSH: usize = 0;
 
@@ -1195,6 +1424,14 @@ const PAWN_EAST_CAPTURE: usize = 3;
 static PAWN_MOVE_SHIFTS: [[isize; 4]; 2] = [[8, 16, 7, 9], [-8, -16, -9, -7]];
 
 
+/// Whether `generate_forcing` should skip king moves to attacked
+/// squares (see `StdMoveGenerator::generate_forcing_moves`).
+///
+/// This is set by the internal "Safe King Moves" option, and is off
+/// by default.
+static SAFE_KING_MOVES: AtomicBool = AtomicBool::new(false);
+
+
 /// The squares on rank 1 and rank 8.
 const BB_PAWN_PROMOTION_RANKS: Bitboard = BB_RANK_1 | BB_RANK_8;
 
@@ -1261,6 +1498,7 @@ fn calc_pawn_dest_sets(us: Color,
 mod tests {
     use board::*;
     use squares::*;
+    use moves::*;
     use utils::MoveStack;
     use move_generator::*;
     use evaluator::*;
@@ -1542,6 +1780,167 @@ mod tests {
         assert_eq!(s.list().len(), 0);
     }
 
+    #[test]
+    fn undo_restores_rights() {
+        let mut s = MoveStack::new();
+        let mut b = P::from_fen("r3k2r/8/8/5pP1/8/8/8/R3K2R w kKQ f6 0 1").ok().unwrap();
+        let castling_rights = b.board().castling_rights.value();
+        let enpassant_file = b.board().enpassant_file;
+        assert_eq!(enpassant_file, 5);
+        b.generate_all(&mut s);
+        s.push(b.null_move());
+        while let Some(m) = s.pop() {
+            assert_eq!(m.castling_rights().value(), castling_rights);
+            assert_eq!(m.enpassant_file(), enpassant_file);
+            if b.do_move(m).is_some() {
+                b.undo_move(m);
+                assert_eq!(b.board().castling_rights.value(), castling_rights);
+                assert_eq!(b.board().enpassant_file, enpassant_file);
+            }
+        }
+        let m = b.try_move_digest(MoveDigest::new(MOVE_NORMAL, A1, A8, 0)).unwrap();
+        assert!(b.do_move(m).is_some());
+        assert!(b.board().castling_rights.value() != castling_rights);
+        assert_eq!(b.board().enpassant_file, 8);
+        b.undo_move(m);
+        assert_eq!(b.board().castling_rights.value(), castling_rights);
+        assert_eq!(b.board().enpassant_file, enpassant_file);
+    }
+
+    #[test]
+    fn do_move_unchecked() {
+        let mut s = MoveStack::new();
+        for fen in ["b3k2r/6P1/8/5pP1/8/8/6P1/R3K2R w kKQ f6 0 1",
+                    "b3k2r/6P1/8/5pP1/8/8/8/R3K2R b kKQ - 0 1",
+                    "4k3/8/8/8/8/5n2/8/R3K2r w Q - 0 1"]
+                .iter() {
+            let mut b = P::from_fen(fen).ok().unwrap();
+            b.generate_all(&mut s);
+            while let Some(m) = s.pop() {
+                let mut c = b.clone();
+                if let Some(h) = b.do_move(m) {
+                    assert_eq!(c.do_move_unchecked(m), h);
+                    assert_eq!(c.hash(), b.hash());
+                    assert_eq!(c.board().pieces.color, b.board().pieces.color);
+                    assert_eq!(c.board().pieces.piece_type, b.board().pieces.piece_type);
+                    assert_eq!(c.board().castling_rights.value(),
+                               b.board().castling_rights.value());
+                    assert_eq!(c.board().enpassant_file, b.board().enpassant_file);
+                    b.undo_move(m);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn try_lan_move_digest() {
+        let b = P::from_fen("r3k3/1P6/8/3pP3/8/8/8/4K2R w Kq d6 0 1").ok().unwrap();
+        for &(lan, move_type) in [("e1f1", MOVE_NORMAL),
+                                  ("h1h8", MOVE_NORMAL),
+                                  ("b7a8n", MOVE_PROMOTION),
+                                  ("b7b8q", MOVE_PROMOTION),
+                                  ("e1g1", MOVE_CASTLING),
+                                  ("e5d6", MOVE_ENPASSANT)]
+                    .iter() {
+            let m = b.try_move_digest(MoveDigest::from_lan(lan).unwrap()).unwrap();
+            assert_eq!(m.notation(), lan);
+            assert_eq!(m.move_type(), move_type);
+        }
+        let m = b.try_move_digest(MoveDigest::new(MOVE_CASTLING, E1, G1, 0)).unwrap();
+        assert_eq!(m.notation(), "e1g1");
+        let m = b.try_move_digest(MoveDigest::new(MOVE_PROMOTION, B7, A8, 1)).unwrap();
+        assert_eq!(m.notation(), "b7a8r");
+        assert!(b.try_move_digest(MoveDigest::from_lan("e1c1").unwrap()).is_none());
+        assert!(b.try_move_digest(MoveDigest::from_lan("e5e6q").unwrap()).is_none());
+        assert!(b.try_move_digest(MoveDigest::from_lan("a8a7").unwrap()).is_none());
+    }
+
+    #[test]
+    fn uncapturable_enpassant() {
+        let b = P::from_fen("k7/8/8/8/4P3/8/8/K7 b - e3 0 1").ok().unwrap();
+        assert_eq!(b.board().enpassant_file, 8);
+        assert_eq!(b.hash(), P::from_fen("k7/8/8/8/4P3/8/8/K7 b - - 0 1").ok().unwrap().hash());
+        let b = P::from_fen("k7/8/8/8/2p1P3/8/8/K7 b - e3 0 1").ok().unwrap();
+        assert_eq!(b.board().enpassant_file, 8);
+        let b = P::from_fen("k7/8/8/8/3pP3/8/8/K7 b - e3 0 1").ok().unwrap();
+        assert_eq!(b.board().enpassant_file, 4);
+        let b = P::from_fen("k7/8/8/Pp6/8/8/8/K7 w - b6 0 1").ok().unwrap();
+        assert_eq!(b.board().enpassant_file, 1);
+
+        let mut s = MoveStack::new();
+        for &(fen, m, after) in [("k7/8/8/8/8/8/4P3/K7 w - - 0 1",
+                                  "e2e4",
+                                  "k7/8/8/8/4P3/8/8/K7 b - e3 0 1"),
+                                 ("k7/8/8/8/3p4/8/4P3/K7 w - - 0 1",
+                                  "e2e4",
+                                  "k7/8/8/8/3pP3/8/8/K7 b - e3 0 1")]
+                    .iter() {
+            let mut b = P::from_fen(fen).ok().unwrap();
+            let a = P::from_fen(after).ok().unwrap();
+            b.generate_all(&mut s);
+            let m = *s.list().iter().find(|x| x.notation() == m).unwrap();
+            s.clear_all();
+            let h = b.hash() ^ b.do_move(m).unwrap();
+            assert_eq!(b.board().enpassant_file, a.board().enpassant_file);
+            assert_eq!(h, a.hash());
+        }
+    }
+
+    #[test]
+    fn gives_check() {
+        let mut s = MoveStack::new();
+
+        let b = P::from_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1").ok().unwrap();
+        b.generate_all(&mut s);
+        let castling = *s.list().iter().find(|m| m.move_type() == MOVE_CASTLING).unwrap();
+        assert!(b.gives_check(castling));
+        s.clear_all();
+
+        let b = P::from_fen("2k5/8/8/8/8/8/8/R3K3 w Q - 0 1").ok().unwrap();
+        b.generate_all(&mut s);
+        let castling = *s.list().iter().find(|m| m.move_type() == MOVE_CASTLING).unwrap();
+        assert!(!b.gives_check(castling));
+        s.clear_all();
+
+        for fen in ["5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+                    "3k4/8/8/8/8/8/8/R3K3 w Q - 0 1",
+                    "b3k2r/6P1/8/5pP1/8/8/6P1/R3K2R w kKQ f6 0 1",
+                    "b3k2r/6P1/8/5pP1/8/8/8/R3K2R b kKQ - 0 1",
+                    "7k/8/8/1K1pP2r/8/8/8/8 w - d6 0 1",
+                    "1n2k3/2P5/8/8/8/6B1/8/4K2Q w - - 0 1",
+                    "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4"]
+                .iter() {
+            let mut b = P::from_fen(fen).ok().unwrap();
+            b.generate_all(&mut s);
+            while let Some(m) = s.pop() {
+                let gives_check = b.gives_check(m);
+                if b.do_move(m).is_some() {
+                    assert_eq!(gives_check, b.is_check());
+                    b.undo_move(m);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mvv_lva_order() {
+        let mut s = MoveStack::new();
+        let b = P::from_fen("4k3/8/2p5/1p1q4/2P5/1Q6/8/4K3 w - - 0 1")
+            .ok()
+            .unwrap();
+        b.generate_all(&mut s);
+        let pxq = s.list().iter().find(|m| m.notation() == "c4d5").cloned().unwrap();
+        let pxp = s.list().iter().find(|m| m.notation() == "c4b5").cloned().unwrap();
+        let qxp = s.list().iter().find(|m| m.notation() == "b3b5").cloned().unwrap();
+        assert!(s.list().iter().all(|m| m.score() == 0));
+        assert!(pxq > qxp);
+        assert!(pxq > pxp);
+        assert!(pxp > qxp);
+        assert_eq!(s.pull_best().unwrap(), pxq);
+        assert_eq!(s.pull_best().unwrap(), pxp);
+        assert_eq!(s.pull_best().unwrap(), qxp);
+    }
+
     #[test]
     fn find_pinned() {
         let b = P::from_fen("k2r4/3r4/3N4/5n2/qp1K2Pq/8/3PPR2/6b1 w - - 0 1")
@@ -1550,6 +1949,114 @@ mod tests {
         assert_eq!(b.find_pinned(b.king_square()), 1 << F2 | 1 << D6 | 1 << G4);
     }
 
+    #[test]
+    fn pinners_and_pinned() {
+        let b = P::from_fen("k2r4/3r4/3N4/5n2/qp1K2Pq/8/3PPR2/6b1 w - - 0 1")
+            .ok()
+            .unwrap();
+        let (pinners, pinned) = b.pinners_and_pinned(D4, BLACK);
+        assert_eq!(pinned, b.find_pinned(b.king_square()));
+        assert_eq!(pinners, 1 << D7 | 1 << G1 | 1 << H4);
+
+        // A hypothetical king square.
+        assert_eq!(b.pinners_and_pinned(E1, BLACK), (1 << H4, 1 << F2));
+        assert_eq!(b.pinners_and_pinned(A8, WHITE), (0, 0));
+
+        // A slider that attacks the square directly is not a pinner.
+        assert_eq!(b.pinners_and_pinned(F5, WHITE), (0, 0));
+    }
+
+    #[test]
+    fn safe_king_moves() {
+        let legal_moves = |b: &mut P, safe_king_moves: bool| {
+            let mut s = MoveStack::new();
+            b.generate_forcing_moves(true, safe_king_moves, &mut s);
+            let generated = s.list().len();
+            let mut legal = vec![];
+            while let Some(m) = s.pop() {
+                if b.do_move(m).is_some() {
+                    b.undo_move(m);
+                    legal.push(m.notation());
+                }
+            }
+            legal.sort();
+            (legal, generated)
+        };
+        for fen in ["4k3/8/8/8/8/5n2/8/R3K2r w Q - 0 1",
+                    "4k3/8/8/4p3/3n1n2/4K3/8/8 w - - 0 1",
+                    "4k3/8/8/2b5/3pn3/4K3/8/8 w - - 0 1",
+                    "k7/8/8/4Pb2/4K3/8/8/8 w - - 0 1",
+                    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"]
+                .iter() {
+            let mut b = P::from_fen(fen).ok().unwrap();
+            let (unfiltered, unfiltered_count) = legal_moves(&mut b, false);
+            let (filtered, filtered_count) = legal_moves(&mut b, true);
+            assert_eq!(filtered, unfiltered);
+            assert!(filtered_count <= unfiltered_count);
+        }
+
+        // King captures of defended pieces are not generated.
+        let mut b = P::from_fen("4k3/8/8/4p3/3n1n2/4K3/8/8 w - - 0 1").ok().unwrap();
+        assert_eq!(legal_moves(&mut b, false).1, 2);
+        assert_eq!(legal_moves(&mut b, true).1, 0);
+    }
+
+    #[test]
+    fn generate_legal() {
+        for fen in ["4k3/8/8/8/8/5n2/8/R3K2r w Q - 0 1",
+                    "4k3/8/8/4p3/3n1n2/4K3/8/8 w - - 0 1",
+                    "k7/8/8/4Pb2/4K3/8/8/8 w - - 0 1",
+                    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"]
+                .iter() {
+            let mut b = P::from_fen(fen).ok().unwrap();
+            let mut s = MoveStack::new();
+            b.generate_all(&mut s);
+            let mut verified = vec![];
+            while let Some(m) = s.pop() {
+                if b.do_move(m).is_some() {
+                    b.undo_move(m);
+                    verified.push(m);
+                }
+            }
+            let mut legal = vec![];
+            b.generate_legal(&mut legal);
+            assert_eq!(legal.len(), verified.len());
+            assert!(legal.iter().all(|m| verified.contains(m)));
+        }
+
+        // The king is in check, and can not escape along the line of
+        // the check.
+        let b = P::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").ok().unwrap();
+        let mut legal = vec![];
+        b.generate_legal(&mut legal);
+        let mut notation: Vec<_> = legal.iter().map(|m| m.notation()).collect();
+        notation.sort();
+        assert_eq!(notation, vec!["e1d2", "e1e2", "e1f2"]);
+    }
+
+    #[test]
+    fn result() {
+        let result = |fen| P::from_fen(fen).ok().unwrap().result();
+
+        // White is checkmated.
+        assert_eq!(result("4k3/8/8/8/8/8/5PPP/3r2K1 w - - 0 1"), GameResult::BlackWins);
+        assert_eq!(result("4k3/8/8/8/8/8/5PPP/3r2K1 w - - 0 1").token(), "0-1");
+
+        // Black is checkmated.
+        assert_eq!(result("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1").token(), "1-0");
+
+        // Stalemate.
+        assert_eq!(result("7k/8/6Q1/8/8/8/8/K7 b - - 0 1"), GameResult::Draw);
+        assert_eq!(result("7k/8/6Q1/8/8/8/8/K7 b - - 0 1").token(), "1/2-1/2");
+
+        // The only way out of check is a king's move.
+        assert_eq!(result("4k3/8/8/8/8/8/8/r3K3 w - - 0 1"), GameResult::Ongoing);
+        assert_eq!(result("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").token(), "*");
+    }
+
     #[test]
     fn generate_forcing() {
         let mut s = MoveStack::new();
@@ -1574,6 +2081,24 @@ mod tests {
         b.generate_forcing(false, &mut s);
         assert_eq!(s.list().len(), 7);
         s.clear_all();
+
+        let b = P::from_fen("4k3/8/8/8/8/5n2/8/R3K2r w Q - 0 1")
+            .ok()
+            .unwrap();
+        assert!(b.is_double_check());
+        b.generate_forcing(false, &mut s);
+        assert!(s.list().iter().all(|m| m.played_piece() == KING));
+        assert_eq!(s.list().len(), 5);
+        s.clear_all();
+        b.generate_all(&mut s);
+        assert!(s.list().iter().all(|m| m.played_piece() == KING));
+        s.clear_all();
+
+        let b = P::from_fen("4k3/8/8/8/8/5n2/8/R3K3 w Q - 0 1")
+            .ok()
+            .unwrap();
+        assert!(b.is_check());
+        assert!(!b.is_double_check());
     }
 
     #[test]
@@ -1673,6 +2198,39 @@ mod tests {
         try_all(&b, &s);
     }
 
+    #[test]
+    fn try_move_digest_rejects_foreign_digests() {
+        let fen = "r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 0 1";
+        let b = P::from_fen(fen).ok().unwrap();
+        let digest = |s| MoveDigest::from_lan(s).unwrap();
+
+        // Valid moves, including castling and en-passant.
+        assert!(b.try_move_digest(digest("a1a7")).is_some());
+        assert!(b.try_move_digest(digest("e1g1")).is_some());
+        assert!(b.try_move_digest(digest("e5d6")).is_some());
+        assert!(b.try_move_digest(MoveDigest::new(MOVE_CASTLING, E1, G1, 0)).is_some());
+        assert!(b.try_move_digest(MoveDigest::new(MOVE_ENPASSANT, E5, D6, 0)).is_some());
+
+        // The origin square holds an enemy piece.
+        assert!(b.try_move_digest(digest("a8a2")).is_none());
+        assert!(b.try_move_digest(digest("d5d4")).is_none());
+
+        // The origin square is empty.
+        assert!(b.try_move_digest(digest("b2b4")).is_none());
+        assert!(b.try_move_digest(digest("e4e5")).is_none());
+
+        // Castling rights are gone.
+        assert!(b.try_move_digest(digest("e1c1")).is_none());
+        assert!(b.try_move_digest(MoveDigest::new(MOVE_CASTLING, E1, C1, 0)).is_none());
+        assert!(b.try_move_digest(MoveDigest::new(MOVE_CASTLING, E8, G8, 0)).is_none());
+
+        // Impossible move types.
+        assert!(b.try_move_digest(MoveDigest::new(MOVE_ENPASSANT, E5, F6, 0)).is_none());
+        assert!(b.try_move_digest(MoveDigest::new(MOVE_PROMOTION, E5, E6, 0)).is_none());
+        assert!(b.try_move_digest(MoveDigest::new(MOVE_CASTLING, A1, D1, 0)).is_none());
+        assert!(b.try_move_digest(MoveDigest::invalid()).is_none());
+    }
+
     #[test]
     fn perft() {
         use utils::perft;
@@ -1682,6 +2240,7 @@ mod tests {
         assert_eq!(perft(&mut b, 1), 20);
         assert_eq!(perft(&mut b, 2), 400);
         assert_eq!(perft(&mut b, 3), 8_902);
+        assert_eq!(perft(&mut b, 4), 197_281);
 
         let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
         let mut b = P::from_fen(fen).ok().unwrap();
@@ -1714,4 +2273,71 @@ mod tests {
         assert_eq!(perft(&mut b, 2), 2_079);
         assert_eq!(perft(&mut b, 3), 89_890);
     }
+
+    #[test]
+    fn perft_divide() {
+        use utils::{perft, perft_divide};
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut b = P::from_fen(fen).ok().unwrap();
+        assert!(perft_divide(&mut b, 0).is_empty());
+        let divide = perft_divide(&mut b, 2);
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|x| x.1).sum::<u64>(), 2_039);
+        for (m, n) in divide {
+            assert!(b.do_move(m).is_some());
+            assert_eq!(perft(&mut b, 1), n);
+            b.undo_move(m);
+        }
+        let e1g1 = perft_divide(&mut b, 3)
+            .into_iter()
+            .find(|x| x.0.notation() == "e1g1")
+            .unwrap();
+        assert_eq!(e1g1.1, 2_059);
+    }
+
+    #[test]
+    fn evaluate_move() {
+        use search_node::SearchNode;
+        use stock::{StdSearchNode, StdQsearch};
+        type N = StdSearchNode<StdQsearch<P>>;
+
+        // Search nodes must evaluate moves exactly as their move
+        // generators do.
+        let see = |fen: &str, notation: &str| {
+            let mut s = MoveStack::new();
+            let p = P::from_fen(fen).ok().unwrap();
+            let n = N::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+            p.generate_all(&mut s);
+            let m = s.list().iter().find(|m| m.notation() == notation).cloned().unwrap();
+            assert_eq!(p.evaluate_move(m), n.evaluate_move(m));
+            p.evaluate_move(m)
+        };
+
+        // Simple exchanges.
+        assert_eq!(see("4k3/8/2p5/1p6/2P5/8/8/4K3 w - - 0 1", "c4b5"), 0);
+        assert_eq!(see("4k3/8/8/1n6/8/2N5/8/4K3 w - - 0 1", "c3b5"), 325);
+        assert_eq!(see("4k3/8/8/8/8/3p4/1R6/4K3 w - - 0 1", "b2c2"), -500);
+
+        // X-ray attackers, revealed when a slider captures.
+        assert_eq!(see("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5"), 100);
+        assert_eq!(see("4r1k1/4r3/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5"), -400);
+        assert_eq!(see("4r1k1/8/4n3/8/8/1B6/Q7/6K1 w - - 0 1", "b3e6"), 325);
+
+        // When the side that made the last capture wins anyway, the
+        // exchange is not examined further. Only the sign is correct
+        // then.
+        assert!(see("6k1/5p2/4p3/8/8/1B6/Q7/6K1 w - - 0 1", "b3e6") < 0);
+
+        // Promotion captures.
+        assert!(see("1n4k1/P7/8/8/8/8/8/6K1 w - - 0 1", "a7b8q") > 0);
+        assert!(see("1nr3k1/P7/8/8/8/8/8/6K1 w - - 0 1", "a7b8q") > 0);
+        assert!(see("1nr3k1/P7/8/8/8/8/8/6K1 w - - 0 1", "a7b8n") > 0);
+
+        // Exchanges involving the king.
+        assert_eq!(see("5r2/8/8/4q1p1/3P4/k3P1P1/P2b1R1B/K4R2 b - - 0 1", "a3a2"), -9900);
+        assert_eq!(see("3k4/8/8/8/8/8/3q4/3RK3 w - - 0 1", "e1d2"), 975);
+        assert_eq!(see("3k4/3p4/8/8/8/8/3Q4/3R2K1 w - - 0 1", "d2d7"), 100);
+        assert_eq!(see("3k4/3p4/8/8/8/8/8/3Q2K1 w - - 0 1", "d1d7"), -875);
+        assert_eq!(see("3k4/3q4/8/8/8/8/3Q4/3R2K1 w - - 0 1", "d2d7"), 975);
+    }
 }
@@ -105,6 +105,11 @@ fn qsearch<T: MoveGenerator>(position: &mut T,
                   stand_pat == position.evaluator().evaluate(position.board()));
     const PIECE_VALUES: [Value; 8] = [10000, 975, 500, 325, 325, 100, 0, 0];
 
+    // Checks are generated only during the first `CHECK_PLIES`
+    // plies. After that only captures and promotions are tried, so
+    // that long sequences of checks can not blow up the tree.
+    const CHECK_PLIES: i8 = 1;
+
     let is_check = position.is_check();
 
     // At the beginning of quiescence, position's static evaluation
@@ -131,9 +136,9 @@ fn qsearch<T: MoveGenerator>(position: &mut T,
                                    (PIECE_VALUES[KNIGHT] - 4 * PIECE_VALUES[PAWN] / 3) as isize;
 
     // Generate all forcing moves. (Include checks only during the
-    // first ply.)
+    // first few plies.)
     move_stack.save();
-    position.generate_forcing(ply <= 0, move_stack);
+    position.generate_forcing(ply < CHECK_PLIES, move_stack);
 
     // Consider the generated moves one by one. See if any of them
     // can raise the lower bound.
@@ -229,7 +234,7 @@ mod tests {
     use board::*;
     use value::*;
     use move_generator::*;
-    use stock::{SimpleEvaluator, StdMoveGenerator};
+    use stock::{SimpleEvaluator, StdMoveGenerator, TEMPO};
     use utils::MoveStack;
     type P = StdMoveGenerator<SimpleEvaluator>;
 
@@ -237,7 +242,9 @@ mod tests {
     fn qsearch() {
         use super::qsearch;
         let mut s = MoveStack::new();
-        let d = 32;
+
+        // The evaluation noise, plus the bonus for having the move.
+        let d = 32 + TEMPO;
 
         let fen = "8/8/8/8/6k1/6P1/8/6K1 b - - 0 1";
         let board = Board::from_fen(fen).ok().unwrap();
@@ -279,4 +286,29 @@ mod tests {
         let mut p = P::from_board(board).ok().unwrap();
         assert!(qsearch(&mut p, -10000, 10000, VALUE_UNKNOWN, 0, 0, &mut s, &mut 0) <= -10000);
     }
+    #[test]
+    fn qsearch_checks() {
+        use super::qsearch;
+        let mut s = MoveStack::new();
+
+        // Back-rank mate -- seen only when checks are generated.
+        let fen = "6k1/5ppp/8/8/8/8/q4PPP/3Q2K1 w - - 0 1";
+        let board = Board::from_fen(fen).ok().unwrap();
+        let mut p = P::from_board(board).ok().unwrap();
+        let mut nodes = 0;
+        assert!(qsearch(&mut p, -10000, 10000, VALUE_UNKNOWN, 0, 0, &mut s, &mut nodes) >= 10000);
+        assert!(nodes < 1000);
+        assert!(qsearch(&mut p, -10000, 10000, VALUE_UNKNOWN, 0, 1, &mut s, &mut 0) < 1000);
+
+        // Both queens can give checks forever.
+        let fen = "6k1/8/8/3q4/3Q4/8/8/K7 w - - 0 1";
+        let board = Board::from_fen(fen).ok().unwrap();
+        let mut p = P::from_board(board).ok().unwrap();
+        let mut nodes = 0;
+        assert!(qsearch(&mut p, -10000, 10000, VALUE_UNKNOWN, 0, 0, &mut s, &mut nodes).abs() <
+                1000);
+        assert!(nodes > 0);
+        assert!(nodes < 10000);
+        assert!(s.list().is_empty());
+    }
 }
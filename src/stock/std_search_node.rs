@@ -2,6 +2,8 @@
 
 use std::cmp::min;
 use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::marker::PhantomData;
 use std::hash::Hasher;
 use std::collections::hash_map::DefaultHasher;
 use uci::{SetOption, OptionDescription};
@@ -39,10 +41,11 @@ pub struct StdSearchNode<T: Qsearch> {
     /// The count of half-moves since the beginning of the game.
     halfmove_count: u16,
 
-    /// `true` if the position is deemed as a draw by repetition or
+    /// `true` if the position is deemed as a draw by repetition,
     /// because 50 moves have been played without capturing a piece or
-    /// advancing a pawn.
-    repeated_or_rule50: bool,
+    /// advancing a pawn, or because none of the players can possibly
+    /// checkmate.
+    is_draw: bool,
 
     /// The hash value for the underlying `Board` instance.
     board_hash: u64,
@@ -66,6 +69,8 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
 
     type QsearchResult = T::QsearchResult;
 
+    type MoveGenerator = T::MoveGenerator;
+
     fn from_history(fen: &str, moves: &mut dyn Iterator<Item = &str>) -> Result<Self, IllegalBoard> {
         let mut p: StdSearchNode<T> = StdSearchNode::from_fen(fen)?;
         let mut move_list = Vec::new();
@@ -99,11 +104,12 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
         //    have equal hashes, as long as they both are far from the
         //    rule-50 limit.
 
-        if self.repeated_or_rule50 {
-            // All repeated and rule-50 positions are a draw, so for
-            // practical purposes they can be considered to be the
-            // exact same position, and therefore we can generate the
-            // same hash value for all of them. This has the important
+        if self.is_draw {
+            // All repeated, rule-50, and insufficient material
+            // positions are a draw, so for practical purposes they
+            // can be considered to be the exact same position, and
+            // therefore we can generate the same hash value for all
+            // of them. This has the important
             // practical advantage that we get two separate records in
             // the transposition table for the first and the second
             // occurrence of the same position. (The second occurrence
@@ -149,6 +155,11 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
         self.position().is_check()
     }
 
+    #[inline]
+    fn gives_check(&self, m: Move) -> bool {
+        self.position().gives_check(m)
+    }
+
     #[inline]
     fn evaluator(&self) -> &Self::Evaluator {
         self.position().evaluator()
@@ -156,7 +167,7 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
 
     #[inline]
     fn evaluate_final(&self) -> Value {
-        if self.repeated_or_rule50 || !self.is_check() {
+        if self.is_draw || !self.is_check() {
             0
         } else {
             VALUE_MIN
@@ -179,7 +190,7 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
         debug_assert!(lower_bound >= VALUE_MIN);
         debug_assert!(upper_bound <= VALUE_MAX);
         debug_assert!(lower_bound < upper_bound);
-        if self.repeated_or_rule50 {
+        if self.is_draw {
             Self::QsearchResult::new(0, 0)
         } else {
             T::qsearch(QsearchParams {
@@ -194,14 +205,14 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
 
     #[inline]
     fn generate_moves<U: AddMove>(&self, moves: &mut U) {
-        if !self.repeated_or_rule50 {
+        if !self.is_draw {
             self.position().generate_all(moves);
         }
     }
 
     #[inline]
     fn try_move_digest(&self, move_digest: MoveDigest) -> Option<Move> {
-        if self.repeated_or_rule50 {
+        if self.is_draw {
             None
         } else {
             self.position().try_move_digest(move_digest)
@@ -219,7 +230,7 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
     }
 
     fn do_move(&mut self, m: Move) -> bool {
-        if self.repeated_or_rule50 && m.is_null() {
+        if self.is_draw && m.is_null() {
             // This is a final position -- null moves are not
             // allowed. We must still allow other moves though,
             // because `from_history` should be able to call `do_move`
@@ -228,52 +239,18 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
         }
 
         if let Some(h) = unsafe { self.position_mut().do_move(m) } {
-            let halfmove_clock = if m.is_pawn_advance_or_capure() {
-                0
-            } else {
-                match self.state().halfmove_clock {
-                    x if x < 99 => x + 1,
-                    _ => {
-                        if !self.is_checkmate() {
-                            self.repeated_or_rule50 = true;
-                        }
-                        99
-                    }
-                }
-            };
-            self.halfmove_count += 1;
-            self.encountered_boards.push(self.board_hash);
-            self.board_hash ^= h;
-            debug_assert!(halfmove_clock <= 99);
-            debug_assert!(self.encountered_boards.len() >= halfmove_clock as usize);
-
-            // Figure out if the new position is repeated (a draw).
-            if halfmove_clock >= 4 {
-                let boards = &self.encountered_boards;
-                let last_irrev = (boards.len() - (halfmove_clock as usize)) as isize;
-                unsafe {
-                    let mut i = (boards.len() - 4) as isize;
-                    while i >= last_irrev {
-                        if self.board_hash == *boards.get_unchecked(i as usize) {
-                            self.repeated_or_rule50 = true;
-                            break;
-                        }
-                        i -= 2;
-                    }
-                }
-            }
-
-            self.state_stack
-                .push(PositionInfo {
-                          halfmove_clock: halfmove_clock,
-                          last_move: m,
-                      });
+            self.register_move(m, h);
             return true;
         }
-
         false
     }
 
+    fn do_move_unchecked(&mut self, m: Move) {
+        debug_assert!(!(self.is_draw && m.is_null()));
+        let h = unsafe { self.position_mut().do_move_unchecked(m) };
+        self.register_move(m, h);
+    }
+
     #[inline]
     fn undo_last_move(&mut self) {
         debug_assert!(self.state_stack.len() > 1);
@@ -282,8 +259,9 @@ impl<T: Qsearch> SearchNode for StdSearchNode<T> {
         }
         self.halfmove_count -= 1;
         self.board_hash = self.encountered_boards.pop().unwrap();
-        self.repeated_or_rule50 = false;
+        self.is_draw = false;
         self.state_stack.pop();
+        debug_assert_eq!(self.board_hash, self.position().hash());
     }
 }
 
@@ -300,6 +278,60 @@ impl<T: Qsearch> Clone for StdSearchNode<T> {
 }
 
 
+/// A snapshot of a `StdSearchNode`'s position.
+///
+/// Snapshots are cheap to clone and can be sent between threads. This
+/// is useful when several threads must search the same root
+/// position. Each thread should obtain its own `StdSearchNode`
+/// instance by calling `node`.
+pub struct StdSearchNodeSnapshot<T: Qsearch> {
+    board: Board,
+    state: PositionInfo,
+    halfmove_count: u16,
+    repeated_boards_hash: u64,
+    encountered_boards: Arc<Vec<u64>>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Qsearch> StdSearchNodeSnapshot<T> {
+    /// Creates a new `StdSearchNode` instance for the snapshotted
+    /// position.
+    ///
+    /// The new instance has its own move generator and evaluator,
+    /// and no played moves.
+    pub fn node(&self) -> StdSearchNode<T> {
+        let gen = T::MoveGenerator::from_board(self.board.clone())
+            .ok()
+            .unwrap();
+        let mut encountered_boards = Vec::with_capacity(self.encountered_boards.len() + 32);
+        encountered_boards.extend_from_slice(&self.encountered_boards);
+        let mut state_stack = Vec::with_capacity(32);
+        state_stack.push(self.state);
+        StdSearchNode {
+            zobrist: ZobristArrays::get(),
+            halfmove_count: self.halfmove_count,
+            board_hash: gen.hash(),
+            position: UnsafeCell::new(gen),
+            is_draw: false,
+            repeated_boards_hash: self.repeated_boards_hash,
+            encountered_boards,
+            state_stack,
+        }
+    }
+}
+
+impl<T: Qsearch> Clone for StdSearchNodeSnapshot<T> {
+    fn clone(&self) -> Self {
+        StdSearchNodeSnapshot {
+            board: self.board.clone(),
+            encountered_boards: self.encountered_boards.clone(),
+            phantom: PhantomData,
+            ..*self
+        }
+    }
+}
+
+
 impl<T: Qsearch> SetOption for StdSearchNode<T> {
     fn options() -> Vec<(&'static str, OptionDescription)> {
         T::options()
@@ -321,7 +353,7 @@ impl<T: Qsearch> StdSearchNode<T> {
                halfmove_count: ((fullmove_number - 1) << 1) + gen.board().to_move as u16,
                board_hash: gen.hash(),
                position: UnsafeCell::new(gen),
-               repeated_or_rule50: false,
+               is_draw: false,
                repeated_boards_hash: 0,
                encountered_boards: vec![0; halfmove_clock as usize],
                state_stack: vec![PositionInfo {
@@ -331,14 +363,31 @@ impl<T: Qsearch> StdSearchNode<T> {
            })
     }
 
+    /// Returns a snapshot of the current position.
+    ///
+    /// The current position becomes the root position for the
+    /// instances created from the snapshot.
+    pub fn snapshot(&self) -> StdSearchNodeSnapshot<T> {
+        let mut root = self.clone();
+        root.declare_as_root();
+        StdSearchNodeSnapshot {
+            board: root.board().clone(),
+            state: *root.state(),
+            halfmove_count: root.halfmove_count,
+            repeated_boards_hash: root.repeated_boards_hash,
+            encountered_boards: Arc::new(root.encountered_boards),
+            phantom: PhantomData,
+        }
+    }
+
     /// Forgets the previous playing history, preserves only the set
     /// of previously repeated, still reachable boards.
     fn declare_as_root(&mut self) {
         let state = *self.state();
 
         // The root position is never deemed as a draw due to
-        // repetition or rule-50.
-        self.repeated_or_rule50 = false;
+        // repetition, rule-50, or insufficient material.
+        self.is_draw = false;
 
         // Calculate the set of previously repeated, still reachable boards.
         let repeated_boards = {
@@ -369,6 +418,60 @@ impl<T: Qsearch> StdSearchNode<T> {
         self.state_stack.reserve(32);
     }
 
+    /// Updates the playing history after move `m` has been played on
+    /// the board. `h` is the value returned by the move generator.
+    #[inline]
+    fn register_move(&mut self, m: Move, h: u64) {
+        let halfmove_clock = if m.is_pawn_advance_or_capure() {
+            0
+        } else {
+            match self.state().halfmove_clock {
+                x if x < 99 => x + 1,
+                _ => {
+                    if !self.is_checkmate() {
+                        self.is_draw = true;
+                    }
+                    99
+                }
+            }
+        };
+        self.halfmove_count += 1;
+        self.encountered_boards.push(self.board_hash);
+        self.board_hash ^= h;
+        debug_assert!(halfmove_clock <= 99);
+        debug_assert!(self.encountered_boards.len() >= halfmove_clock as usize);
+
+        // Figure out if there is insufficient material left (a
+        // draw). This can happen only after a capture or a pawn
+        // promotion.
+        if halfmove_clock == 0 && self.board().is_insufficient_material() {
+            self.is_draw = true;
+        }
+
+        // Figure out if the new position is repeated (a draw).
+        if halfmove_clock >= 4 {
+            let boards = &self.encountered_boards;
+            let last_irrev = (boards.len() - (halfmove_clock as usize)) as isize;
+            unsafe {
+                let mut i = (boards.len() - 4) as isize;
+                while i >= last_irrev {
+                    if self.board_hash == *boards.get_unchecked(i as usize) {
+                        self.is_draw = true;
+                        break;
+                    }
+                    i -= 2;
+                }
+            }
+        }
+
+        self.state_stack
+            .push(PositionInfo {
+                      halfmove_clock: halfmove_clock,
+                      last_move: m,
+                  });
+        debug_assert_eq!(self.board_hash, self.position().hash());
+    }
+
     /// Returns if the root position (the earliest in `state_stack`)
     /// can be reached by playing moves from the current position.
     #[inline]
@@ -447,14 +550,167 @@ fn set_non_repeated_values<T>(slice: &mut [T], value: T) -> Vec<T>
 #[cfg(test)]
 mod tests {
     use utils::MoveStack;
+    use squares::*;
     use value::*;
     use search_node::*;
     use evaluator::*;
     use qsearch::*;
     use moves::Move;
+    use board::*;
     use stock::{StdSearchNode, StdQsearch, StdMoveGenerator, SimpleEvaluator};
     type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
 
+    #[test]
+    fn undo_last_moves() {
+        let mut p = P::from_history("r3k2r/pppq1ppp/2n2n2/3pp3/3PP3/2N2N2/PPPQ1PPP/R3K2R w KQkq - 4 7",
+                                    &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let hash = p.hash();
+        let board = p.board().clone();
+        let halfmove_clock = p.halfmove_clock();
+        let fullmove_number = p.fullmove_number();
+        let static_eval = p.evaluator().evaluate(p.board());
+        for played_move in &["e1c1", "e8g8", "d4e5", "c6e5", "f3e5"] {
+            let m = p.legal_moves()
+                .into_iter()
+                .find(|m| m.notation() == *played_move)
+                .unwrap();
+            assert!(p.do_move(m));
+        }
+        assert!(p.hash() != hash);
+        p.undo_last_moves(5);
+        assert_eq!(p.hash(), hash);
+        assert_eq!(p.board().pieces.color, board.pieces.color);
+        assert_eq!(p.board().pieces.piece_type, board.pieces.piece_type);
+        assert_eq!(p.board().castling_rights.value(), board.castling_rights.value());
+        assert_eq!(p.board().enpassant_file, board.enpassant_file);
+        assert_eq!(p.board().to_move, board.to_move);
+        assert_eq!(p.halfmove_clock(), halfmove_clock);
+        assert_eq!(p.fullmove_number(), fullmove_number);
+        assert_eq!(p.evaluator().evaluate(p.board()), static_eval);
+    }
+
+    #[test]
+    fn do_move_unchecked() {
+        let fen = "7k/8/8/8/8/8/8/R6K w - - 0 1";
+        let mut p = P::from_history(fen, &mut vec![].into_iter()).ok().unwrap();
+        let mut q = p.clone();
+        for played_move in &["a1a2", "h8g8", "a2a1", "g8h8"] {
+            let m = p.legal_moves()
+                .into_iter()
+                .find(|m| m.notation() == *played_move)
+                .unwrap();
+            assert!(p.do_move(m));
+            q.do_move_unchecked(m);
+            assert_eq!(q.hash(), p.hash());
+            assert_eq!(q.halfmove_clock(), p.halfmove_clock());
+        }
+
+        // The repetition is detected too.
+        assert!(q.legal_moves().is_empty());
+        q.undo_last_moves(4);
+        assert_eq!(q.board().pieces.color, P::from_fen(fen).ok().unwrap().board().pieces.color);
+    }
+
+    #[test]
+    fn legal_moves_for() {
+        let p = P::from_history("4k3/8/8/8/1b6/8/3N4/4K1N1 w - - 0 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        let mut dests: Vec<String> = p.legal_moves_for(G1).iter().map(|m| m.notation()).collect();
+        dests.sort();
+        assert_eq!(dests, vec!["g1e2", "g1f3", "g1h3"]);
+        assert!(p.legal_moves_for(D2).is_empty());
+        assert!(p.legal_moves_for(E4).is_empty());
+        assert!(p.legal_moves_for(E8).is_empty());
+        assert_eq!(p.legal_moves_for(E1).len(), 4);
+    }
+
+    #[test]
+    fn do_null_move() {
+        let mut p = P::from_history("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1",
+                                    &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let hash = p.hash();
+        let board = p.board().clone();
+        assert_eq!(board.enpassant_file, 3);
+        {
+            let mut g = p.do_null_move().unwrap();
+            assert_eq!(g.board().to_move, BLACK);
+            assert_eq!(g.board().enpassant_file, 8);
+            assert!(g.last_move().is_null());
+            assert!(g.hash() != hash);
+            assert!(!g.legal_moves().is_empty());
+            assert!(g.do_null_move().is_some());
+        }
+        assert_eq!(p.hash(), hash);
+        assert_eq!(p.board().to_move, board.to_move);
+        assert_eq!(p.board().enpassant_file, board.enpassant_file);
+        assert_eq!(p.board().castling_rights.value(), board.castling_rights.value());
+        assert_eq!(p.board().pieces.color, board.pieces.color);
+        assert_eq!(p.board().pieces.piece_type, board.pieces.piece_type);
+        assert!(p.legal_moves().iter().any(|m| m.notation() == "e5d6"));
+
+        let mut p = P::from_history("4k3/8/8/8/8/8/8/4K2r w - - 0 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        assert!(p.do_null_move().is_none());
+    }
+
+    #[test]
+    fn snapshot() {
+        use std::thread;
+
+        fn perft(p: &mut P, depth: usize) -> u64 {
+            if depth == 0 {
+                return 1;
+            }
+            let mut count = 0;
+            for m in p.legal_moves() {
+                assert!(p.do_move(m));
+                count += perft(p, depth - 1);
+                p.undo_last_move();
+            }
+            count
+        }
+
+        let p = P::from_history("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+                                &mut vec!["f3g1", "c6b8", "g1f3", "b8c6"].into_iter())
+                .ok()
+                .unwrap();
+        let snapshot = p.snapshot();
+        let root = snapshot.node();
+        assert_eq!(root.hash(), p.hash());
+        assert_eq!(root.halfmove_clock(), p.halfmove_clock());
+        assert_eq!(root.fullmove_number(), p.fullmove_number());
+        let legal_moves = p.legal_moves();
+        let expected: Vec<u64> = legal_moves
+            .iter()
+            .map(|&m| {
+                     let mut p = p.clone();
+                     assert!(p.do_move(m));
+                     perft(&mut p, 2)
+                 })
+            .collect();
+
+        let handles: Vec<_> = legal_moves
+            .iter()
+            .map(|&m| {
+                     let snapshot = snapshot.clone();
+                     thread::spawn(move || {
+                                       let mut p = snapshot.node();
+                                       assert!(p.do_move(m));
+                                       perft(&mut p, 2)
+                                   })
+                 })
+            .collect();
+        let results: Vec<u64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(results, expected);
+        assert_eq!(snapshot.node().hash(), p.hash());
+    }
+
     #[test]
     fn is_legal() {
         assert!(P::from_fen("8/8/8/8/8/8/8/8 w - - 0 1").is_err());
@@ -696,6 +952,54 @@ mod tests {
         assert!(!p.is_checkmate());
     }
 
+    #[test]
+    fn insufficient_material() {
+        let p = P::from_fen("4k3/8/8/8/8/8/4r3/4KB2 w - - 0 1")
+            .ok()
+            .unwrap();
+        let moves = p.legal_moves();
+
+        // Capturing the last rook leaves only a bishop on the board.
+        let mut q = p.clone();
+        assert!(q.do_move(*moves.iter().find(|m| m.notation() == "f1e2").unwrap()));
+        assert!(q.legal_moves().is_empty());
+        assert_eq!(q.evaluate_final(), 0);
+        assert_eq!(q.hash(), 1);
+        q.undo_last_move();
+        assert!(!q.legal_moves().is_empty());
+
+        // Two knights are not an automatic draw.
+        let p = P::from_fen("4k3/8/8/8/8/8/4r3/4KNN1 w - - 0 1")
+            .ok()
+            .unwrap();
+        let mut q = p.clone();
+        assert!(q.do_move(*p.legal_moves().iter().find(|m| m.notation() == "e1e2").unwrap()));
+        assert!(!q.legal_moves().is_empty());
+        assert!(q.hash() != 1);
+    }
+
+    #[test]
+    fn rule50_checkmate() {
+        let p = P::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80")
+            .ok()
+            .unwrap();
+        let moves = p.legal_moves();
+
+        // The move that completes 50 reversible moves is checkmate.
+        let mut q = p.clone();
+        assert!(q.do_move(*moves.iter().find(|m| m.notation() == "a1a8").unwrap()));
+        assert!(q.is_check());
+        assert_eq!(q.evaluate_final(), VALUE_MIN);
+        assert!(q.hash() != 1);
+
+        // Any other reversible move leads to a draw.
+        let mut q = p.clone();
+        assert!(q.do_move(*moves.iter().find(|m| m.notation() == "a1a7").unwrap()));
+        assert!(q.legal_moves().is_empty());
+        assert_eq!(q.evaluate_final(), 0);
+        assert_eq!(q.hash(), 1);
+    }
+
     #[test]
     fn repeated_boards_hash() {
         let p1 = P::from_fen("8/8/8/8/8/7k/8/7K w - - 0 1").ok().unwrap();
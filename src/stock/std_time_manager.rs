@@ -2,11 +2,13 @@
 
 use std::sync::RwLock;
 use std::time::{SystemTime, Duration};
-use std::cmp::min;
+use std::cmp::{min, Reverse};
 use board::*;
 use depth::*;
 use value::*;
+use moves::Move;
 use search::*;
+use qsearch::QsearchResult;
 use ttable::Variation;
 use search_node::SearchNode;
 use time_manager::{TimeManager, RemainingTime};
@@ -22,6 +24,18 @@ pub struct StdTimeManager {
     hard_limit: f64,
     allotted_time: f64,
     must_play: bool,
+
+    // The minimum time (milliseconds) the engine must think before
+    // playing a move, regardless of the search progress.
+    min_think_time: f64,
+
+    // A root move that looks much better than all the alternatives,
+    // together with the value of the second-best move.
+    easy_move: Option<(Move, Value)>,
+
+    // The number of consecutive completed depths for which the easy
+    // move remained the best move.
+    easy_move_depths: usize,
 }
 
 
@@ -42,12 +56,14 @@ impl<T> TimeManager<T> for StdTimeManager
         let n = time.movestogo.unwrap_or(40) as f64;
         debug_assert!(n >= 1.0);
 
-        // Calculate the total time we have.
-        let time_heap = t + inc * (n - 1.0);
+        let ponder = ::get_option("Ponder") == "true";
+        let (allotted_time, hard_limit) = time_budget(t, inc, n, ponder);
 
-        // Set a hard limit for the time we will spend on this
-        // move. Thinking longer that that would be reckless.
-        let hard_limit = (t / n.sqrt() + inc).min(t - 1000.0);
+        // The minimum thinking time must never exceed the hard limit.
+        let min_think_time = ::get_option("MinThinkTime")
+            .parse::<f64>()
+            .unwrap_or(0.0)
+            .min(hard_limit);
 
         StdTimeManager {
             started_at: SystemTime::now(),
@@ -62,18 +78,15 @@ impl<T> TimeManager<T> for StdTimeManager
                 // order to find a good ponder move.
                 hard_limit.min(500.0)
             },
-            allotted_time: if ::get_option("Ponder") == "true" {
-                // Statistically, the move we ponder will be played in
-                // 50% of the cases. Therefore, in principal we should
-                // add half of opponent's thinking time to our time
-                // heap. In reality we do not know how opponent's time
-                // will be spend, so we speculatively increase our
-                // time heap by 50%.
-                1.5 * time_heap / n
+            allotted_time,
+            must_play: false,
+            min_think_time: if position.legal_moves().len() > 1 {
+                min_think_time
             } else {
-                time_heap / n
+                0.0
             },
-            must_play: false,
+            easy_move: find_easy_move(position),
+            easy_move_depths: 0,
         }
     }
 
@@ -83,18 +96,30 @@ impl<T> TimeManager<T> for StdTimeManager
                  report: Option<&SearchReport<Vec<Variation>>>)
                  -> bool {
         if !self.must_play {
+            let elapsed = elapsed_millis(&self.started_at);
+
+            // Before the minimum thinking time has passed, we neither
+            // set a target depth (the search would end prematurely),
+            // nor play the easy move.
+            let may_play = elapsed >= self.min_think_time;
+
             let mut is_finished = false;
             if let Some(r) = report {
                 if r.depth > self.depth {
                     self.depth = r.depth;
                     let (target_depth, t_next) = self.target_depth(r);
                     let t_pessimistic = t_next * AVG_SLOPE.read().unwrap().exp().sqrt();
-                    let msg = format!("TARGET_DEPTH={}", target_depth);
-                    search_instance.send_message(msg.as_str());
-                    is_finished = r.depth >= target_depth || t_pessimistic > self.hard_limit
+                    let is_easy_move = self.is_easy_move(r);
+                    if may_play {
+                        let msg = format!("TARGET_DEPTH={}", target_depth);
+                        search_instance.send_message(msg.as_str());
+                        is_finished = r.depth >= target_depth ||
+                                      t_pessimistic > self.hard_limit ||
+                                      is_easy_move;
+                    }
                 }
             }
-            self.must_play = is_finished || elapsed_millis(&self.started_at) > self.hard_limit;
+            self.must_play = may_play && (is_finished || elapsed > self.hard_limit);
         }
         self.must_play
     }
@@ -103,7 +128,13 @@ impl<T> TimeManager<T> for StdTimeManager
 
 impl SetOption for StdTimeManager {
     fn options() -> Vec<(&'static str, OptionDescription)> {
-        vec![("Ponder", OptionDescription::Check { default: false })]
+        vec![("Ponder", OptionDescription::Check { default: false }),
+             ("MinThinkTime",
+              OptionDescription::Spin {
+                  min: 0,
+                  max: 10000,
+                  default: 0,
+              })]
     }
 }
 
@@ -169,6 +200,97 @@ impl StdTimeManager {
 
         (target_depth, t_next)
     }
+
+    /// Returns `true` if the easy move has remained the best move,
+    /// with a big enough margin, for the last few completed depths.
+    fn is_easy_move(&mut self, report: &SearchReport<Vec<Variation>>) -> bool {
+        if let Some((m, second_best_value)) = self.easy_move {
+            if report.best_move() == Some(m) && report.value != VALUE_UNKNOWN &&
+               report.value as isize - second_best_value as isize >= EASY_MOVE_MARGIN {
+                self.easy_move_depths += 1;
+            } else {
+                self.easy_move = None;
+            }
+        }
+        self.easy_move.is_some() && self.easy_move_depths >= EASY_MOVE_DEPTHS
+    }
+}
+
+
+/// Calculates the time budget (milliseconds) for the current move.
+///
+/// `t` is our remaining time, `inc` is our time increment, and `n`
+/// is the number of moves until the next time control. Returns the
+/// time we would like to spend on the move, and a hard limit for it.
+fn time_budget(t: f64, inc: f64, n: f64, ponder: bool) -> (f64, f64) {
+    // Calculate the total time we have.
+    let time_heap = t + inc * (n - 1.0);
+
+    let allotted_time = if ponder {
+        // Statistically, the move we ponder will be played in 50% of
+        // the cases. Therefore, in principal we should add half of
+        // opponent's thinking time to our time heap. In reality we
+        // do not know how opponent's time will be spend, so we
+        // speculatively increase our time heap by 50%.
+        1.5 * time_heap / n
+    } else {
+        time_heap / n
+    };
+
+    // Set a hard limit for the time we will spend on this move.
+    // Thinking longer that that would be reckless. On very low time,
+    // the safety margin is reduced, so that it does not eat up all
+    // our remaining time.
+    let safety_margin = (t / 10.0).min(SAFETY_MARGIN);
+    let hard_limit = (t / n.sqrt() + inc).min(t - safety_margin).max(0.0);
+
+    (allotted_time, hard_limit)
+}
+
+
+/// The time (milliseconds) that we keep in reserve, so as not to lose
+/// on time because of communication delays.
+const SAFETY_MARGIN: f64 = 1000.0;
+
+
+/// The minimal margin (in centipawns) by which the easy move must be
+/// better than the second-best move.
+///
+/// TODO: `200` must be bound to pawn's value.
+const EASY_MOVE_MARGIN: isize = 200;
+
+/// The number of consecutive completed depths for which the easy
+/// move must remain the best move, before it is played.
+const EASY_MOVE_DEPTHS: usize = 4;
+
+
+/// Finds a root move that, according to the quiescence search, is
+/// much better than all the alternatives.
+///
+/// Returns the move and the value of the second-best move, or `None`
+/// if there is no such move.
+fn find_easy_move<N: SearchNode>(position: &N) -> Option<(Move, Value)> {
+    let mut p = position.clone();
+    let mut values: Vec<(Value, Move)> = p.legal_moves()
+        .into_iter()
+        .map(|m| {
+            assert!(p.do_move(m));
+            let value = -p.qsearch(0, VALUE_MIN, VALUE_MAX, VALUE_UNKNOWN).value();
+            p.undo_last_move();
+            (value, m)
+        })
+        .collect();
+    if values.len() < 2 {
+        return None;
+    }
+    values.sort_by_key(|&(v, _)| Reverse(v));
+    let (best_value, best_move) = values[0];
+    let second_best_value = values[1].0;
+    if best_value as isize - second_best_value as isize >= EASY_MOVE_MARGIN {
+        Some((best_move, second_best_value))
+    } else {
+        None
+    }
 }
 
 
@@ -200,6 +322,23 @@ fn linear_regression(points: &[(f64, f64)]) -> (f64, f64) {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use super::StdTimeManager;
+    use depth::*;
+    use value::*;
+    use ttable::*;
+    use search::{SearchParams, DeepeningSearch};
+    use moves::Move;
+    use search_node::SearchNode;
+    use time_manager::{TimeManager, RemainingTime};
+    use stock::{StdTtable, StdTtableEntry, StdSearchNode, StdQsearch, StdMoveGenerator,
+                SimpleEvaluator, SimpleSearch, Deepening};
+
+    type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
+    type T = StdTtable<StdTtableEntry>;
+    type D = Deepening<SimpleSearch<T, P>>;
+
     #[test]
     fn linear_regression() {
         use super::linear_regression;
@@ -209,4 +348,128 @@ mod tests {
         let y = slope * x + intercept;
         assert!(4.99 < y && y < 5.01);
     }
+
+    #[test]
+    fn time_budget() {
+        use super::time_budget;
+
+        // 40 moves in 5 minutes.
+        let (allotted, hard) = time_budget(300_000.0, 0.0, 40.0, false);
+        assert_eq!(allotted, 7500.0);
+        assert!(allotted < hard && hard < 60_000.0);
+
+        // Pondering increases the allotted time.
+        let (allotted_ponder, hard_ponder) = time_budget(300_000.0, 0.0, 40.0, true);
+        assert!(allotted_ponder > allotted);
+        assert_eq!(hard_ponder, hard);
+
+        // Sudden death with increment (2+1).
+        let (allotted, hard) = time_budget(120_000.0, 1000.0, 40.0, false);
+        assert!(3000.0 < allotted && allotted < 5000.0);
+        assert!(allotted < hard && hard < 120_000.0 - 1000.0);
+
+        // The last move before the time control.
+        let (allotted, hard) = time_budget(10_000.0, 0.0, 1.0, false);
+        assert_eq!(allotted, 10_000.0);
+        assert_eq!(hard, 9000.0);
+
+        // Very low time.
+        for &t in [0.0, 50.0, 300.0, 900.0].iter() {
+            let (_, hard) = time_budget(t, 0.0, 1.0, false);
+            assert!(0.0 <= hard && hard <= 0.9 * t);
+            let (_, hard) = time_budget(t, 0.0, 40.0, false);
+            assert!(0.0 <= hard && hard <= t / 4.0);
+        }
+    }
+
+    fn init_configuration() {
+        let mut configuration = ::CONFIGURATION.write().unwrap();
+        configuration.entry("MultiPV").or_insert("1".to_string());
+        configuration.entry("Initial Aspiration Window").or_insert("16".to_string());
+        configuration.entry("Ponder").or_insert("false".to_string());
+        configuration.entry("MinThinkTime").or_insert("0".to_string());
+    }
+
+    /// Runs a search with `tm`, and returns the played move.
+    fn play(p: P, tm: &mut StdTimeManager) -> Option<Move> {
+        let searchmoves = p.legal_moves();
+        let mut search = D::new(Arc::new(T::new(None)));
+        let started_at = Instant::now();
+        search.start_search(SearchParams {
+            search_id: 0,
+            position: p,
+            depth: DEPTH_MAX,
+            lower_bound: VALUE_MIN,
+            upper_bound: VALUE_MAX,
+            searchmoves,
+            report_interval: None,
+            contempt: 0,
+            analyse_mode: false,
+            threads: 1,
+        });
+        let mut best_move = None;
+        loop {
+            assert!(started_at.elapsed() < Duration::from_secs(60));
+            search.wait_report(Duration::from_millis(10));
+            if let Ok(r) = search.try_recv_report() {
+                if !r.data.is_empty() {
+                    best_move = r.best_move();
+                }
+                if tm.must_play(&mut search, Some(&r)) {
+                    search.send_message("TERMINATE");
+                }
+                if r.done {
+                    break;
+                }
+            }
+        }
+        best_move
+    }
+
+    #[test]
+    fn easy_move() {
+        init_configuration();
+
+        // White can win the black queen for a knight.
+        let p = P::from_history("r1b1kbnr/pppp1ppp/2n5/4p3/3qP3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 4",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let time = RemainingTime {
+            white_millis: 600_000,
+            black_millis: 600_000,
+            winc_millis: 0,
+            binc_millis: 0,
+            movestogo: Some(1),
+        };
+        let mut tm: StdTimeManager = TimeManager::<D>::new(&p, &time);
+        let started_at = Instant::now();
+        let best_move = play(p, &mut tm);
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+        assert_eq!(best_move.unwrap().notation(), "f3d4");
+    }
+
+    #[test]
+    fn min_think_time() {
+        init_configuration();
+        let p = P::from_history("r1b1kbnr/pppp1ppp/2n5/4p3/3qP3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 4",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let time = RemainingTime {
+            white_millis: 600_000,
+            black_millis: 600_000,
+            winc_millis: 0,
+            binc_millis: 0,
+            movestogo: Some(1),
+        };
+
+        // The minimum thinking time takes precedence over the easy move.
+        let mut tm: StdTimeManager = TimeManager::<D>::new(&p, &time);
+        tm.min_think_time = 1500.0;
+        let started_at = Instant::now();
+        let best_move = play(p, &mut tm);
+        assert!(started_at.elapsed() >= Duration::from_millis(1500));
+        assert_eq!(best_move.unwrap().notation(), "f3d4");
+    }
 }
@@ -8,6 +8,7 @@ use std::isize;
 use std::cell::Cell;
 use std::cmp::max;
 use std::mem;
+use std::ptr;
 use ttable::*;
 use moves::MoveDigest;
 
@@ -178,16 +179,20 @@ impl<T: TtableEntry> Ttable for StdTtable<T> {
                 "too small transposition table entry: {} bytes",
                         mem::size_of::<T>());
 
-        let size_mb = size_mb.unwrap_or(16);
-        let bucket_count = {
-            // Make sure that the number of buckets is a power of 2.
-            let n = max(1, ((size_mb * 1024 * 1024) / BUCKET_SIZE) as u64);
-            1 << (63 - n.leading_zeros())
-        };
+        let mut bucket_count = bucket_count(size_mb.unwrap_or(16));
         let alloc_ptr;
         let table_ptr = unsafe {
+            // If there is not enough memory, try smaller tables.
+            alloc_ptr = loop {
+                let p = libc::calloc(bucket_count + 1, BUCKET_SIZE);
+                if !p.is_null() || bucket_count == 1 {
+                    break p;
+                }
+                bucket_count >>= 1;
+            };
+            assert!(!alloc_ptr.is_null(), "can not allocate the transposition table");
+
             // Make sure that the first bucket is optimally aligned.
-            alloc_ptr = libc::calloc(bucket_count + 1, BUCKET_SIZE);
             let mut addr = mem::transmute::<*mut c_void, usize>(alloc_ptr);
             addr += BUCKET_SIZE;
             addr &= !(BUCKET_SIZE - 1);
@@ -203,6 +208,42 @@ impl<T: TtableEntry> Ttable for StdTtable<T> {
         }
     }
 
+    /// Frees the old table before allocating the new one, so that
+    /// the two do not have to fit in memory at the same time.
+    fn resize(&mut self, size_mb: Option<usize>) {
+        unsafe {
+            libc::free(self.alloc_ptr);
+        }
+        self.alloc_ptr = ptr::null_mut();
+        *self = StdTtable::new(size_mb);
+    }
+
+    fn size_mb(&self) -> Option<usize> {
+        Some(self.bucket_count * BUCKET_SIZE / (1024 * 1024))
+    }
+
+    fn rounded_size_mb(size_mb: usize) -> usize {
+        bucket_count(size_mb) * BUCKET_SIZE / (1024 * 1024)
+    }
+
+    /// Estimates the usage from the records in the first buckets of
+    /// the table. (Scanning the whole table would be too slow.)
+    fn usage(&self) -> Option<u16> {
+        const N: usize = 1000;
+        let slots = Bucket::<Record<T>>::len();
+        let mut sampled = 0;
+        let mut used = 0;
+        for bucket in self.buckets().take(N.div_ceil(slots)) {
+            for slot in 0..slots {
+                if bucket.get_generation(slot) == self.generation.get() {
+                    used += 1;
+                }
+            }
+            sampled += slots;
+        }
+        Some((used * 1000 / sampled) as u16)
+    }
+
     fn new_search(&self) {
         const N: usize = 128;
 
@@ -278,6 +319,10 @@ impl<T: TtableEntry> Ttable for StdTtable<T> {
 
             // Calculate the score for the record in this slot. The
             // replaced record will be the one with the lowest score.
+            //
+            // **Note:** At equal generation and depth, records with
+            // exact bounds have higher importance than bound-limited
+            // records, and therefore will be retained.
             let mut score = record.data.importance() as isize;
             if generation == self.generation.get() {
                 // Positions from the current generation are always
@@ -316,6 +361,25 @@ impl<T: TtableEntry> Ttable for StdTtable<T> {
         None
     }
 
+    /// Issues a prefetch instruction for key's bucket on x86-64. Does
+    /// nothing on other platforms.
+    #[inline]
+    #[allow(unused_variables)]
+    fn prefetch(&self, key: u64) {
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            _mm_prefetch::<_MM_HINT_T0>(self.bucket_ptr(key) as *const i8);
+        }
+    }
+
+    /// Advances the generation number, so that all existing records
+    /// are considered to be from previous searches. No memory is
+    /// touched except for a small sample of the buckets.
+    fn soft_clear(&self) {
+        self.new_search();
+    }
+
     fn clear(&self) {
         for bucket in self.buckets() {
             for slot in 0..Bucket::<Record<T>>::len() {
@@ -330,10 +394,14 @@ impl<T: TtableEntry> StdTtable<T> {
     /// Returns the bucket for a given key.
     #[inline]
     fn bucket(&self, key: u64) -> Bucket<Record<T>> {
-        unsafe {
-            let byte_offset = (key as usize & (self.bucket_count - 1)) * BUCKET_SIZE;
-            Bucket::new(self.table_ptr.offset(byte_offset as isize))
-        }
+        unsafe { Bucket::new(self.bucket_ptr(key)) }
+    }
+
+    /// Returns a raw pointer to the bucket for a given key.
+    #[inline]
+    fn bucket_ptr(&self, key: u64) -> *mut c_void {
+        let byte_offset = (key as usize & (self.bucket_count - 1)) * BUCKET_SIZE;
+        unsafe { self.table_ptr.offset(byte_offset as isize) }
     }
 
     /// Returns an iterator over the buckets in the table.
@@ -388,6 +456,16 @@ impl<T: TtableEntry> Iterator for Iter<T> {
 }
 
 
+/// A helper function for `StdTtable`. It returns the number of
+/// buckets for a table of `size_mb` Mbytes. The number of buckets is
+/// always a power of 2, so the size is rounded down.
+#[inline]
+fn bucket_count(size_mb: usize) -> usize {
+    let n = max(1, (size_mb.saturating_mul(1024 * 1024) / BUCKET_SIZE) as u64);
+    1 << (63 - n.leading_zeros())
+}
+
+
 /// A helper function for `StdTtable`. It takes the highest 32 bits of
 /// an `u64` value and splits them into two `u16` values.
 #[inline]
@@ -504,6 +582,172 @@ mod tests {
         assert!(tt.probe(1).is_some());
     }
 
+    #[test]
+    fn prefer_exact_bounds() {
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+
+        // The same key.
+        tt.store(1, StdTtableEntry::new(10, BOUND_EXACT, 20));
+        tt.store(1, StdTtableEntry::new(30, BOUND_LOWER, 20));
+        assert_eq!(tt.probe(1).unwrap().bound(), BOUND_EXACT);
+        assert_eq!(tt.probe(1).unwrap().value(), 10);
+        tt.store(2, StdTtableEntry::new(30, BOUND_LOWER, 20));
+        tt.store(2, StdTtableEntry::new(10, BOUND_EXACT, 20));
+        assert_eq!(tt.probe(2).unwrap().bound(), BOUND_EXACT);
+        assert_eq!(tt.probe(2).unwrap().value(), 10);
+
+        // Different keys in the same bucket.
+        tt.clear();
+        let n = Bucket::<Record<StdTtableEntry>>::len() as u64;
+        for i in 1..(n + 1) {
+            let bound = if i == 2 { BOUND_LOWER } else { BOUND_EXACT };
+            tt.store(i << 32, StdTtableEntry::new(0, bound, 20));
+        }
+        tt.store((n + 1) << 32, StdTtableEntry::new(0, BOUND_EXACT, 20));
+        assert!(tt.probe(2 << 32).is_none());
+        for i in 1..(n + 2) {
+            if i != 2 {
+                assert_eq!(tt.probe(i << 32).unwrap().bound(), BOUND_EXACT);
+            }
+        }
+    }
+
+    #[test]
+    fn extract_pv_skips_null_moves() {
+        use search_node::SearchNode;
+        use stock::{StdSearchNode, StdQsearch, StdMoveGenerator, SimpleEvaluator};
+        type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
+
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let p = P::from_history("4k3/8/8/8/8/8/8/4K3 w - - 0 1", &mut vec![].into_iter())
+            .ok()
+            .unwrap();
+        let null_move = p.null_move();
+        assert!(null_move.is_null());
+        tt.store(p.hash(),
+                 StdTtableEntry::new(0, BOUND_EXACT, 10).set_move_digest(null_move.digest()));
+        let pv = tt.extract_pv(&p);
+        assert!(pv.moves.is_empty());
+    }
+
+    #[test]
+    fn extract_pv_truncates_illegal_moves() {
+        use moves::MoveDigest;
+        use search_node::SearchNode;
+        use stock::{StdSearchNode, StdQsearch, StdMoveGenerator, SimpleEvaluator};
+        type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
+
+        let p = P::from_history("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                                &mut vec![].into_iter())
+                .ok()
+                .unwrap();
+        let e2e4 = MoveDigest::from_lan("e2e4").unwrap();
+        let mut q = p.clone();
+        assert!(q.do_move(q.try_move_digest(e2e4).unwrap()));
+
+        // Simulate key collisions, by storing moves that are illegal
+        // in the position after "e2e4".
+        for lan in &["e2e4", "d8h4", "e8g8"] {
+            let tt = StdTtable::<StdTtableEntry>::new(None);
+            tt.store(p.hash(),
+                     StdTtableEntry::new(0, BOUND_EXACT, 10).set_move_digest(e2e4));
+            tt.store(q.hash(),
+                     StdTtableEntry::new(0, BOUND_EXACT, 9)
+                         .set_move_digest(MoveDigest::from_lan(lan).unwrap()));
+            let pv = tt.extract_pv(&p);
+            assert_eq!(pv.moves.len(), 1);
+            assert_eq!(pv.moves[0].notation(), "e2e4");
+        }
+    }
+
+    #[test]
+    fn absurd_size() {
+        let tt = StdTtable::<StdTtableEntry>::new(Some(usize::MAX));
+        let size_mb = tt.size_mb().unwrap();
+        assert!(size_mb > 0 && size_mb < usize::MAX / (1024 * 1024));
+        tt.store(1, StdTtableEntry::new(0, BOUND_EXACT, 10));
+        assert_eq!(tt.probe(1).unwrap().depth(), 10);
+        assert_eq!(StdTtable::<StdTtableEntry>::new(Some(16)).size_mb(), Some(16));
+    }
+
+    #[test]
+    fn rounded_size() {
+        type T = StdTtable<StdTtableEntry>;
+        assert_eq!(T::rounded_size_mb(16), 16);
+        assert_eq!(T::rounded_size_mb(100), 64);
+        assert_eq!(T::new(Some(100)).size_mb(), Some(T::rounded_size_mb(100)));
+    }
+
+    #[test]
+    fn resize() {
+        let mut tt = StdTtable::<StdTtableEntry>::new(Some(1));
+        let bucket_count = tt.bucket_count;
+        tt.store(1, StdTtableEntry::new(0, BOUND_EXACT, 10));
+        tt.resize(Some(4));
+        assert_eq!(tt.bucket_count, 4 * bucket_count);
+        assert_eq!(tt.size_mb(), Some(4));
+        assert!(tt.probe(1).is_none());
+        tt.store(1, StdTtableEntry::new(0, BOUND_EXACT, 10));
+        assert_eq!(tt.probe(1).unwrap().depth(), 10);
+    }
+
+    #[test]
+    fn soft_clear() {
+        let tt = StdTtable::<StdTtableEntry>::new(None);
+        let n = Bucket::<Record<StdTtableEntry>>::len() as u64;
+
+        // All these keys go to the same bucket.
+        for i in 1..(n + 1) {
+            tt.store(i << 32, StdTtableEntry::new(0, BOUND_EXACT, 50));
+        }
+        tt.soft_clear();
+        for i in 1..(n + 1) {
+            assert!(tt.probe(i << 32).is_some());
+        }
+        tt.soft_clear();
+        for i in (n + 1)..(2 * n + 1) {
+            tt.store(i << 32, StdTtableEntry::new(0, BOUND_EXACT, 1));
+        }
+        for i in 1..(n + 1) {
+            assert!(tt.probe(i << 32).is_none());
+        }
+        for i in (n + 1)..(2 * n + 1) {
+            assert_eq!(tt.probe(i << 32).unwrap().depth(), 1);
+        }
+    }
+
+    #[test]
+    fn prefetch() {
+        let tt = StdTtable::<StdTtableEntry>::new(Some(1));
+        for i in 0..1000u64 {
+            let key = i.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            tt.prefetch(key);
+            tt.store(key, StdTtableEntry::new(0, BOUND_EXACT, 1));
+        }
+        for i in 0..1000u64 {
+            let key = i.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            tt.prefetch(key);
+            assert!(tt.probe(key).is_some());
+        }
+    }
+
+    #[test]
+    fn usage() {
+        let tt = StdTtable::<StdTtableEntry>::new(Some(1));
+        assert_eq!(tt.usage(), Some(0));
+        for i in 0..(1 << 20) {
+            let key = (i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            tt.store(key, StdTtableEntry::new(0, BOUND_EXACT, 1));
+        }
+        assert!(tt.usage().unwrap() > 950);
+
+        // Only the current search counts.
+        tt.new_search();
+        assert_eq!(tt.usage(), Some(0));
+        tt.clear();
+        assert_eq!(tt.usage(), Some(0));
+    }
+
     #[test]
     fn new_search() {
         let tt = StdTtable::<StdTtableEntry>::new(None);
@@ -0,0 +1,258 @@
+//! Implements a tapered (middlegame/endgame) evaluator.
+
+use std::cell::RefCell;
+use uci::SetOption;
+use moves::*;
+use board::*;
+use value::*;
+use evaluator::Evaluator;
+use bitsets::*;
+use utils::ZobristArrays;
+use stock::{TEMPO, PawnHashTable};
+
+
+/// An evaluator that interpolates between a middlegame and an endgame
+/// score.
+///
+/// The evaluator keeps two separate scores: one that is appropriate
+/// for the middlegame, and one that is appropriate for the endgame.
+/// Both scores are updated incrementally as moves are played. The
+/// game phase is calculated from the remaining non-pawn material (see
+/// `phase`), and the final evaluation is a blend of the two scores,
+/// weighted by the game phase.
+///
+/// Currently, the middlegame and endgame scores differ in the values
+/// of the pieces, and in the bonus for having a centralized king,
+/// which is given in the endgame only. The pawn structure is
+/// evaluated separately, and its value is added to the blend. The
+/// pawn-structure evaluations are cached in a per-thread
+/// `PawnHashTable`.
+#[derive(Clone, Copy)]
+pub struct TaperedEvaluator {
+    /// The middlegame score, from white's point of view.
+    mg: Value,
+
+    /// The endgame score, from white's point of view.
+    eg: Value,
+
+    /// The sum of the phase weights of all non-pawn pieces on the
+    /// board (see `PHASE_WEIGHTS`).
+    phase: i16,
+
+    /// The Zobrist hash of the pawn structure (see
+    /// `Board::pawn_hash`).
+    pawn_hash: u64,
+}
+
+impl TaperedEvaluator {
+    /// Returns the game phase.
+    ///
+    /// The returned value is between `0` (endgame -- only kings and
+    /// pawns are left on the board) and `PHASE_MAX` (middlegame --
+    /// all pieces are still on the board).
+    #[inline]
+    pub fn phase(&self) -> i16 {
+        if self.phase > PHASE_MAX {
+            PHASE_MAX
+        } else {
+            self.phase
+        }
+    }
+
+    /// A helper method. It adds (or subtracts, if `sign` is `-1`)
+    /// `piece`'s contribution to the scores and the game phase.
+    #[inline]
+    fn add_piece(&mut self, color: Color, piece: PieceType, sign: i16) {
+        self.phase += sign * PHASE_WEIGHTS[piece];
+        let sign = if color == WHITE { sign } else { -sign };
+        self.mg += sign * PIECE_VALUES_MG[piece];
+        self.eg += sign * PIECE_VALUES_EG[piece];
+    }
+
+    /// A helper method. It updates the scores and the game phase in
+    /// accordance with the move `m` played by `us` (`sign` is `1`),
+    /// or with the same move taken back (`sign` is `-1`).
+    #[inline]
+    fn apply_move(&mut self, us: Color, m: Move, sign: i16) {
+        if m.is_null() {
+            return;
+        }
+        self.pawn_hash ^= ZobristArrays::get().pawn_hash_delta(us, m);
+        let them = opposite(us);
+        let captured_piece = m.captured_piece();
+        if captured_piece < PIECE_NONE {
+            self.add_piece(them, captured_piece, -sign);
+        }
+        if m.move_type() == MOVE_PROMOTION {
+            self.add_piece(us, PAWN, -sign);
+            self.add_piece(us, Move::piece_from_aux_data(m.aux_data()), sign);
+        }
+        if m.played_piece() == KING {
+            let bonus = KING_CENTRALIZATION_EG[m.dest_square()] -
+                        KING_CENTRALIZATION_EG[m.orig_square()];
+            self.eg += if us == WHITE { sign * bonus } else { -sign * bonus };
+        }
+    }
+}
+
+impl SetOption for TaperedEvaluator {}
+
+impl Evaluator for TaperedEvaluator {
+    fn new(position: &Board) -> TaperedEvaluator {
+        let mut e = TaperedEvaluator {
+            mg: 0,
+            eg: 0,
+            phase: 0,
+            pawn_hash: position.pawn_hash(),
+        };
+        let color = position.pieces.color;
+        for piece in QUEEN..PIECE_NONE {
+            let occupied = position.pieces.piece_type[piece];
+            for &c in [WHITE, BLACK].iter() {
+                for _ in 0..pop_count(occupied & color[c]) {
+                    e.add_piece(c, piece, 1);
+                }
+            }
+        }
+        let kings = position.pieces.piece_type[KING];
+        e.eg += KING_CENTRALIZATION_EG[bsf(kings & color[WHITE])] -
+                KING_CENTRALIZATION_EG[bsf(kings & color[BLACK])];
+        e
+    }
+
+    #[inline]
+    fn done_move(&mut self, position: &Board, m: Move) {
+        self.apply_move(opposite(position.to_move), m, 1);
+    }
+
+    #[inline]
+    fn undone_move(&mut self, position: &Board, m: Move) {
+        self.apply_move(position.to_move, m, -1);
+    }
+
+    #[inline]
+    fn evaluate(&self, position: &Board) -> Value {
+        thread_local!(
+            static PAWN_HASH_TABLE: RefCell<PawnHashTable> =
+                RefCell::new(PawnHashTable::new(PAWN_HASH_TABLE_SIZE_LOG2))
+        );
+
+        let phase = self.phase() as i32;
+        let pawns = PAWN_HASH_TABLE.with(|t| t.borrow_mut().evaluate(self.pawn_hash, position));
+        let blended = (self.mg as i32 * phase + self.eg as i32 * (PHASE_MAX as i32 - phase)) /
+                      PHASE_MAX as i32 + pawns.value as i32;
+        let value = if position.to_move == WHITE {
+            blended
+        } else {
+            -blended
+        };
+        value as Value + TEMPO
+    }
+
+    #[inline]
+    fn is_zugzwangy(&self, _: &Board) -> bool {
+        // Positions with only kings and pawns are prone to zugzwang.
+        self.phase == 0
+    }
+}
+
+
+/// The game phase at the start of the game.
+pub const PHASE_MAX: i16 = 24;
+
+
+/// The number of slots in the per-thread pawn hash table is
+/// `2.pow(PAWN_HASH_TABLE_SIZE_LOG2)`.
+const PAWN_HASH_TABLE_SIZE_LOG2: usize = 12;
+
+
+/// The contribution of each piece type to the game phase.
+const PHASE_WEIGHTS: [i16; 8] = [0, 4, 2, 1, 1, 0, 0, 0];
+
+
+/// The values of the pieces in the middlegame.
+const PIECE_VALUES_MG: [Value; 8] = [0, 975, 475, 325, 325, 85, 0, 0];
+
+
+/// The values of the pieces in the endgame.
+const PIECE_VALUES_EG: [Value; 8] = [0, 975, 525, 335, 310, 110, 0, 0];
+
+
+/// The bonus for king's distance from the edge of the board, given in
+/// the endgame only.
+const KING_CENTRALIZATION_EG: [Value; 64] = [
+     0, 10, 10, 10, 10, 10, 10,  0,
+    10, 20, 20, 20, 20, 20, 20, 10,
+    10, 20, 30, 30, 30, 30, 20, 10,
+    10, 20, 30, 40, 40, 30, 20, 10,
+    10, 20, 30, 40, 40, 30, 20, 10,
+    10, 20, 30, 30, 30, 30, 20, 10,
+    10, 20, 20, 20, 20, 20, 20, 10,
+     0, 10, 10, 10, 10, 10, 10,  0,
+];
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use move_generator::MoveGenerator;
+    use stock::StdMoveGenerator;
+
+    type P = StdMoveGenerator<TaperedEvaluator>;
+
+    #[test]
+    fn phase() {
+        let b = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .ok()
+            .unwrap();
+        let e = TaperedEvaluator::new(&b);
+        assert_eq!(e.phase(), PHASE_MAX);
+        assert!(!e.is_zugzwangy(&b));
+
+        let b = Board::from_fen("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1").ok().unwrap();
+        let e = TaperedEvaluator::new(&b);
+        assert_eq!(e.phase(), 0);
+        assert!(e.is_zugzwangy(&b));
+
+        // Only the endgame score counts in the endgame. (The lone
+        // pawn is isolated, which costs 15 centipawns.)
+        assert_eq!(e.evaluate(&b), e.eg - 15 + TEMPO);
+    }
+
+    #[test]
+    fn incremental_update() {
+        let b = Board::from_fen("r3k2r/1P3ppp/8/8/2n5/8/5PPP/R2QK2R w KQkq - 0 1").ok().unwrap();
+        let mut p = P::from_board(b).ok().unwrap();
+        let mut moves = vec![];
+        for s in ["b7a8q", "e8e7", "a8h8", "c4d2", "e1d2", "e7d6"].iter() {
+            let m = p.try_move_digest(MoveDigest::from_lan(s).unwrap()).unwrap();
+            assert!(p.do_move(m).is_some());
+            moves.push(m);
+            let fresh = TaperedEvaluator::new(p.board());
+            let e = p.evaluator();
+            assert_eq!((e.mg, e.eg, e.phase), (fresh.mg, fresh.eg, fresh.phase));
+            assert_eq!(e.pawn_hash, fresh.pawn_hash);
+            assert_eq!(e.evaluate(p.board()), fresh.evaluate(p.board()));
+        }
+        for &m in moves.iter().rev() {
+            p.undo_move(m);
+            let fresh = TaperedEvaluator::new(p.board());
+            let e = p.evaluator();
+            assert_eq!((e.mg, e.eg, e.phase), (fresh.mg, fresh.eg, fresh.phase));
+            assert_eq!(e.pawn_hash, fresh.pawn_hash);
+        }
+    }
+
+    #[test]
+    fn pawn_structure() {
+        let eval = |fen| {
+            let b = Board::from_fen(fen).ok().unwrap();
+            TaperedEvaluator::new(&b).evaluate(&b)
+        };
+
+        // Doubled and isolated pawns are penalized.
+        assert_eq!(eval("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1") -
+                   eval("4k3/8/8/8/8/3P4/3P4/4K3 w - - 0 1"),
+                   30);
+    }
+}
@@ -55,6 +55,47 @@ pub trait Ttable: Sync + Send + 'static {
     /// `size_mb` is the desired size in Mbytes.
     fn new(size_mb: Option<usize>) -> Self;
 
+    /// Re-sizes the table.
+    ///
+    /// `size_mb` is the desired new size in Mbytes. The entries are
+    /// not preserved. The default implementation replaces the table
+    /// with a new one.
+    fn resize(&mut self, size_mb: Option<usize>)
+        where Self: Sized
+    {
+        *self = Self::new(size_mb);
+    }
+
+    /// Returns the actual size of the table in Mbytes, if known.
+    ///
+    /// The actual size may be smaller than the requested size, if
+    /// not enough memory could be allocated. The default
+    /// implementation returns `None`.
+    fn size_mb(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the size in Mbytes that a table created for `size_mb`
+    /// Mbytes will have, provided that there is enough memory.
+    ///
+    /// Implementations may round the requested size (to a power of
+    /// two, for example). The default implementation returns
+    /// `size_mb`.
+    fn rounded_size_mb(size_mb: usize) -> usize
+        where Self: Sized
+    {
+        size_mb
+    }
+
+    /// Returns how full the table is, in permill, if known.
+    ///
+    /// Only the entries stored during the current search are
+    /// counted. The returned value can be an estimate (from a sample
+    /// of the table). The default implementation returns `None`.
+    fn usage(&self) -> Option<u16> {
+        None
+    }
+
     /// Signals that a new search is about to begin.
     fn new_search(&self);
 
@@ -68,13 +109,37 @@ pub trait Ttable: Sync + Send + 'static {
     /// Probes for data by key.
     fn probe(&self, key: u64) -> Option<Self::Entry>;
 
+    /// Hints that data for a given key will be probed for soon.
+    ///
+    /// Implementations can start loading the relevant memory into
+    /// the CPU cache, so that the subsequent `probe` will not have to
+    /// wait for it. The default implementation does nothing.
+    #[inline]
+    #[allow(unused_variables)]
+    fn prefetch(&self, key: u64) {}
+
     /// Removes all entries in the table.
     fn clear(&self);
 
+    /// Marks all entries in the table as stale.
+    ///
+    /// Stale entries will be replaced before any entries stored
+    /// after the call to `soft_clear`. Unlike `clear`, this method
+    /// may leave the table's memory untouched, and therefore can be
+    /// much faster for big tables. The default implementation calls
+    /// `clear`.
+    fn soft_clear(&self) {
+        self.clear();
+    }
+
     /// Extracts the principal variation for a given position.
     ///
     /// The principal variation (PV) is the sequence of moves that the
     /// engine considers best and therefore expects to be played.
+    /// Each hash move is verified to be legal before it is played,
+    /// and the extraction stops at the first illegal one. Therefore
+    /// the returned moves are always a legal sequence, even when a
+    /// hash key collision has occurred.
     fn extract_pv<T: SearchNode>(&self, position: &T) -> Variation {
         let mut p = position.clone();
         let mut our_turn = true;
@@ -122,9 +187,11 @@ pub trait Ttable: Sync + Send + 'static {
                        }
                        v => v == value,
                    } {
-                    // Verify that the hash move is legal.
+                    // Verify that the hash move is legal. (Null moves
+                    // are internal to the search and never belong to
+                    // the PV.)
                     if let Some(m) = p.try_move_digest(e.move_digest()) {
-                        if p.do_move(m) {
+                        if !m.is_null() && p.do_move(m) {
                             moves.push(m);
 
                             // Note: we continue expanding the PV only on best moves.
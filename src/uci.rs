@@ -15,12 +15,13 @@
 //! `UciEngine` trait. Then `run_engine` will handle the communication
 //! with the GUI all by itself.
 
+use std::cmp;
 use std::default::Default;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::thread::{spawn, sleep};
 use std::io;
-use std::io::{Write, BufWriter, BufRead, ErrorKind};
-use std::sync::mpsc::{channel, TryRecvError};
+use std::io::{Read, Write, BufWriter, BufReader, BufRead, ErrorKind};
+use std::sync::mpsc::{sync_channel, TryRecvError};
 use regex::Regex;
 
 
@@ -115,8 +116,17 @@ pub struct GoParams {
     /// Search until the "stop" command.
     ///
     /// The engine must not exit the search without being told so in
-    /// this mode.
+    /// this mode. When this flag is set, all other search limits
+    /// (time, depth, nodes, mate) must be ignored.
     pub infinite: bool,
+
+    /// Instead of searching, count the leaf nodes of the move tree
+    /// to that depth (plies).
+    ///
+    /// This is not a part of the UCI specification, but is supported
+    /// by many engines. It is useful for validating the move
+    /// generator.
+    pub perft: Option<u64>,
 }
 
 
@@ -221,9 +231,11 @@ pub trait UciEngine {
 
     /// Creates a new instance.
     ///
-    /// `tt_size_mb` is the preferred size of the transposition table
-    /// in Mbytes.
-    fn new(tt_size_mb: Option<usize>) -> Self;
+    /// `options` are the configuration options (name and value) that
+    /// the GUI has set before the engine was created, in the order in
+    /// which they were set. For example, the "Hash" option, if given,
+    /// is the preferred size of the transposition table in Mbytes.
+    fn new(options: &[(&str, &str)]) -> Self;
 
     /// Sets a new value for a given configuration option.
     fn set_option(&mut self, name: &str, value: &str);
@@ -246,6 +258,19 @@ pub trait UciEngine {
     /// Tells the engine to start thinking.
     fn go(&mut self, params: &GoParams);
 
+    /// Performs move path enumeration on the current position.
+    ///
+    /// Returns the number of leaf nodes at `depth` for each legal
+    /// move (in long algebraic notation). When `depth` is zero, an
+    /// empty vector is returned. This method blocks the current
+    /// thread until the enumeration is finished.
+    ///
+    /// The default implementation returns an empty vector.
+    #[allow(unused_variables)]
+    fn perft(&mut self, depth: u8) -> Vec<(String, u64)> {
+        vec![]
+    }
+
     /// Forces the engine to stop thinking and reply with the best
     /// move it had found.
     fn stop(&mut self);
@@ -285,8 +310,10 @@ pub fn run_engine<E: UciEngine>() -> io::Result<()> {
 /// A UCI protocol server.
 ///
 /// Connects the engine to the GUI.
-struct Server<E: UciEngine> {
-    engine: Option<E>,
+pub struct Server<E: UciEngine> {
+    /// The engine is created when the first command that needs it
+    /// is received.
+    pub engine: Option<E>,
 }
 
 
@@ -313,31 +340,7 @@ impl<E: UciEngine> Server<E> {
         write!(writer, "id name {}\n", E::name())?;
         write!(writer, "id author {}\n", E::author())?;
         for (name, description) in E::options() {
-            write!(writer,
-                    "option name {} type {}\n",
-                    name,
-                    match description {
-                        OptionDescription::Check { default } => {
-                            format!("check default {}", default)
-                        }
-                        OptionDescription::Spin { default, min, max } => {
-                            format!("spin default {} min {} max {}", default, min, max)
-                        }
-                        OptionDescription::Combo { default, list } => {
-                            format!("combo default {}{}",
-                                    default,
-                                    list.into_iter()
-                                        .fold(String::new(), |mut acc, x| {
-                acc.push_str(" var ");
-                acc.push_str(x.as_str());
-                acc
-            }))
-                        }
-                        OptionDescription::String { default } => {
-                            format!("string default {}", default)
-                        }
-                        OptionDescription::Button => "button".to_string(),
-                    })?;
+            write!(writer, "{}\n", option_line(name, description))?;
         }
         write!(writer, "uciok\n")?;
         writer.flush()?;
@@ -349,13 +352,26 @@ impl<E: UciEngine> Server<E> {
     ///
     /// Will return `Err` if an IO error has occurred.
     pub fn serve(&mut self) -> io::Result<()> {
-        let mut writer = BufWriter::new(io::stdout());
-        let (tx, rx) = channel();
+        self.serve_io(io::stdin(), io::stdout())
+    }
+
+    /// Serves UCI commands read from `input`, writes the replies to
+    /// `output`, until a "quit" command is received.
+    ///
+    /// Will return `Err` if an IO error has occurred.
+    pub fn serve_io<R, W>(&mut self, input: R, output: W) -> io::Result<()>
+        where R: Read + Send + 'static,
+              W: Write
+    {
+        let mut writer = BufWriter::new(output);
+        let (tx, rx) = sync_channel(COMMAND_QUEUE_CAPACITY);
 
-        // Spawn a thread that reads from `stdin` and writes to `tx`.
+        // Spawn a thread that reads from `input` and writes to `tx`.
+        // When the queue is full, the thread blocks until there is
+        // room for more commands, so a GUI that floods us with
+        // commands can not make us run out of memory.
         let read_thread = spawn(move || -> io::Result<()> {
-            let stdin = io::stdin();
-            let mut reader = stdin.lock();
+            let mut reader = BufReader::new(input);
             let mut line = String::new();
             loop {
                 if let Ok(cmd) = match reader.read_line(&mut line)? {
@@ -365,12 +381,16 @@ impl<E: UciEngine> Server<E> {
                     if let UciCommand::Quit = cmd {
                         return Ok(());
                     }
-                    tx.send(cmd).unwrap();
+                    if tx.send(cmd).is_err() {
+                        // The receiving end has gone.
+                        return Ok(());
+                    }
                 }
                 line.clear();
             }
         });
 
+        let mut early_options = vec![];
         'mainloop: loop {
             // Try to receive commands from the GUI, pass them to the engine.
             'read_commands: while let Some(cmd) = match rx.try_recv() {
@@ -381,21 +401,19 @@ impl<E: UciEngine> Server<E> {
                 let engine = if let Some(ref mut e) = self.engine {
                     e
                 } else {
-                    // Initialize the engine. (The UCI specification
-                    // states that the "Hash" "setoption" command
-                    // should be the first command passed to the
-                    // engine.)
-                    if let UciCommand::SetOption {
-                               ref name,
-                               ref value,
-                           } = cmd {
-                        if name == "Hash" {
-                            let hash_size_mb = value.parse::<usize>().ok();
-                            self.engine = Some(E::new(hash_size_mb));
-                            continue 'read_commands;
-                        }
+                    // Initialize the engine. The GUI may send several
+                    // "setoption" commands before anything else, and
+                    // some options (the "Hash" option, for example)
+                    // are best known at construction time. So, they
+                    // are collected and passed to `E::new`.
+                    if let UciCommand::SetOption { name, value } = cmd {
+                        early_options.push((name, value));
+                        continue 'read_commands;
                     }
-                    self.engine = Some(E::new(None));
+                    let options: Vec<(&str, &str)> = early_options.iter()
+                        .map(|(name, value)| (name.as_str(), value.as_str()))
+                        .collect();
+                    self.engine = Some(E::new(&options));
                     self.engine.as_mut().unwrap()
                 };
 
@@ -424,6 +442,23 @@ impl<E: UciEngine> Server<E> {
                     UciCommand::PonderHit => {
                         engine.ponder_hit();
                     }
+                    UciCommand::Go(GoParams { perft: Some(depth), .. }) => {
+                        let depth = cmp::min(depth, 63) as u8;
+                        let divide = engine.perft(depth);
+                        for &(ref m, nodes) in divide.iter() {
+                            writeln!(writer, "{}: {}", m, nodes)?;
+                        }
+
+                        // At depth zero, the only leaf node is the
+                        // root position itself.
+                        let total = if depth == 0 {
+                            1
+                        } else {
+                            divide.iter().fold(0, |acc, &(_, nodes)| acc + nodes)
+                        };
+                        writeln!(writer, "\nNodes searched: {}", total)?;
+                        writer.flush()?;
+                    }
                     UciCommand::Go(params) => {
                         engine.go(&params);
                     }
@@ -433,6 +468,7 @@ impl<E: UciEngine> Server<E> {
 
             if let Some(ref mut engine) = self.engine {
                 // Wait for engine replies, fetch them to `stdout`.
+                let started_at = Instant::now();
                 let mut reply_count = 0;
                 while let Some(reply) = engine.wait_for_reply(Duration::from_millis(25)) {
                     reply_count += 1;
@@ -459,10 +495,12 @@ impl<E: UciEngine> Server<E> {
                             }
                         }
                     }
-                    if reply_count >= 40 {
+                    if reply_count >= 40 || started_at.elapsed() >= Duration::from_millis(50) {
                         // The engine is sending lots of replies, but
                         // we should not forget to process GUI
-                        // commands as well.
+                        // commands as well. (For example, "isready"
+                        // must be answered promptly, even while the
+                        // engine is thinking.)
                         break;
                     }
                 }
@@ -477,15 +515,53 @@ impl<E: UciEngine> Server<E> {
         if let Some(ref mut engine) = self.engine {
             engine.exit();
         }
-        read_thread.join().unwrap()
+        read_thread
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("the reading thread panicked")))
     }
 }
 
 
+/// The maximum number of received commands waiting to be processed.
+const COMMAND_QUEUE_CAPACITY: usize = 1024;
+
+
 /// Represents a parse error.
 struct ParseError;
 
 
+/// Formats an "option" line for the UCI handshake.
+///
+/// The default value of spin options is clamped into the allowed
+/// range, because the allowed range may depend on the hardware (the
+/// number of CPUs for example).
+fn option_line(name: &str, description: OptionDescription) -> String {
+    format!("option name {} type {}",
+            name,
+            match description {
+                OptionDescription::Check { default } => format!("check default {}", default),
+                OptionDescription::Spin { default, min, max } => {
+                    format!("spin default {} min {} max {}",
+                            cmp::max(min, cmp::min(default, max)),
+                            min,
+                            max)
+                }
+                OptionDescription::Combo { default, list } => {
+                    format!("combo default {}{}",
+                            default,
+                            list.into_iter()
+                                .fold(String::new(), |mut acc, x| {
+                                    acc.push_str(" var ");
+                                    acc.push_str(x.as_str());
+                                    acc
+                                }))
+                }
+                OptionDescription::String { default } => format!("string default {}", default),
+                OptionDescription::Button => "button".to_string(),
+            })
+}
+
+
 fn parse_uci_command(s: &str) -> Result<UciCommand, ParseError> {
     lazy_static! {
         static ref RE: Regex = Regex::new(
@@ -521,8 +597,19 @@ fn parse_setoption_params(s: &str) -> Result<UciCommand, ParseError> {
             r"^name\s+(\S.*?)(?:\s+value\s+(.*?))?\s*$").unwrap();
     }
     if let Some(captures) = RE.captures(s) {
+        // Option names may consist of several words. The whitespace
+        // between the words is normalized, so that the name can be
+        // compared to the advertised option names. The whitespace in
+        // the value is kept, because string options (file paths for
+        // example) may contain spaces.
         Ok(UciCommand::SetOption {
-               name: captures.get(1).unwrap().as_str().to_string(),
+               name: captures
+                   .get(1)
+                   .unwrap()
+                   .as_str()
+                   .split_whitespace()
+                   .collect::<Vec<_>>()
+                   .join(" "),
                value: captures.get(2).map_or("", |m| m.as_str()).to_string(),
            })
     } else {
@@ -566,7 +653,7 @@ fn parse_go_params(s: &str) -> Result<UciCommand, ParseError> {
             format!(
                 r"\b(?P<keyword>{})(?:\s+(?P<number>\d+)|(?P<moves>{}))?(?:\s+|$)",
                 "wtime|btime|winc|binc|movestogo|depth|\
-                 nodes|mate|movetime|ponder|infinite|searchmoves",
+                 nodes|mate|movetime|ponder|infinite|searchmoves|perft",
                 r"(?:\s+[a-h][1-8][a-h][1-8][qrbn]?)+",  // a non-empty list of moves
             ).as_str()
         ).unwrap();
@@ -602,6 +689,7 @@ fn parse_go_params(s: &str) -> Result<UciCommand, ParseError> {
                         "nodes" => &mut params.nodes,
                         "mate" => &mut params.mate,
                         "movetime" => &mut params.movetime,
+                        "perft" => &mut params.perft,
                         _ => panic!("invalid keyword"),
                     };
                     *field = number.as_str().parse::<u64>().ok();
@@ -630,7 +718,9 @@ mod tests {
                       "wtime 22000 infinite btime 11000",
                       "wtime fdfee / 22000 infinite btime 11000 fdfds",
                       "wtime 22000 infinite btime 11000 ponder",
-                      "searchmoves"];
+                      "searchmoves",
+                      "perft 5",
+                      "perft 0"];
         for (i, s) in params.iter().enumerate() {
             if let Some(UciCommand::Go(p)) = parse_go_params(s).ok() {
                 match i {
@@ -683,6 +773,13 @@ mod tests {
                     12 => {
                         assert!(p.searchmoves.is_empty());
                     }
+                    13 => {
+                        assert_eq!(p.perft, Some(5));
+                        assert_eq!(p.depth, None);
+                    }
+                    14 => {
+                        assert_eq!(p.perft, Some(0));
+                    }
                     _ => (),
                 }
             } else {
@@ -727,6 +824,28 @@ mod tests {
         assert!(parse_setoption_params("namexxx     ").is_err());
     }
 
+    #[test]
+    fn parse_setoption_multi_word() {
+        use super::{parse_setoption_params, parse_uci_command, UciCommand};
+        let parse = |s: &str| match parse_setoption_params(s) {
+            Ok(UciCommand::SetOption { name, value }) => (name, value),
+            _ => panic!("unsuccessful parsing: {}", s),
+        };
+        assert_eq!(parse("name Move Overhead value 40"),
+                   ("Move Overhead".to_string(), "40".to_string()));
+        assert_eq!(parse("name  Move   Overhead   value  40 "),
+                   ("Move Overhead".to_string(), "40".to_string()));
+        assert_eq!(parse("name Clear Hash"), ("Clear Hash".to_string(), "".to_string()));
+        assert_eq!(parse("name NN File value /path with  spaces/nn.bin"),
+                   ("NN File".to_string(), "/path with  spaces/nn.bin".to_string()));
+        assert!(match parse_uci_command("setoption name NN File value /a b/c") {
+                    Ok(UciCommand::SetOption { name, value }) => {
+                        name == "NN File" && value == "/a b/c"
+                    }
+                    _ => false,
+                });
+    }
+
     #[test]
     fn parse_position_params() {
         use super::{parse_position_params, UciCommand};
@@ -789,6 +908,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn option_line() {
+        use std::thread;
+        use super::{option_line, OptionDescription, SetOption};
+        use stock::*;
+        type P = StdSearchNode<StdQsearch<StdMoveGenerator<SimpleEvaluator>>>;
+        type S = Deepening<SimpleSearch<StdTtable<StdTtableEntry>, P>>;
+
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        let threads = S::options().into_iter().find(|o| o.0 == "Threads").unwrap();
+        assert_eq!(option_line(threads.0, threads.1),
+                   format!("option name Threads type spin default 1 min 1 max {}", cpus));
+        assert_eq!(option_line("Foo",
+                               OptionDescription::Spin {
+                                   min: 1,
+                                   max: 4,
+                                   default: 8,
+                               }),
+                   "option name Foo type spin default 4 min 1 max 4");
+        assert_eq!(option_line("Foo",
+                               OptionDescription::Spin {
+                                   min: 1,
+                                   max: 4,
+                                   default: -8,
+                               }),
+                   "option name Foo type spin default 1 min 1 max 4");
+        assert_eq!(option_line("Foo",
+                               OptionDescription::Combo {
+                                   list: vec!["a".to_string(), "b".to_string()],
+                                   default: "a".to_string(),
+                               }),
+                   "option name Foo type combo default a var a var b");
+    }
+
     #[test]
     fn parse_uci_command() {
         use super::{parse_uci_command, UciCommand};
@@ -853,4 +1006,214 @@ mod tests {
                     _ => false,
                 });
     }
+
+    #[test]
+    fn isready_while_thinking() {
+        use std::io::{self, Read, Write};
+        use std::sync::{Arc, Mutex};
+        use std::sync::mpsc::{channel, Receiver};
+        use std::thread::{sleep, spawn};
+        use std::time::{Duration, Instant};
+        use super::*;
+
+        // An engine that sends a reply every 20 milliseconds while
+        // thinking.
+        struct BusyEngine {
+            thinking: bool,
+        }
+
+        impl UciEngine for BusyEngine {
+            fn name() -> &'static str {
+                "busy"
+            }
+            fn author() -> &'static str {
+                "busy"
+            }
+            fn options() -> Vec<(&'static str, OptionDescription)> {
+                vec![]
+            }
+            fn new(_: &[(&str, &str)]) -> Self {
+                BusyEngine { thinking: false }
+            }
+            fn set_option(&mut self, _: &str, _: &str) {}
+            fn new_game(&mut self) {}
+            fn position(&mut self, _: &str, _: &mut dyn Iterator<Item = &str>) {}
+            fn go(&mut self, _: &GoParams) {
+                self.thinking = true;
+            }
+            fn stop(&mut self) {
+                self.thinking = false;
+            }
+            fn ponder_hit(&mut self) {}
+            fn wait_for_reply(&mut self, duration: Duration) -> Option<EngineReply> {
+                if self.thinking {
+                    sleep(Duration::from_millis(20));
+                    Some(EngineReply::Info(vec![InfoItem {
+                                                    info_type: "nodes".to_string(),
+                                                    data: "1".to_string(),
+                                                }]))
+                } else {
+                    sleep(duration);
+                    None
+                }
+            }
+            fn exit(&mut self) {}
+        }
+
+        // Feeds the lines sent through a channel to the server.
+        struct ChannelReader {
+            rx: Receiver<String>,
+            buffer: Vec<u8>,
+        }
+
+        impl Read for ChannelReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.buffer.is_empty() {
+                    match self.rx.recv() {
+                        Ok(line) => self.buffer = line.into_bytes(),
+                        Err(_) => return Ok(0),
+                    }
+                }
+                let n = buf.len().min(self.buffer.len());
+                buf[..n].copy_from_slice(&self.buffer[..n]);
+                self.buffer.drain(..n);
+                Ok(n)
+            }
+        }
+
+        // Collects the server's output.
+        #[derive(Clone)]
+        struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for SharedWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (tx, rx) = channel();
+        let output = SharedWriter(Arc::new(Mutex::new(vec![])));
+        let server_thread = {
+            let input = ChannelReader {
+                rx,
+                buffer: vec![],
+            };
+            let output = output.clone();
+            spawn(move || {
+                      let mut server = Server::<BusyEngine> { engine: None };
+                      server.serve_io(input, output)
+                  })
+        };
+        let output_contains = |s: &str| {
+            String::from_utf8_lossy(&output.0.lock().unwrap()).contains(s)
+        };
+
+        tx.send("go infinite\n".to_string()).unwrap();
+        sleep(Duration::from_millis(200));
+        assert!(output_contains("info nodes 1"));
+        assert!(!output_contains("readyok"));
+        let started_at = Instant::now();
+        tx.send("isready\n".to_string()).unwrap();
+        while !output_contains("readyok") {
+            assert!(started_at.elapsed() < Duration::from_millis(500));
+            sleep(Duration::from_millis(1));
+        }
+        tx.send("quit\n".to_string()).unwrap();
+        assert!(server_thread.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn command_queue_is_bounded() {
+        use std::io::{self, Read};
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        use std::thread::{sleep, spawn};
+        use std::time::Duration;
+        use super::*;
+
+        static RELEASED: AtomicBool = AtomicBool::new(false);
+
+        // An engine that hangs on "ucinewgame" until released.
+        struct StuckEngine;
+
+        impl UciEngine for StuckEngine {
+            fn name() -> &'static str {
+                "stuck"
+            }
+            fn author() -> &'static str {
+                "stuck"
+            }
+            fn options() -> Vec<(&'static str, OptionDescription)> {
+                vec![]
+            }
+            fn new(_: &[(&str, &str)]) -> Self {
+                StuckEngine
+            }
+            fn set_option(&mut self, _: &str, _: &str) {}
+            fn new_game(&mut self) {
+                while !RELEASED.load(Ordering::SeqCst) {
+                    sleep(Duration::from_millis(1));
+                }
+            }
+            fn position(&mut self, _: &str, _: &mut dyn Iterator<Item = &str>) {}
+            fn go(&mut self, _: &GoParams) {}
+            fn stop(&mut self) {}
+            fn ponder_hit(&mut self) {}
+            fn wait_for_reply(&mut self, duration: Duration) -> Option<EngineReply> {
+                sleep(duration);
+                None
+            }
+            fn exit(&mut self) {}
+        }
+
+        // Feeds a long script to the server, counting the bytes read.
+        struct CountingReader {
+            data: Vec<u8>,
+            consumed: Arc<AtomicUsize>,
+        }
+
+        impl Read for CountingReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let start = self.consumed.load(Ordering::SeqCst);
+                let n = buf.len().min(self.data.len() - start);
+                buf[..n].copy_from_slice(&self.data[start..start + n]);
+                self.consumed.store(start + n, Ordering::SeqCst);
+                Ok(n)
+            }
+        }
+
+        const N: usize = 100_000;
+        let mut script = "ucinewgame\n".to_string();
+        for _ in 0..N {
+            script.push_str("isready\n");
+        }
+        script.push_str("quit\n");
+        let total = script.len();
+        let consumed = Arc::new(AtomicUsize::new(0));
+        let server_thread = {
+            let input = CountingReader {
+                data: script.into_bytes(),
+                consumed: consumed.clone(),
+            };
+            spawn(move || {
+                      let mut server = Server::<StuckEngine> { engine: None };
+                      let mut output = vec![];
+                      server.serve_io(input, &mut output).map(|_| output)
+                  })
+        };
+
+        // While the engine is stuck, the reader must not run far
+        // ahead of it.
+        sleep(Duration::from_millis(300));
+        assert!(consumed.load(Ordering::SeqCst) < total / 4);
+
+        RELEASED.store(true, Ordering::SeqCst);
+        let output = server_thread.join().unwrap().ok().unwrap();
+        assert_eq!(consumed.load(Ordering::SeqCst), total);
+        assert_eq!(String::from_utf8_lossy(&output).matches("readyok").count(), N);
+    }
 }
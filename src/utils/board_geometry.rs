@@ -1,12 +1,40 @@
 //! Implements `BoardGeometry`.
 
-use rand::{Rng, thread_rng};
+use rand::Rng;
 use board::*;
 use bitsets::*;
+use super::EngineRng;
 
 
 /// Tables and methods useful for move generation and position
 /// evaluation.
+///
+/// The tables are expensive to compute, so they are computed only
+/// once, and shared by everyone. Use `BoardGeometry::get` to obtain a
+/// reference to them. Custom move generators and evaluators will find
+/// here:
+///
+/// * `squares_at_line`, `squares_between_including`, and
+///   `squares_behind_blocker` -- useful for finding pins, X-ray
+///   attacks, and check-blocking squares;
+///
+/// * `pawn_attacks` -- the squares attacked by a pawn;
+///
+/// * `attacks_from` -- the squares attacked by a king, a queen, a
+///   rook, a bishop or a knight, for any board occupation (magic
+///   bitboards are used for the sliders).
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::*;
+/// # use alcibiades::squares::*;
+/// # use alcibiades::utils::BoardGeometry;
+/// let g = BoardGeometry::get();
+/// assert_eq!(g.attacks_from(ROOK, A1, 1 << A4 | 1 << C1),
+///            1 << A2 | 1 << A3 | 1 << A4 | 1 << B1 | 1 << C1);
+/// assert_eq!(g.pawn_attacks[WHITE][E4], 1 << D5 | 1 << F5);
+/// ```
 pub struct BoardGeometry {
     /// Contains bitboards with all squares lying at the line
     /// determined by two squares.
@@ -378,7 +406,7 @@ unsafe fn init_slider_map(piece: PieceType,
                           from_scratch: bool)
                           -> usize {
     assert!(piece == BISHOP || piece == ROOK);
-    let mut rng = thread_rng();
+    let mut rng = EngineRng::new(piece as u64);
 
     for (sq, entry) in piece_map.iter_mut().enumerate() {
         let attacks: fn(Square, Bitboard) -> Bitboard = if piece == BISHOP {
@@ -640,7 +668,7 @@ mod tests {
     use super::*;
     use super::{bb_bishop_attacks, bb_rook_attacks};
     use squares::*;
-    use bitsets::pop_count;
+    use bitsets::{pop_count, BB_ALL};
 
     #[test]
     fn board_geometry() {
@@ -663,10 +691,24 @@ mod tests {
                    g.squares_at_line[A1][A4]);
     }
 
+    #[test]
+    fn get() {
+        let g = BoardGeometry::get();
+        assert!(::std::ptr::eq(g, BoardGeometry::get()));
+        assert_eq!(g.squares_at_line[B1][G1], 0b11111111);
+        assert_eq!(g.pawn_attacks[BLACK][E5], 1 << D4 | 1 << F4);
+        for square in 0..64 {
+            assert_eq!(pop_count(g.attacks_from(ROOK, square, 0)), 14);
+            assert!(g.attacks_from(BISHOP, square, BB_ALL) != 0);
+            assert!(g.attacks_from(KNIGHT, square, 0) != 0);
+        }
+    }
+
     #[test]
     fn attacks_from() {
-        use rand::{Rng, thread_rng};
-        let mut rng = thread_rng();
+        use rand::Rng;
+        use utils::EngineRng;
+        let mut rng = EngineRng::new(1);
         let g = BoardGeometry::new();
         for piece in KING..PAWN {
             for square in 0..64 {
@@ -4,14 +4,20 @@ mod board_geometry;
 mod zobrist_arrays;
 mod move_stack;
 mod notation;
+mod rng;
+mod polyglot_book;
 
 pub use self::board_geometry::BoardGeometry;
 pub use self::zobrist_arrays::ZobristArrays;
 pub use self::move_stack::MoveStack;
-pub use self::notation::parse_fen;
+pub use self::notation::{parse_fen, parse_castling_rights, placement_fen, move_to_san,
+                         parse_san, NotationError};
+pub use self::rng::EngineRng;
+pub use self::polyglot_book::{PolyglotBook, BookEntry, polyglot_key};
 
 
 use depth::*;
+use moves::Move;
 use move_generator::MoveGenerator;
 
 /// Performs move path enumeration.
@@ -33,12 +39,11 @@ pub fn perft<T: MoveGenerator>(position: &mut T, depth: Depth) -> u64 {
         }
         let mut nodes = 0;
         s.save();
-        p.generate_all(s);
+        p.generate_legal(s);
         while let Some(m) = s.pop() {
-            if p.do_move(m).is_some() {
-                nodes += pft(s, p, d - 1);
-                p.undo_move(m);
-            }
+            p.do_move_unchecked(m);
+            nodes += pft(s, p, d - 1);
+            p.undo_move(m);
         }
         s.restore();
         nodes
@@ -47,3 +52,25 @@ pub fn perft<T: MoveGenerator>(position: &mut T, depth: Depth) -> u64 {
     let mut s = MoveStack::new();
     pft(&mut s, position, depth)
 }
+
+
+/// Performs move path enumeration, and returns the number of leaf
+/// nodes for each legal move in the root position.
+///
+/// This is useful when looking for the move for which `perft` gives a
+/// wrong result. The sum of the returned counts equals `perft(position,
+/// depth)`. When `depth` is zero, an empty vector is returned.
+pub fn perft_divide<T: MoveGenerator>(position: &mut T, depth: Depth) -> Vec<(Move, u64)> {
+    let mut result = vec![];
+    if depth <= 0 {
+        return result;
+    }
+    let mut s = MoveStack::new();
+    position.generate_legal(&mut s);
+    for m in s.list().iter() {
+        position.do_move_unchecked(*m);
+        result.push((*m, perft(position, depth - 1)));
+        position.undo_move(*m);
+    }
+    result
+}
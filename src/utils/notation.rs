@@ -1,9 +1,14 @@
-//! Implements Forsyth–Edwards Notation parsing.
+//! Implements Forsyth–Edwards Notation parsing, and Standard
+//! Algebraic Notation.
 
+use std::fmt;
 use regex::Regex;
 use board::*;
 use files::*;
 use ranks::*;
+use moves::*;
+use move_generator::MoveGenerator;
+use utils::MoveStack;
 
 
 /// Parses Forsyth–Edwards Notation (FEN).
@@ -33,6 +38,11 @@ use ranks::*;
 ///    "-". Otherwise, this has one or more letters: "K" (White can
 ///    castle kingside), "Q" (White can castle queenside), "k" (Black
 ///    can castle kingside), and/or "q" (Black can castle queenside).
+///    The Shredder-FEN notation, which uses the file letters of the
+///    castling rooks ("A"-"H" for White, "a"-"h" for Black), is
+///    accepted too. Since only standard castling is supported, the
+///    king must stand on file E, and the rooks on files A or H (see
+///    `parse_castling_rights`).
 ///
 /// 4. En-passant target square (in algebraic notation). If there's no
 ///    en-passant target square, this is "-". If a pawn has just made
@@ -54,7 +64,7 @@ pub fn parse_fen(s: &str) -> Result<(Board, u8, u16), IllegalBoard> {
     if fields.len() == 6 {
         let pieces = parse_fen_piece_placement(fields[0])?;
         let to_move = parse_fen_active_color(fields[1])?;
-        let castling_rights = parse_fen_castling_rights(fields[2])?;
+        let castling_rights = parse_castling_rights(fields[2], &pieces)?;
         let enpassant_file = if let Some(x) = parse_fen_enpassant_square(fields[3])? {
             match to_move {
                 WHITE if Board::rank(x) == RANK_6 => Board::file(x),
@@ -82,6 +92,55 @@ pub fn parse_fen(s: &str) -> Result<(Board, u8, u16), IllegalBoard> {
 }
 
 
+/// Returns the piece placement field of the Forsyth–Edwards Notation
+/// (FEN) for a board.
+///
+/// This is the first of the six FEN fields (see `parse_fen`). It is
+/// useful when only the board diagram is needed, without the state
+/// fields.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::*;
+/// # use alcibiades::utils::placement_fen;
+/// let b = Board::from_fen("8/8/8/8/8/8/4P3/4K2k w - - 0 1").ok().unwrap();
+/// assert_eq!(placement_fen(&b), "8/8/8/8/8/8/4P3/4K2k");
+/// ```
+pub fn placement_fen(board: &Board) -> String {
+    let mut s = String::with_capacity(64);
+    for rank in (0..8).rev() {
+        let mut empty_squares = 0;
+        for file in 0..8 {
+            let bb = 1 << Board::square(file, rank);
+            if bb & board.occupied == 0 {
+                empty_squares += 1;
+                continue;
+            }
+            if empty_squares > 0 {
+                s.push((b'0' + empty_squares) as char);
+                empty_squares = 0;
+            }
+            let piece = (KING..PIECE_NONE)
+                .find(|&p| bb & board.pieces.piece_type[p] != 0)
+                .unwrap();
+            if bb & board.pieces.color[WHITE] != 0 {
+                s.push(PIECE_LETTERS[piece]);
+            } else {
+                s.push(PIECE_LETTERS[piece].to_ascii_lowercase());
+            }
+        }
+        if empty_squares > 0 {
+            s.push((b'0' + empty_squares) as char);
+        }
+        if rank > 0 {
+            s.push('/');
+        }
+    }
+    s
+}
+
+
 /// Parses square's algebraic notation (lowercase only).
 pub fn parse_square(s: &str) -> Result<Square, IllegalBoard> {
     lazy_static! {
@@ -180,22 +239,163 @@ fn parse_fen_active_color(s: &str) -> Result<Color, IllegalBoard> {
 }
 
 
-fn parse_fen_castling_rights(s: &str) -> Result<CastlingRights, IllegalBoard> {
-    let mut rights = CastlingRights::new(0);
-    if s != "-" {
-        for c in s.chars() {
-            let (color, side) = match c {
-                'K' => (WHITE, KINGSIDE),
-                'Q' => (WHITE, QUEENSIDE),
-                'k' => (BLACK, KINGSIDE),
-                'q' => (BLACK, QUEENSIDE),
-                _ => return Err(IllegalBoard),
-            };
-            if !rights.grant(color, side) {
-                return Err(IllegalBoard);
+/// Describes why a position given in some notation is not accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotationError {
+    /// The character does not designate a castling right.
+    InvalidCastlingRight(char),
+
+    /// The castling right is given more than once.
+    DuplicatedCastlingRight(char),
+
+    /// The castling right is given, but the king is not on its
+    /// initial square (E1 for White, E8 for Black). Chess960
+    /// positions are not supported.
+    KingNotOnInitialSquare(char),
+
+    /// The castling right is given, but there is no rook of the same
+    /// color on the square that the right refers to.
+    MissingCastlingRook(char),
+
+    /// The castling right refers to a rook that is not on file A or
+    /// H. Chess960 positions are not supported.
+    UnsupportedCastlingRook(char),
+
+    /// The square holds an invalid color or piece type.
+    InvalidPiece(Square),
+
+    /// The side to move is neither White nor Black.
+    InvalidSideToMove,
+
+    /// The player does not have exactly one king.
+    WrongKingCount(Color),
+
+    /// The player has more than 16 pieces, or more than 8 pawns.
+    TooManyPieces(Color),
+
+    /// There is a pawn on the first or the last rank.
+    PawnOnBackRank(Square),
+
+    /// The en-passant file is out of range, or no pawn can have just
+    /// made a double push on it.
+    InvalidEnpassantFile(usize),
+
+    /// The king of the side that is not to move is in check.
+    OpponentInCheck,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const COLOR_NAMES: [&str; 2] = ["White", "Black"];
+        match *self {
+            NotationError::InvalidCastlingRight(c) => {
+                write!(f, "'{}' is not a castling right", c)
+            }
+            NotationError::DuplicatedCastlingRight(c) => {
+                write!(f, "castling right '{}' is given more than once", c)
+            }
+            NotationError::KingNotOnInitialSquare(c) => {
+                write!(f, "castling right '{}' is given, but the king is not on file E", c)
+            }
+            NotationError::MissingCastlingRook(c) => {
+                write!(f, "castling right '{}' is given, but there is no rook for it", c)
+            }
+            NotationError::UnsupportedCastlingRook(c) => {
+                write!(f, "castling right '{}' refers to a rook not on file A or H", c)
+            }
+            NotationError::InvalidPiece(square) => {
+                write!(f, "invalid piece on square {}", square)
+            }
+            NotationError::InvalidSideToMove => write!(f, "invalid side to move"),
+            NotationError::WrongKingCount(color) => {
+                write!(f, "{} does not have exactly one king", COLOR_NAMES[color])
+            }
+            NotationError::TooManyPieces(color) => {
+                write!(f, "{} has too many pieces", COLOR_NAMES[color])
+            }
+            NotationError::PawnOnBackRank(square) => {
+                write!(f, "pawn on the first or the last rank, on square {}", square)
+            }
+            NotationError::InvalidEnpassantFile(file) => {
+                write!(f, "invalid en-passant file {}", file)
+            }
+            NotationError::OpponentInCheck => {
+                write!(f, "the king of the side not to move is in check")
             }
         }
     }
+}
+
+impl From<NotationError> for IllegalBoard {
+    fn from(_: NotationError) -> IllegalBoard {
+        IllegalBoard
+    }
+}
+
+
+/// Parses the castling availability field of the Forsyth–Edwards
+/// Notation (FEN) for a given piece placement.
+///
+/// Both the classical form ("KQkq") and the Shredder-FEN form
+/// ("HAha"), which uses the file letters of the castling rooks, are
+/// accepted, and can be mixed. Every right must agree with the piece
+/// placement: the king must stand on its initial square, and there
+/// must be a rook on file H (for kingside castling) or file A (for
+/// queenside castling). Because only standard castling is supported,
+/// a Shredder-FEN letter always refers to a single rook, and a
+/// classical letter always refers to the rook in the corner.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::*;
+/// # use alcibiades::utils::*;
+/// let (board, _, _) = parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").ok().unwrap();
+/// let rights = parse_castling_rights("HAha", &board.pieces).ok().unwrap();
+/// assert_eq!(rights.value(), board.castling_rights.value());
+/// assert_eq!(parse_castling_rights("KQkq", &board.pieces).ok().unwrap().value(),
+///            rights.value());
+///
+/// let (board, _, _) = parse_fen("r2k3r/8/8/8/8/8/8/R3K2R w - - 0 1").ok().unwrap();
+/// assert_eq!(parse_castling_rights("Kq", &board.pieces).err(),
+///            Some(NotationError::KingNotOnInitialSquare('q')));
+/// ```
+pub fn parse_castling_rights(s: &str,
+                             pieces: &PiecesPlacement)
+                             -> Result<CastlingRights, NotationError> {
+    let mut rights = CastlingRights::new(0);
+    if s == "-" {
+        return Ok(rights);
+    }
+    for c in s.chars() {
+        let (color, file) = match c {
+            'K' => (WHITE, FILE_H),
+            'Q' => (WHITE, FILE_A),
+            'k' => (BLACK, FILE_H),
+            'q' => (BLACK, FILE_A),
+            'A'..='H' => (WHITE, c as usize - 'A' as usize),
+            'a'..='h' => (BLACK, c as usize - 'a' as usize),
+            _ => return Err(NotationError::InvalidCastlingRight(c)),
+        };
+
+        // Verify that the king and the rook are where they should be.
+        let rank = if color == WHITE { RANK_1 } else { RANK_8 };
+        let ours = pieces.color[color];
+        if ours & pieces.piece_type[KING] & 1 << Board::square(FILE_E, rank) == 0 {
+            return Err(NotationError::KingNotOnInitialSquare(c));
+        }
+        if ours & pieces.piece_type[ROOK] & 1 << Board::square(file, rank) == 0 {
+            return Err(NotationError::MissingCastlingRook(c));
+        }
+        let side = match file {
+            FILE_A => QUEENSIDE,
+            FILE_H => KINGSIDE,
+            _ => return Err(NotationError::UnsupportedCastlingRook(c)),
+        };
+        if !rights.grant(color, side) {
+            return Err(NotationError::DuplicatedCastlingRight(c));
+        }
+    }
     Ok(rights)
 }
 
@@ -209,6 +409,197 @@ fn parse_fen_enpassant_square(s: &str) -> Result<Option<Square>, IllegalBoard> {
 }
 
 
+/// Returns the Standard Algebraic Notation (SAN) for a move.
+///
+/// `m` must be a legal move in `position`. Examples: `Nf3`, `exd5`,
+/// `O-O`, `e8=Q+`, `Qh4#`. When two or more pieces of the same type
+/// can move to the same square, the origin square's file, rank, or
+/// both are added (for example: `Nbd7`, `R1e2`, `Qh4e1`). Null moves
+/// are written as `--`.
+///
+/// # Examples:
+///
+/// ```rust
+/// # use alcibiades::*;
+/// # use alcibiades::utils::move_to_san;
+/// # use alcibiades::stock::{StdMoveGenerator, SimpleEvaluator};
+/// let b = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").ok().unwrap();
+/// let p = StdMoveGenerator::<SimpleEvaluator>::from_board(b).ok().unwrap();
+/// let m = p.try_move_digest(MoveDigest::from_lan("e1g1").unwrap()).unwrap();
+/// assert_eq!(move_to_san(&p, m), "O-O");
+/// ```
+pub fn move_to_san<T: MoveGenerator>(position: &T, m: Move) -> String {
+    if m.is_null() {
+        return "--".to_string();
+    }
+    let orig_square = m.orig_square();
+    let dest_square = m.dest_square();
+    let piece = m.played_piece();
+    let is_capture = m.captured_piece() < PIECE_NONE;
+    let mut position = position.clone();
+    let mut san = String::with_capacity(8);
+
+    if m.move_type() == MOVE_CASTLING {
+        san.push_str(if Board::file(dest_square) == FILE_G {
+                         "O-O"
+                     } else {
+                         "O-O-O"
+                     });
+    } else {
+        if piece == PAWN {
+            if is_capture {
+                san.push(file_char(orig_square));
+            }
+        } else {
+            san.push(PIECE_LETTERS[piece]);
+
+            // Add the minimal necessary disambiguation.
+            let others: Vec<Move> = legal_moves(&mut position)
+                .into_iter()
+                .filter(|x| {
+                            x.played_piece() == piece && x.dest_square() == dest_square &&
+                            x.orig_square() != orig_square
+                        })
+                .collect();
+            if !others.is_empty() {
+                let file = Board::file(orig_square);
+                let rank = Board::rank(orig_square);
+                if others.iter().all(|x| Board::file(x.orig_square()) != file) {
+                    san.push(file_char(orig_square));
+                } else if others.iter().all(|x| Board::rank(x.orig_square()) != rank) {
+                    san.push(rank_char(orig_square));
+                } else {
+                    san.push(file_char(orig_square));
+                    san.push(rank_char(orig_square));
+                }
+            }
+        }
+        if is_capture {
+            san.push('x');
+        }
+        san.push(file_char(dest_square));
+        san.push(rank_char(dest_square));
+        if m.move_type() == MOVE_PROMOTION {
+            san.push('=');
+            san.push(PIECE_LETTERS[Move::piece_from_aux_data(m.aux_data())]);
+        }
+    }
+
+    // Add a check or a checkmate sign.
+    if position.gives_check(m) && position.do_move(m).is_some() {
+        san.push(if legal_moves(&mut position).is_empty() {
+                     '#'
+                 } else {
+                     '+'
+                 });
+        position.undo_move(m);
+    }
+    san
+}
+
+
+/// Parses a move in Standard Algebraic Notation (SAN).
+///
+/// Returns `None` if the notation is invalid, or if it does not match
+/// exactly one legal move in `position`. (See
+/// `MoveGenerator::parse_san`.)
+pub fn parse_san<T: MoveGenerator>(position: &T, s: &str) -> Option<Move> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(concat!(r"^(?:(O-O-O|0-0-0)|(O-O|0-0)|",
+                                                  r"([KQRBN])?([a-h])?([1-8])?(x)?([a-h][1-8])",
+                                                  r"(?:=?([QRBN]))?(?:\s*e\.p\.)?)[+#]?[!?]*$"))
+                .unwrap();
+    }
+    let captures = RE.captures(s.trim())?;
+    let castling_dest_file = if captures.get(1).is_some() {
+        Some(FILE_C)
+    } else if captures.get(2).is_some() {
+        Some(FILE_G)
+    } else {
+        None
+    };
+    let piece = captures.get(3).map_or(PAWN, |x| piece_from_letter(x.as_str()));
+    let file = captures.get(4).map(|x| (x.as_str().as_bytes()[0] - b'a') as usize);
+    let rank = captures.get(5).map(|x| (x.as_str().as_bytes()[0] - b'1') as usize);
+    let is_capture = captures.get(6).is_some();
+    let dest_square = captures.get(7).and_then(|x| parse_square(x.as_str()).ok());
+    let promoted_piece = captures.get(8).map(|x| piece_from_letter(x.as_str()));
+
+    let mut position = position.clone();
+    let candidates: Vec<Move> = legal_moves(&mut position)
+        .into_iter()
+        .filter(|m| {
+            let orig_square = m.orig_square();
+            if let Some(f) = castling_dest_file {
+                return m.move_type() == MOVE_CASTLING && Board::file(m.dest_square()) == f;
+            }
+            m.move_type() != MOVE_CASTLING && m.played_piece() == piece &&
+            dest_square == Some(m.dest_square()) &&
+            file.is_none_or(|f| Board::file(orig_square) == f) &&
+            rank.is_none_or(|r| Board::rank(orig_square) == r) &&
+            (!is_capture || m.captured_piece() < PIECE_NONE) &&
+            (piece != PAWN || is_capture ||
+             Board::file(orig_square) == Board::file(m.dest_square())) &&
+            match promoted_piece {
+                Some(p) => {
+                    m.move_type() == MOVE_PROMOTION && Move::piece_from_aux_data(m.aux_data()) == p
+                }
+                None => m.move_type() != MOVE_PROMOTION,
+            }
+        })
+        .collect();
+    if candidates.len() == 1 {
+        Some(candidates[0])
+    } else {
+        None
+    }
+}
+
+
+/// The SAN letters for the pieces, indexed by piece type.
+const PIECE_LETTERS: [char; 6] = ['K', 'Q', 'R', 'B', 'N', 'P'];
+
+
+/// A helper function. It returns the piece type for a SAN letter.
+fn piece_from_letter(letter: &str) -> PieceType {
+    match letter {
+        "K" => KING,
+        "Q" => QUEEN,
+        "R" => ROOK,
+        "B" => BISHOP,
+        "N" => KNIGHT,
+        _ => PAWN,
+    }
+}
+
+
+/// A helper function. It returns the file letter of a square.
+fn file_char(square: Square) -> char {
+    (b'a' + Board::file(square) as u8) as char
+}
+
+
+/// A helper function. It returns the rank digit of a square.
+fn rank_char(square: Square) -> char {
+    (b'1' + Board::rank(square) as u8) as char
+}
+
+
+/// A helper function. It returns all legal moves in the position.
+fn legal_moves<T: MoveGenerator>(position: &mut T) -> Vec<Move> {
+    let mut moves = MoveStack::new();
+    let mut legal_moves = vec![];
+    position.generate_all(&mut moves);
+    while let Some(m) = moves.pop() {
+        if position.do_move(m).is_some() {
+            legal_moves.push(m);
+            position.undo_move(m);
+        }
+    }
+    legal_moves
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -238,4 +629,171 @@ mod tests {
         assert!(parse_fen("8/8/8/6k1/7P/8/8/6RK b - h3 0 1").is_ok());
         assert!(parse_fen("8/8/8/6k1/7P/8/8/7K b - h3 0 0").is_err());
     }
+
+    #[test]
+    fn placement_fen_string() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let (b, _, _) = parse_fen(fen).ok().unwrap();
+        assert_eq!(placement_fen(&b), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR");
+        for fen in ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+                    "k7/8/8/8/8/8/8/7K w - - 0 1"] {
+            let (b, _, _) = parse_fen(fen).ok().unwrap();
+            assert_eq!(placement_fen(&b), fen.split_whitespace().next().unwrap());
+        }
+    }
+
+    #[test]
+    fn parse_shredder_fen() {
+        let rights = |fen| parse_fen(fen).ok().unwrap().0.castling_rights.value();
+        let kqkq = rights("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(kqkq, 0b1111);
+        assert_eq!(rights("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1"), kqkq);
+        assert_eq!(rights("r3k2r/8/8/8/8/8/8/R3K2R w AHah - 0 1"), kqkq);
+        assert_eq!(rights("r3k2r/8/8/8/8/8/8/R3K2R w Kah - 0 1"), kqkq & !0b0001);
+        let b = parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w Ah - 0 1").ok().unwrap().0;
+        assert!(b.castling_rights.can_castle(WHITE, QUEENSIDE));
+        assert!(!b.castling_rights.can_castle(WHITE, KINGSIDE));
+        assert!(b.castling_rights.can_castle(BLACK, KINGSIDE));
+        assert!(!b.castling_rights.can_castle(BLACK, QUEENSIDE));
+
+        // Inconsistent with the rook placement.
+        assert!(parse_fen("r3k3/8/8/8/8/8/8/R3K2R w h - 0 1").is_err());
+        assert!(parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w B - 0 1").is_err());
+        assert!(parse_fen("r3k2r/8/8/8/8/8/8/R3K1R1 w G - 0 1").is_err());
+        assert!(parse_fen("r3k2r/8/8/8/8/8/8/R2K3R w H - 0 1").is_err());
+
+        // The same right given twice.
+        assert!(parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w KH - 0 1").is_err());
+        assert!(parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w qa - 0 1").is_err());
+    }
+
+    #[test]
+    fn classical_and_shredder_fen() {
+        let classical = parse_fen("r3k2r/pppq1ppp/8/8/8/8/PPPQ1PPP/R3K2R b KQkq - 3 12")
+            .ok()
+            .unwrap();
+        let shredder = parse_fen("r3k2r/pppq1ppp/8/8/8/8/PPPQ1PPP/R3K2R b HAha - 3 12")
+            .ok()
+            .unwrap();
+        assert_eq!(classical.0.pieces.color, shredder.0.pieces.color);
+        assert_eq!(classical.0.pieces.piece_type, shredder.0.pieces.piece_type);
+        assert_eq!(classical.0.to_move, shredder.0.to_move);
+        assert_eq!(classical.0.castling_rights.value(),
+                   shredder.0.castling_rights.value());
+        assert_eq!(classical.0.enpassant_file, shredder.0.enpassant_file);
+        assert_eq!((classical.1, classical.2), (shredder.1, shredder.2));
+    }
+
+    #[test]
+    fn inconsistent_castling_rights() {
+        let error = |placement: &str, rights: &str| {
+            let fen = format!("{} w - - 0 1", placement);
+            let board = parse_fen(&fen).ok().unwrap().0;
+            parse_castling_rights(rights, &board.pieces).err()
+        };
+        let standard = "r3k2r/8/8/8/8/8/8/R3K2R";
+        assert_eq!(error(standard, "KQkq"), None);
+        assert_eq!(error(standard, "-"), None);
+        assert_eq!(error(standard, "KQx"), Some(NotationError::InvalidCastlingRight('x')));
+        assert_eq!(error(standard, "Kk"), None);
+        assert_eq!(error(standard, "KkH"),
+                   Some(NotationError::DuplicatedCastlingRight('H')));
+        assert_eq!(error("r3k2r/8/8/8/8/8/8/R4RK1", "Qkq"),
+                   Some(NotationError::KingNotOnInitialSquare('Q')));
+        assert_eq!(error("r3k3/8/8/8/8/8/8/R3K2R", "KQk"),
+                   Some(NotationError::MissingCastlingRook('k')));
+        assert_eq!(error("r3k3/8/8/8/8/8/8/R3K2R", "KQh"),
+                   Some(NotationError::MissingCastlingRook('h')));
+
+        // Chess960 castling is not supported.
+        assert_eq!(error("1r2k1r1/8/8/8/8/8/8/1R2K1R1", "GBgb"),
+                   Some(NotationError::UnsupportedCastlingRook('G')));
+        assert_eq!(error("rk4r1/8/8/8/8/8/8/RK4R1", "GAga"),
+                   Some(NotationError::KingNotOnInitialSquare('G')));
+
+        // Classical rights are checked too.
+        assert!(parse_fen("r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1").is_err());
+    }
+
+    #[test]
+    fn san() {
+        use stock::{StdMoveGenerator, SimpleEvaluator};
+        type P = StdMoveGenerator<SimpleEvaluator>;
+        let san = |fen: &str, notation: &str| {
+            let p = P::from_board(Board::from_fen(fen).ok().unwrap()).ok().unwrap();
+            let m = p.try_move_digest(MoveDigest::from_lan(notation).unwrap()).unwrap();
+            move_to_san(&p, m)
+        };
+        let fen = "r3k2r/1P6/8/3pP3/8/1N3N2/8/R3K2R w KQkq d6 0 1";
+        assert_eq!(san(fen, "e1g1"), "O-O");
+        assert_eq!(san(fen, "e1c1"), "O-O-O");
+        assert_eq!(san(fen, "e5d6"), "exd6");
+        assert_eq!(san(fen, "e5e6"), "e6");
+        assert_eq!(san(fen, "b7a8q"), "bxa8=Q+");
+        assert_eq!(san(fen, "b7b8n"), "b8=N");
+        assert_eq!(san(fen, "a1a8"), "Rxa8+");
+        assert_eq!(san(fen, "h1h8"), "Rxh8+");
+        assert_eq!(san(fen, "e1e2"), "Ke2");
+
+        // Two knights that can reach the same square.
+        assert_eq!(san(fen, "b3d4"), "Nbd4");
+        assert_eq!(san(fen, "f3d4"), "Nfd4");
+        assert_eq!(san(fen, "f3g5"), "Ng5");
+        let fen = "4k3/8/8/8/8/1N6/8/1N2K3 w - - 0 1";
+        assert_eq!(san(fen, "b3d2"), "N3d2");
+        assert_eq!(san(fen, "b1d2"), "N1d2");
+        assert_eq!(san(fen, "b1c3"), "Nc3");
+        let fen = "4k3/8/8/8/8/2N3N1/8/2N1K3 w - - 0 1";
+        assert_eq!(san(fen, "c3e2"), "Nc3e2");
+        assert_eq!(san(fen, "c1e2"), "N1e2");
+        assert_eq!(san(fen, "g3e2"), "Nge2");
+        assert_eq!(san(fen, "c3d5"), "Nd5");
+
+        // Castling with the rook giving check.
+        assert_eq!(san("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"), "O-O+");
+        assert_eq!(san("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1"), "O-O-O+");
+
+        // Checkmate.
+        assert_eq!(san("7k/6pp/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn parse_san() {
+        use stock::{StdMoveGenerator, SimpleEvaluator};
+        type P = StdMoveGenerator<SimpleEvaluator>;
+        let parse = |fen: &str, san: &str| {
+            let p = P::from_board(Board::from_fen(fen).ok().unwrap()).ok().unwrap();
+            p.parse_san(san).map(|m| m.notation())
+        };
+        let fen = "r3k2r/1P6/8/3pP3/8/1N3N2/8/R3K2R w KQkq d6 0 1";
+        assert_eq!(parse(fen, "O-O"), Some("e1g1".to_string()));
+        assert_eq!(parse(fen, "O-O-O+"), Some("e1c1".to_string()));
+        assert_eq!(parse(fen, "0-0-0"), Some("e1c1".to_string()));
+        assert_eq!(parse(fen, "exd6"), Some("e5d6".to_string()));
+        assert_eq!(parse(fen, "exd6e.p."), Some("e5d6".to_string()));
+        assert_eq!(parse(fen, "exd6 e.p."), Some("e5d6".to_string()));
+        assert_eq!(parse(fen, "e6"), Some("e5e6".to_string()));
+        assert_eq!(parse(fen, "d6"), None);
+        assert_eq!(parse(fen, "bxa8=Q+"), Some("b7a8q".to_string()));
+        assert_eq!(parse(fen, "bxa8N"), Some("b7a8n".to_string()));
+        assert_eq!(parse(fen, "b8"), None);
+        assert_eq!(parse(fen, "b8=R!?"), Some("b7b8r".to_string()));
+        assert_eq!(parse(fen, "Rxa8"), Some("a1a8".to_string()));
+        assert_eq!(parse(fen, "Rxa7"), None);
+        assert_eq!(parse(fen, "Ng5"), Some("f3g5".to_string()));
+        assert_eq!(parse(fen, "Nbd4"), Some("b3d4".to_string()));
+        assert_eq!(parse(fen, "N3d4"), None);
+        assert_eq!(parse(fen, "Nd4"), None);
+        assert_eq!(parse(fen, "Nd5"), None);
+        assert_eq!(parse(fen, "e2e4"), None);
+        assert_eq!(parse(fen, "Ke2"), Some("e1e2".to_string()));
+
+        // All legal moves survive a round trip through SAN.
+        let p = P::from_board(Board::from_fen(fen).ok().unwrap()).ok().unwrap();
+        let mut q = p.clone();
+        for m in legal_moves(&mut q) {
+            assert_eq!(p.parse_san(&move_to_san(&p, m)), Some(m));
+        }
+    }
 }
@@ -0,0 +1,67 @@
+//! Implements `EngineRng`.
+
+use rand::{Rng, SeedableRng};
+use rand::isaac::Isaac64Rng;
+
+
+/// A seedable source of random numbers.
+///
+/// All randomness in the engine should come from `EngineRng`
+/// instances, so that engine-vs-engine matches and tests are
+/// reproducible. Two instances created with the same seed will
+/// produce the same sequence of random numbers. `EngineRng`
+/// implements `rand::Rng`.
+pub struct EngineRng(Isaac64Rng);
+
+impl EngineRng {
+    /// Creates a new instance from a given seed.
+    pub fn new(seed: u64) -> EngineRng {
+        let seed: &[_] = &[seed];
+        EngineRng(SeedableRng::from_seed(seed))
+    }
+
+    /// Creates a new instance, seeded by the "Random Seed"
+    /// configuration option.
+    ///
+    /// If the option is not set, `0` is used as a seed.
+    pub fn from_option() -> EngineRng {
+        let seed = ::CONFIGURATION
+            .read()
+            .unwrap()
+            .get("Random Seed")
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
+        EngineRng::new(seed)
+    }
+}
+
+impl Rng for EngineRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn seeding() {
+        let mut a = EngineRng::new(7);
+        let mut b = EngineRng::new(7);
+        let mut c = EngineRng::new(8);
+        let x: Vec<u64> = (0..10).map(|_| a.gen()).collect();
+        let y: Vec<u64> = (0..10).map(|_| b.gen()).collect();
+        let z: Vec<u64> = (0..10).map(|_| c.gen()).collect();
+        assert_eq!(x, y);
+        assert!(x != z);
+    }
+}
@@ -1,5 +1,8 @@
 //! Implements `ZobristArrays`.
 
+use board::*;
+use bitsets::*;
+use moves::*;
 
 /// Tables for calculating Zobrist hashes.
 ///
@@ -92,4 +95,46 @@ impl ZobristArrays {
             ARRAYS.as_ref().unwrap()
         }
     }
+
+    /// Calculates a Zobrist hash value for the pawn structure on a
+    /// given board.
+    ///
+    /// Only the placement of the pawns is taken into account.
+    pub fn pawn_hash(&self, board: &Board) -> u64 {
+        let mut hash = 0;
+        for color in 0..2 {
+            let mut bb = board.pieces.color[color] & board.pieces.piece_type[PAWN];
+            while bb != 0 {
+                hash ^= self.pieces[color][PAWN][bsf_reset(&mut bb)];
+            }
+        }
+        hash
+    }
+
+    /// Returns the value with which the pawn hash (see `pawn_hash`)
+    /// should be XOR-ed when the move `m` is played by `us`.
+    ///
+    /// Taking the move back changes the pawn hash by the same value.
+    #[inline]
+    pub fn pawn_hash_delta(&self, us: Color, m: Move) -> u64 {
+        let mut h = 0;
+        let dest_square = m.dest_square();
+        if m.played_piece() == PAWN {
+            h ^= self.pieces[us][PAWN][m.orig_square()];
+            if m.move_type() != MOVE_PROMOTION {
+                h ^= self.pieces[us][PAWN][dest_square];
+            }
+        }
+        if m.captured_piece() == PAWN {
+            let captured_pawn_square = if m.move_type() != MOVE_ENPASSANT {
+                dest_square
+            } else if us == WHITE {
+                dest_square - 8
+            } else {
+                dest_square + 8
+            };
+            h ^= self.pieces[opposite(us)][PAWN][captured_pawn_square];
+        }
+        h
+    }
 }
@@ -1,5 +1,7 @@
 //! Defines the `Value` type and its related constants.
 
+use board::{Color, WHITE};
+
 
 /// Evaluation value in centipawns.
 ///
@@ -45,3 +47,64 @@ pub const VALUE_MAX: Value = ::std::i16::MAX;
 pub const VALUE_MIN: Value = -VALUE_MAX;
 pub const VALUE_EVAL_MAX: Value = 29999;
 pub const VALUE_EVAL_MIN: Value = -VALUE_EVAL_MAX;
+
+// The whole search relies on these relations. In particular, values
+// must be safe to negate, and `VALUE_UNKNOWN` must not be mistaken
+// for a real value.
+const _: () = assert!(VALUE_MIN < VALUE_EVAL_MIN && VALUE_EVAL_MIN < 0);
+const _: () = assert!(0 < VALUE_EVAL_MAX && VALUE_EVAL_MAX < VALUE_MAX);
+const _: () = assert!(VALUE_MIN == -VALUE_MAX && VALUE_EVAL_MIN == -VALUE_EVAL_MAX);
+const _: () = assert!(VALUE_UNKNOWN < VALUE_MIN);
+
+
+/// Converts a value given from white's point of view to the point of
+/// view of `color`.
+///
+/// Search and evaluation values are always relative to the side to
+/// move (see `Value`). This function should be used whenever a value
+/// has to be converted from or to an absolute (white's) point of
+/// view. Applying it twice with the same color gives the original
+/// value. `VALUE_UNKNOWN` is never changed.
+#[inline]
+pub fn relative_to(value: Value, color: Color) -> Value {
+    if color == WHITE || value == VALUE_UNKNOWN {
+        value
+    } else {
+        -value
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::*;
+
+    #[test]
+    fn value_bounds() {
+        use depth::DEPTH_MAX;
+
+        // There is enough room for the inevitable checkmates.
+        assert!(VALUE_MAX - 2 * DEPTH_MAX as Value > VALUE_EVAL_MAX);
+        assert!(VALUE_EVAL_MIN > VALUE_MIN + 2 * DEPTH_MAX as Value);
+
+        // `VALUE_UNKNOWN` can not be produced by negating a value.
+        for &v in [VALUE_MIN, VALUE_EVAL_MIN, 0, VALUE_EVAL_MAX, VALUE_MAX].iter() {
+            assert!(-v != VALUE_UNKNOWN);
+        }
+        assert_eq!(VALUE_UNKNOWN.checked_neg(), None);
+    }
+
+    #[test]
+    fn relative_to() {
+        assert_eq!(super::relative_to(100, WHITE), 100);
+        assert_eq!(super::relative_to(100, BLACK), -100);
+        assert_eq!(super::relative_to(VALUE_UNKNOWN, BLACK), VALUE_UNKNOWN);
+        for &v in [VALUE_MIN, -1, 0, 25, VALUE_EVAL_MAX, VALUE_MAX].iter() {
+            for &c in [WHITE, BLACK].iter() {
+                assert_eq!(super::relative_to(super::relative_to(v, c), c), v);
+            }
+            assert_eq!(super::relative_to(v, BLACK), -super::relative_to(v, WHITE));
+        }
+    }
+}